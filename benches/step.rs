@@ -0,0 +1,51 @@
+//! Compares the naive grid stepper with the bit-packed one on a dense soup.
+//!
+//! Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use rs_game_of_life::{BitGrid, Engine, Grid, Rule};
+
+const SIZE: usize = 1024;
+const GENERATIONS: u32 = 100;
+
+fn main() {
+    let naive = bench(Grid::new(SIZE, SIZE));
+    let packed = bench(BitGrid::new(SIZE, SIZE, Rule::CONWAY));
+    report("grid", naive);
+    report("bitgrid", packed);
+    println!(
+        "bitgrid is {:.1}x faster",
+        naive.as_secs_f64() / packed.as_secs_f64()
+    );
+}
+
+/// Time taken to run `GENERATIONS` steps from the same soup.
+fn bench(mut engine: impl Engine) -> Duration {
+    let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+    for y in 0..SIZE as i64 {
+        for x in 0..SIZE as i64 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            engine.set_cell(x, y, seed.is_multiple_of(3) as u8);
+        }
+    }
+    let start = Instant::now();
+    for _ in 0..GENERATIONS {
+        engine.step();
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:>8}: {} generations of {}x{} in {:?} ({:.0} gen/s)",
+        name,
+        GENERATIONS,
+        SIZE,
+        SIZE,
+        elapsed,
+        f64::from(GENERATIONS) / elapsed.as_secs_f64()
+    );
+}
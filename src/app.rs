@@ -0,0 +1,1091 @@
+use std::{
+    any::Any,
+    fmt, fs, io, panic,
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use rs_game_of_life::{
+    census,
+    format::{macrocell, rle},
+    period::Detector,
+    rule::Lattice,
+    volume::Axis,
+    BitGrid, Blocks, Census, Continuous, Engine, Grid, Hashlife, Pattern, Random, Rule, SpaceTime,
+    Sparse, Topology, Update, Volume,
+};
+
+const MIN_TICK: Duration = Duration::from_millis(10);
+const MAX_TICK: Duration = Duration::from_millis(2000);
+
+/// Largest step exponent the viewer lets the user pick.
+const MAX_STEP_EXPONENT: u32 = 40;
+
+/// Largest step exponent on engines that compute every generation in turn,
+/// so that a tick stays short enough for the viewer to keep responding.
+const MAX_PLAIN_STEP_EXPONENT: u32 = 4;
+
+/// Layers of the volumes the viewer creates for 3D rules.
+const VOLUME_DEPTH: usize = 32;
+
+/// The simulation backends the viewer can run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineKind {
+    Grid,
+    BitGrid,
+    Hashlife,
+    Sparse,
+    SpaceTime,
+    Blocks,
+    Volume,
+    Continuous,
+}
+
+impl EngineKind {
+    /// The backend to run `rule` on when none is asked for.
+    pub fn default_for(rule: &Rule) -> Self {
+        if rule.wolfram().is_some() {
+            EngineKind::SpaceTime
+        } else if rule.margolus().is_some() {
+            EngineKind::Blocks
+        } else if rule.cubic().is_some() {
+            EngineKind::Volume
+        } else if rule.lenia().is_some() {
+            EngineKind::Continuous
+        } else {
+            EngineKind::Grid
+        }
+    }
+
+    /// Checks that the backend can apply rules with `update`. Only the grid
+    /// goes beyond synchronous updates.
+    pub fn supports_update(self, update: Update) -> Result<(), String> {
+        match self {
+            EngineKind::Grid => Ok(()),
+            _ if update.is_synchronous() => Ok(()),
+            _ => Err(format!(
+                "{} only updates synchronously; use the grid engine for {}",
+                self, update
+            )),
+        }
+    }
+
+    /// Checks that the backend can run `rule`.
+    pub fn supports(self, rule: &Rule) -> Result<(), String> {
+        match self {
+            EngineKind::SpaceTime if rule.wolfram().is_none() => Err(format!(
+                "{} only runs one-dimensional rules such as W30, not {}",
+                self, rule
+            )),
+            EngineKind::SpaceTime => match rule.topology() {
+                None if rule.is_birth(0) => Err(format!(
+                    "{} needs a bounded line to run {}, such as {}:T100",
+                    self, rule, rule
+                )),
+                None => Ok(()),
+                Some(Topology::Plane { width, .. })
+                | Some(Topology::Torus {
+                    width, shift: None, ..
+                }) if width > 0 => Ok(()),
+                Some(topology) => Err(format!("{} cannot run on {}", self, topology)),
+            },
+            _ if rule.wolfram().is_some() => Err(format!(
+                "{} cannot run one-dimensional rules such as {}",
+                self, rule
+            )),
+            EngineKind::Blocks => match (rule.margolus(), rule.topology()) {
+                (None, _) => Err(format!(
+                    "{} only runs block rules such as Critters, not {}",
+                    self, rule
+                )),
+                (Some(margolus), None) => match (margolus.next_block(0), margolus.next_block(15)) {
+                    (0, _) | (15, 0) => Ok(()),
+                    _ => Err(format!(
+                        "{} needs a torus to run {}, such as {}:T64,64",
+                        self, rule, rule
+                    )),
+                },
+                (
+                    Some(_),
+                    Some(Topology::Torus {
+                        width,
+                        height,
+                        shift: None,
+                    }),
+                ) if width > 0 && height > 0 && width % 2 == 0 && height % 2 == 0 => Ok(()),
+                (Some(_), Some(topology)) => Err(format!(
+                    "{} only runs on the plane or a torus of even size, not {}",
+                    self, topology
+                )),
+            },
+            _ if rule.margolus().is_some() => {
+                Err(format!("{} cannot run block rules such as {}", self, rule))
+            }
+            EngineKind::Volume if rule.cubic().is_none() => Err(format!(
+                "{} only runs 3D rules such as B5/S45/3D, not {}",
+                self, rule
+            )),
+            EngineKind::Volume => match rule.topology() {
+                None | Some(Topology::Torus { shift: None, .. }) => Ok(()),
+                Some(topology) => Err(format!("{} cannot run on {}", self, topology)),
+            },
+            _ if rule.cubic().is_some() => {
+                Err(format!("{} cannot run 3D rules such as {}", self, rule))
+            }
+            EngineKind::Continuous if rule.lenia().is_none() => Err(format!(
+                "{} only runs continuous rules such as R=13;T=10;b=1;m=0.15;s=0.015, not {}",
+                self, rule
+            )),
+            EngineKind::Continuous => match rule.topology() {
+                None | Some(Topology::Torus { shift: None, .. }) => Ok(()),
+                Some(topology) => Err(format!("{} cannot run on {}", self, topology)),
+            },
+            _ if rule.lenia().is_some() => Err(format!(
+                "{} cannot run continuous rules such as {}",
+                self, rule
+            )),
+            EngineKind::Grid => Ok(()),
+            _ if rule.larger_than_life().is_some() => Err(format!(
+                "{} cannot run Larger than Life rules such as {}",
+                self, rule
+            )),
+            _ if rule.lattice() == Lattice::Triangular => Err(format!(
+                "{} cannot run triangular rules such as {}",
+                self, rule
+            )),
+            EngineKind::BitGrid if rule.lattice() != Lattice::Square => Err(format!(
+                "{} only runs rules on square cells, not {}",
+                self, rule
+            )),
+            EngineKind::BitGrid if rule.states() > 2 || !rule.is_totalistic() => Err(format!(
+                "{} only runs two-state totalistic rules, not {}",
+                self, rule
+            )),
+            EngineKind::BitGrid => match rule.topology() {
+                None | Some(Topology::Plane { .. }) => Ok(()),
+                Some(topology) => Err(format!("{} cannot run on {}", self, topology)),
+            },
+            _ if rule.is_birth(0) => Err(format!("{} cannot run B0 rules such as {}", self, rule)),
+            _ if rule.topology().is_some() => Err(format!(
+                "{} only runs on the unbounded plane, not {}",
+                self, rule
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for EngineKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(EngineKind::Grid),
+            "bitgrid" => Ok(EngineKind::BitGrid),
+            "hashlife" => Ok(EngineKind::Hashlife),
+            "sparse" => Ok(EngineKind::Sparse),
+            "spacetime" => Ok(EngineKind::SpaceTime),
+            "blocks" => Ok(EngineKind::Blocks),
+            "volume" => Ok(EngineKind::Volume),
+            "continuous" => Ok(EngineKind::Continuous),
+            _ => Err(format!(
+                "unknown engine '{}', expected grid, bitgrid, hashlife, sparse, spacetime, blocks, volume or continuous",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for EngineKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            EngineKind::Grid => "grid",
+            EngineKind::BitGrid => "bitgrid",
+            EngineKind::Hashlife => "hashlife",
+            EngineKind::Sparse => "sparse",
+            EngineKind::SpaceTime => "spacetime",
+            EngineKind::Blocks => "blocks",
+            EngineKind::Volume => "volume",
+            EngineKind::Continuous => "continuous",
+        })
+    }
+}
+
+/// A census being taken on another thread, as simulating how nearby
+/// objects interact can take longer than the viewer may stop responding.
+struct PendingCensus {
+    generation: u64,
+    rule: Rule,
+    /// The census, or what went wrong taking it.
+    result: Receiver<Result<Census, String>>,
+}
+
+impl PendingCensus {
+    /// Starts taking the census of `cells`, live at `generation` under
+    /// `rule`, on a thread of its own.
+    fn start(generation: u64, rule: Rule, cells: Vec<(i64, i64)>) -> io::Result<Self> {
+        let (sender, result) = mpsc::channel();
+        let census_rule = rule.clone();
+        thread::Builder::new()
+            .name("census".to_string())
+            .spawn(move || {
+                let census = panic::catch_unwind(|| Census::of_cells(&census_rule, &cells))
+                    .map_err(|payload| panic_message(payload.as_ref()));
+                // The viewer may have quit before the census is done.
+                let _ = sender.send(census);
+            })?;
+        Ok(PendingCensus {
+            generation,
+            rule,
+            result,
+        })
+    }
+}
+
+/// The message a panic was raised with.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "unknown error".to_string()),
+    }
+}
+
+/// State of the viewer: the universe being simulated and how it is run.
+pub struct App {
+    pub engine: Box<dyn Engine>,
+    /// Cell shown in the top-left corner of the board.
+    pub viewport: (i64, i64),
+    /// Cells visible on the board, horizontally and vertically.
+    pub view_size: (usize, usize),
+    pub paused: bool,
+    /// Whether a reversible rule is being run backwards.
+    pub backwards: bool,
+    /// For 3D rules, the axis the volume is seen along as a density
+    /// projection, or `None` to show one layer.
+    pub projection: Option<Axis>,
+    pub tick_rate: Duration,
+    /// Each tick advances `2^step_exponent` generations.
+    pub step_exponent: u32,
+    pub should_quit: bool,
+    /// Feedback shown in the status bar, such as where a snapshot went.
+    pub message: Option<String>,
+    /// Where snapshots and censuses are saved; empty for the working
+    /// directory.
+    pub output_dir: PathBuf,
+    /// The cell being edited, while in edit mode.
+    pub cursor: Option<(i64, i64)>,
+    /// The state edit mode paints with.
+    pub brush: u8,
+    /// The seed soups and stochastic updates are drawn from, so that a run
+    /// can be repeated.
+    pub seed: u64,
+    random: Random,
+    /// Watches for the pattern repeating, on engines whose live cells are
+    /// the whole universe and whose updates are deterministic.
+    pub detector: Option<Detector>,
+    /// The last census taken and the generation it was taken at, shown
+    /// beside the board until dismissed.
+    pub census: Option<(u64, Census)>,
+    pending_census: Option<PendingCensus>,
+}
+
+impl App {
+    /// Creates a viewer running `rule` on a `kind` backend, showing
+    /// `view_size` cells and starting from `pattern` or, without one, from
+    /// a random soup filling the view. One-dimensional rules start from
+    /// the top row of the pattern, or from a single live cell. Random
+    /// numbers come from `seed`.
+    pub fn new(
+        kind: EngineKind,
+        rule: Rule,
+        pattern: Option<Pattern>,
+        view_size: (usize, usize),
+        seed: u64,
+    ) -> Self {
+        let bounds = pattern.as_ref().and_then(Pattern::bounds);
+        let engine: Box<dyn Engine> = match kind {
+            EngineKind::Grid
+            | EngineKind::BitGrid
+            | EngineKind::Volume
+            | EngineKind::Continuous => {
+                let (width, height) = bounds.map_or(view_size, |b| {
+                    (
+                        view_size.0.max(b.width as usize),
+                        view_size.1.max(b.height as usize),
+                    )
+                });
+                match kind {
+                    EngineKind::Grid => Box::new(Grid::with_rule(width, height, rule)),
+                    EngineKind::BitGrid => Box::new(BitGrid::new(width, height, rule)),
+                    EngineKind::Volume => Box::new(Volume::new(width, height, VOLUME_DEPTH, rule)),
+                    _ => Box::new(Continuous::new(width, height, rule)),
+                }
+            }
+            EngineKind::Hashlife => Box::new(Hashlife::new(rule)),
+            EngineKind::Sparse => Box::new(Sparse::new(rule)),
+            EngineKind::SpaceTime => Box::new(SpaceTime::new(rule)),
+            EngineKind::Blocks => Box::new(Blocks::new(rule)),
+        };
+        let mut app = App::with_engine(engine, view_size, seed);
+        match (pattern, bounds) {
+            (Some(pattern), Some(bounds)) => {
+                // A grid only holds non-negative coordinates, so the pattern
+                // is moved onto it; other backends keep its coordinates.
+                let (dx, dy) = match kind {
+                    EngineKind::Grid
+                    | EngineKind::BitGrid
+                    | EngineKind::Volume
+                    | EngineKind::Continuous => (-bounds.left, -bounds.top),
+                    EngineKind::Hashlife | EngineKind::Sparse => (0, 0),
+                    EngineKind::Blocks if app.engine.rule().topology().is_some() => {
+                        (-bounds.left, -bounds.top)
+                    }
+                    EngineKind::Blocks => (0, 0),
+                    EngineKind::SpaceTime if app.engine.rule().topology().is_some() => {
+                        (-bounds.left, -bounds.top)
+                    }
+                    EngineKind::SpaceTime => (0, -bounds.top),
+                };
+                for &(x, y, state) in &pattern.cells {
+                    app.engine.set_cell(x + dx, y + dy, state);
+                }
+                app.center_on(
+                    bounds.left + dx + bounds.width as i64 / 2,
+                    bounds.top + dy + bounds.height as i64 / 2,
+                );
+                if kind == EngineKind::SpaceTime {
+                    app.viewport.1 = 0;
+                }
+            }
+            _ if kind == EngineKind::SpaceTime => {
+                let width = app.engine.rule().topology().map_or(0, |t| t.size().0);
+                let middle = i64::from(width / 2);
+                app.engine.set_cell(middle, 0, 1);
+                app.center_on(middle, 0);
+                app.viewport.1 = 0;
+            }
+            _ => {
+                if let EngineKind::Hashlife | EngineKind::Sparse | EngineKind::Blocks = kind {
+                    app.center_on(0, 0);
+                }
+                // A soup means little to rule tables such as WireWorld,
+                // which are for building things by hand.
+                if app.engine.rule().table().is_some() {
+                    app.paused = true;
+                    app.toggle_editing();
+                } else {
+                    app.randomize();
+                }
+            }
+        }
+        app.forget();
+        app
+    }
+
+    /// Creates a viewer for a universe that is already populated, centred
+    /// on its live cells.
+    pub fn from_engine(engine: Box<dyn Engine>, view_size: (usize, usize), seed: u64) -> Self {
+        let mut app = App::with_engine(engine, view_size, seed);
+        app.center_on_pattern();
+        app.forget();
+        app
+    }
+
+    fn with_engine(mut engine: Box<dyn Engine>, view_size: (usize, usize), seed: u64) -> Self {
+        let mut random = Random::new(seed);
+        if let Some(grid) = engine.as_any_mut().downcast_mut::<Grid>() {
+            grid.set_seed(random.next_u64());
+        }
+        let any = engine.as_any();
+        let detector = if any.is::<SpaceTime>() || any.is::<Volume>() || any.is::<Continuous>() {
+            None
+        } else {
+            Some(Detector::new(true))
+        };
+        App {
+            engine,
+            viewport: (0, 0),
+            view_size,
+            paused: false,
+            backwards: false,
+            projection: None,
+            tick_rate: Duration::from_millis(100),
+            step_exponent: 0,
+            should_quit: false,
+            message: None,
+            output_dir: PathBuf::new(),
+            cursor: None,
+            brush: 1,
+            seed,
+            random,
+            detector,
+            census: None,
+            pending_census: None,
+        }
+    }
+
+    /// Advances the simulation unless it is paused, and picks up a census
+    /// that has finished.
+    pub fn on_tick(&mut self) {
+        self.poll_census();
+        if !self.paused {
+            self.advance();
+        }
+    }
+
+    /// Runs `2^step_exponent` generations, backwards if so set. A
+    /// space-time diagram scrolls to keep the newest generation in view,
+    /// unless it was out of view to begin with.
+    fn advance(&mut self) {
+        if self.backwards {
+            self.step_back();
+            return;
+        }
+        let height = self.view_size.1 as i64;
+        let newest = self.engine.generation() as i64;
+        let following = self.engine.as_any().is::<SpaceTime>()
+            && (self.viewport.1..self.viewport.1 + height).contains(&newest);
+        self.engine.step_pow2(self.step_exponent);
+        let newest = self.engine.generation() as i64;
+        if following && newest >= self.viewport.1 + height {
+            self.viewport.1 = newest - height + 1;
+        }
+        // Larger steps skip the generations a repeat would be spotted in.
+        if self.step_exponent == 0 {
+            self.watch();
+        }
+    }
+
+    /// Shows the detector the current generation.
+    fn watch(&mut self) {
+        if let Some(detector) = &mut self.detector {
+            detector.observe(self.engine.as_ref());
+        }
+    }
+
+    /// Makes the detector start again from the current generation, after
+    /// the universe has changed other than by stepping.
+    fn forget(&mut self) {
+        if let Some(detector) = &mut self.detector {
+            detector.reset();
+        }
+        self.watch();
+    }
+
+    /// Switches between spotting patterns that come back anywhere and only
+    /// those that come back in place.
+    fn toggle_translations(&mut self) {
+        if let Some(detector) = &self.detector {
+            let translations = !detector.translations();
+            self.detector = Some(Detector::new(translations));
+            self.forget();
+            self.message = Some(if translations {
+                "spotting repeats anywhere".to_string()
+            } else {
+                "spotting repeats in place only".to_string()
+            });
+        }
+    }
+
+    /// Doubles the number of generations a tick runs. Only Hashlife skips
+    /// ahead; other engines go one generation at a time, so their steps
+    /// stay small.
+    fn raise_step_exponent(&mut self) {
+        let max = if self.engine.as_any().is::<Hashlife>() {
+            MAX_STEP_EXPONENT
+        } else {
+            MAX_PLAIN_STEP_EXPONENT
+        };
+        if self.step_exponent < max {
+            self.step_exponent += 1;
+        } else if max < MAX_STEP_EXPONENT {
+            self.message = Some(format!(
+                "{} runs every generation; use hashlife for steps above 2^{}",
+                self.engine.name(),
+                max
+            ));
+        }
+    }
+
+    /// Goes back `2^step_exponent` generations on a reversible block rule,
+    /// pausing at generation 0.
+    fn step_back(&mut self) {
+        if let Some(blocks) = self.engine.as_any_mut().downcast_mut::<Blocks>() {
+            for _ in 0..1u64 << self.step_exponent {
+                if blocks.generation() == 0 {
+                    self.paused = true;
+                    self.backwards = false;
+                    self.message = Some("reached generation 0".to_string());
+                    break;
+                }
+                blocks.step_back();
+            }
+        }
+    }
+
+    /// Switches between running forwards and backwards, for rules that can
+    /// run both ways.
+    fn toggle_backwards(&mut self) {
+        match self.engine.as_any().downcast_ref::<Blocks>() {
+            Some(blocks) if blocks.is_reversible() => self.backwards = !self.backwards,
+            _ => self.message = Some(format!("{} cannot run backwards", self.engine.rule())),
+        }
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        if self.cursor.is_some() && self.on_edit_key(key) {
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char(' ') | KeyCode::Char('p') => self.paused = !self.paused,
+            KeyCode::Char('n') | KeyCode::Char('.') => self.advance(),
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.tick_rate = (self.tick_rate / 2).max(MIN_TICK)
+            }
+            KeyCode::Char('-') => self.tick_rate = (self.tick_rate * 2).min(MAX_TICK),
+            KeyCode::Char(']') => self.raise_step_exponent(),
+            KeyCode::Char('[') => self.step_exponent = self.step_exponent.saturating_sub(1),
+            KeyCode::Char('r') => {
+                self.randomize();
+                self.forget();
+            }
+            KeyCode::Char('c') => {
+                self.engine.clear();
+                self.forget();
+            }
+            KeyCode::Char('s') => self.save_snapshot(),
+            KeyCode::Char('a') => self.toggle_census(),
+            KeyCode::Char('f') => self.center_on_pattern(),
+            KeyCode::Char('e') => self.toggle_editing(),
+            KeyCode::Char('b') => self.toggle_backwards(),
+            KeyCode::Char('<') => self.move_layer(-1),
+            KeyCode::Char('>') => self.move_layer(1),
+            KeyCode::Char('v') => self.cycle_projection(),
+            KeyCode::Char('t') => self.toggle_translations(),
+            KeyCode::Left => self.viewport.0 -= self.pan_step().0,
+            KeyCode::Right => self.viewport.0 += self.pan_step().0,
+            KeyCode::Up => self.viewport.1 -= self.pan_step().1,
+            KeyCode::Down => self.viewport.1 += self.pan_step().1,
+            _ => {}
+        }
+    }
+
+    /// Handles the keys that mean something else in edit mode: the arrows
+    /// move the cursor, Tab and the digits pick the state to paint with,
+    /// Enter paints it and Backspace clears the cell. Returns whether the
+    /// key was used.
+    fn on_edit_key(&mut self, key: KeyEvent) -> bool {
+        let (x, y) = self.cursor.unwrap();
+        let states = self.engine.rule().states();
+        match key.code {
+            KeyCode::Left => self.move_cursor(x - 1, y),
+            KeyCode::Right => self.move_cursor(x + 1, y),
+            KeyCode::Up => self.move_cursor(x, y - 1),
+            KeyCode::Down => self.move_cursor(x, y + 1),
+            KeyCode::Tab => self.brush = ((u16::from(self.brush) + 1) % states) as u8,
+            KeyCode::BackTab => self.brush = ((u16::from(self.brush) + states - 1) % states) as u8,
+            KeyCode::Char(digit @ '0'..='9') => {
+                let state = digit as u16 - '0' as u16;
+                if state < states {
+                    self.brush = state as u8;
+                }
+            }
+            KeyCode::Enter => {
+                self.engine.set_cell(x, y, self.brush);
+                self.forget();
+            }
+            KeyCode::Backspace | KeyCode::Delete => {
+                self.engine.set_cell(x, y, 0);
+                self.forget();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Moves `offset` layers through a volume.
+    fn move_layer(&mut self, offset: i64) {
+        if let Some(volume) = self.engine.as_any_mut().downcast_mut::<Volume>() {
+            let layer = (volume.layer() as i64 + offset).max(0);
+            volume.set_layer(layer as usize);
+        }
+    }
+
+    /// Switches a volume between showing one layer and projecting it along
+    /// each axis in turn.
+    fn cycle_projection(&mut self) {
+        if self.engine.as_any().is::<Volume>() {
+            self.projection = match self.projection {
+                None => Some(Axis::Z),
+                Some(Axis::Z) => Some(Axis::Y),
+                Some(Axis::Y) => Some(Axis::X),
+                Some(Axis::X) => None,
+            };
+        }
+    }
+
+    /// Enters edit mode with the cursor in the middle of the view, or of
+    /// the rule's bounded grid, or leaves it.
+    fn toggle_editing(&mut self) {
+        let (width, height) = match self.engine.rule().topology() {
+            Some(topology) => topology.size(),
+            None => (0, 0),
+        };
+        let middle = |size: u32, start: i64, view: usize| match size {
+            0 => start + view as i64 / 2,
+            size => i64::from(size / 2),
+        };
+        // Only the newest generation of a space-time diagram can be edited.
+        let y = if self.engine.as_any().is::<SpaceTime>() {
+            self.engine.generation() as i64
+        } else {
+            middle(height, self.viewport.1, self.view_size.1)
+        };
+        self.cursor = match self.cursor {
+            Some(_) => None,
+            None => Some((middle(width, self.viewport.0, self.view_size.0), y)),
+        };
+        if u16::from(self.brush) >= self.engine.rule().states() {
+            self.brush = 1;
+        }
+    }
+
+    /// Puts the cursor on `(x, y)`, panning to keep it in view.
+    fn move_cursor(&mut self, x: i64, y: i64) {
+        self.cursor = Some((x, y));
+        let (width, height) = (self.view_size.0 as i64, self.view_size.1 as i64);
+        if x < self.viewport.0 || x >= self.viewport.0 + width {
+            self.viewport.0 = x - width / 2;
+        }
+        if y < self.viewport.1 || y >= self.viewport.1 + height {
+            self.viewport.1 = y - height / 2;
+        }
+    }
+
+    /// Spreads each step across `threads` threads on backends that can, 0
+    /// meaning one per core.
+    pub fn set_threads(&mut self, threads: usize) {
+        if let Some(grid) = self.engine.as_any_mut().downcast_mut::<Grid>() {
+            grid.set_threads(threads);
+        }
+    }
+
+    /// Makes the grid engine apply its rule with `update`. Repeats mean
+    /// nothing under stochastic updates, so they are no longer looked for.
+    pub fn set_update(&mut self, update: Update) {
+        if let Some(grid) = self.engine.as_any_mut().downcast_mut::<Grid>() {
+            grid.set_update(update);
+            if !update.is_synchronous() {
+                self.detector = None;
+            }
+        }
+    }
+
+    /// Keeps the centre of the view in place when the terminal is resized.
+    pub fn on_resize(&mut self, view_size: (usize, usize)) {
+        let center = (
+            self.viewport.0 + self.view_size.0 as i64 / 2,
+            self.viewport.1 + self.view_size.1 as i64 / 2,
+        );
+        self.view_size = view_size;
+        self.center_on(center.0, center.1);
+    }
+
+    /// Panning moves the view by a quarter of its size.
+    fn pan_step(&self) -> (i64, i64) {
+        (
+            (self.view_size.0 as i64 / 4).max(1),
+            (self.view_size.1 as i64 / 4).max(1),
+        )
+    }
+
+    /// Moves the view onto the middle of the live cells, if there are any.
+    fn center_on_pattern(&mut self) {
+        if let Some(bounds) = self.engine.bounding_box() {
+            self.center_on(
+                bounds.left + (bounds.width / 2) as i64,
+                bounds.top + (bounds.height / 2) as i64,
+            );
+        }
+    }
+
+    /// Moves the view so that `(x, y)` is in its middle.
+    fn center_on(&mut self, x: i64, y: i64) {
+        self.viewport = (
+            x - self.view_size.0 as i64 / 2,
+            y - self.view_size.1 as i64 / 2,
+        );
+    }
+
+    /// Replaces the universe with a fresh soup covering the view, where
+    /// about a third of the cells are alive. A volume gets a sparser soup
+    /// filling the middle of the box, since 3D rules die out in dense ones,
+    /// and a continuous field random values over the middle of the view.
+    fn randomize(&mut self) {
+        self.engine.clear();
+        if self.engine.as_any().is::<Continuous>() {
+            let (left, top) = self.viewport;
+            let (width, height) = (self.view_size.0 as i64, self.view_size.1 as i64);
+            for y in height / 4..height * 3 / 4 {
+                for x in width / 4..width * 3 / 4 {
+                    let state = (self.random.next_u64() >> 8) as u8;
+                    self.engine.set_cell(left + x, top + y, state);
+                }
+            }
+            return;
+        }
+        if let Some(volume) = self.engine.as_any().downcast_ref::<Volume>() {
+            let middle = |size: usize| size as i64 / 4..size as i64 * 3 / 4;
+            let (xs, ys, zs) = (
+                middle(volume.width()),
+                middle(volume.height()),
+                middle(volume.depth()),
+            );
+            let mut cells = Vec::new();
+            for z in zs {
+                for y in ys.clone() {
+                    for x in xs.clone() {
+                        if self.random.next_u64().is_multiple_of(4) {
+                            cells.push((x, y, z));
+                        }
+                    }
+                }
+            }
+            let volume = self.engine.as_any_mut().downcast_mut::<Volume>().unwrap();
+            for (x, y, z) in cells {
+                volume.set_cell_at(x, y, z, 1);
+            }
+            return;
+        }
+        let (left, top) = self.viewport;
+        for y in 0..self.view_size.1 as i64 {
+            for x in 0..self.view_size.0 as i64 {
+                if self.random.next_u64().is_multiple_of(3) {
+                    self.engine.set_cell(left + x, top + y, 1);
+                }
+            }
+        }
+    }
+
+    /// Writes the current universe to a file in the output directory:
+    /// Macrocell for Hashlife, so huge universes stay compact, a text
+    /// drawing for space-time diagrams, and RLE for everything else.
+    fn save_snapshot(&mut self) {
+        let generation = self.engine.generation();
+        let any = self.engine.as_any();
+        let (path, contents) = match (
+            any.downcast_ref::<Hashlife>(),
+            any.downcast_ref::<SpaceTime>(),
+        ) {
+            (Some(universe), _) => (
+                format!("snapshot-{}.mc", generation),
+                macrocell::write(universe),
+            ),
+            (_, Some(diagram)) => (format!("snapshot-{}.txt", generation), diagram.to_text()),
+            (None, None) => {
+                let pattern = Pattern {
+                    rule: Some(self.engine.rule().to_string()),
+                    comments: vec![format!("Generation {}", generation)],
+                    cells: self.engine.live_cells(),
+                    ..Pattern::default()
+                };
+                (format!("snapshot-{}.rle", generation), rle::write(&pattern))
+            }
+        };
+        let path = self.output_dir.join(path);
+        self.message = Some(match fs::write(&path, contents) {
+            Ok(()) => format!("saved {}", path.display()),
+            Err(e) => format!("could not save {}: {}", path.display(), e),
+        });
+    }
+
+    /// Hides the census on show, or else starts splitting the universe
+    /// into objects on another thread, to be counted by apgcode once done.
+    fn toggle_census(&mut self) {
+        if self.census.take().is_some() {
+            return;
+        }
+        if let Some(pending) = &self.pending_census {
+            self.message = Some(format!(
+                "still taking the census of generation {}",
+                pending.generation
+            ));
+            return;
+        }
+        let rule = self.engine.rule();
+        if !census::is_supported(&rule) {
+            self.message = Some(format!("{} has no apgcodes to take a census with", rule));
+            return;
+        }
+        let generation = self.engine.generation();
+        let cells: Vec<_> = self
+            .engine
+            .live_cells()
+            .into_iter()
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        self.message = Some(match PendingCensus::start(generation, rule, cells) {
+            Ok(pending) => {
+                self.pending_census = Some(pending);
+                format!("taking a census of generation {}", generation)
+            }
+            Err(e) => format!("could not start a census: {}", e),
+        });
+    }
+
+    /// Once the census on the way is done, shows it and writes it to a
+    /// file in the output directory.
+    fn poll_census(&mut self) {
+        let received = match &self.pending_census {
+            Some(pending) => pending.result.try_recv(),
+            None => return,
+        };
+        if received == Err(TryRecvError::Empty) {
+            return;
+        }
+        let PendingCensus {
+            generation, rule, ..
+        } = self.pending_census.take().unwrap();
+        let census = match received {
+            Ok(Ok(census)) => census,
+            Ok(Err(reason)) => {
+                self.message = Some(format!(
+                    "census of generation {} failed: {}",
+                    generation, reason
+                ));
+                return;
+            }
+            Err(_) => {
+                self.message = Some(format!("census of generation {} failed", generation));
+                return;
+            }
+        };
+        let path = self.output_dir.join(format!("census-{}.txt", generation));
+        let contents = format!(
+            "Census of {} at generation {}\n\n{}\n",
+            rule, generation, census
+        );
+        self.message = Some(match fs::write(&path, contents) {
+            Ok(()) => format!(
+                "counted {} objects, saved {}",
+                census.objects().len(),
+                path.display()
+            ),
+            Err(e) => format!("could not save {}: {}", path.display(), e),
+        });
+        self.census = Some((generation, census));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, key: char) {
+        app.on_key(KeyEvent::from(KeyCode::Char(key)));
+    }
+
+    /// A viewer on the unbounded plane holding just a blinker.
+    fn blinker() -> App {
+        let pattern = Pattern {
+            cells: vec![(0, 0, 1), (1, 0, 1), (2, 0, 1)],
+            ..Pattern::default()
+        };
+        App::new(EngineKind::Sparse, Rule::CONWAY, Some(pattern), (8, 8), 0)
+    }
+
+    /// Ticks `app` until the census it is taking is done.
+    fn wait_for_census(app: &mut App) {
+        let deadline = std::time::Instant::now() + Duration::from_secs(30);
+        while app.pending_census.is_some() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "the census is taking too long"
+            );
+            thread::sleep(Duration::from_millis(1));
+            app.on_tick();
+        }
+    }
+
+    #[test]
+    fn space_pauses_and_resumes() {
+        let mut app = blinker();
+        press(&mut app, ' ');
+        assert!(app.paused);
+        app.on_tick();
+        assert_eq!(app.engine.generation(), 0);
+        press(&mut app, ' ');
+        app.on_tick();
+        assert_eq!(app.engine.generation(), 1);
+    }
+
+    #[test]
+    fn n_steps_once_while_paused() {
+        let mut app = blinker();
+        press(&mut app, 'p');
+        press(&mut app, 'n');
+        assert_eq!(app.engine.generation(), 1);
+        assert_eq!(app.engine.population(), 3);
+    }
+
+    #[test]
+    fn speed_stays_within_bounds() {
+        let mut app = blinker();
+        for _ in 0..20 {
+            press(&mut app, '+');
+        }
+        assert_eq!(app.tick_rate, MIN_TICK);
+        for _ in 0..20 {
+            press(&mut app, '-');
+        }
+        assert_eq!(app.tick_rate, MAX_TICK);
+    }
+
+    #[test]
+    fn step_size_is_capped_without_hashlife() {
+        let mut app = blinker();
+        for _ in 0..10 {
+            press(&mut app, ']');
+        }
+        assert_eq!(app.step_exponent, MAX_PLAIN_STEP_EXPONENT);
+        assert!(app.message.is_some());
+        press(&mut app, '[');
+        assert_eq!(app.step_exponent, MAX_PLAIN_STEP_EXPONENT - 1);
+
+        let mut app = App::from_engine(Box::new(Hashlife::default()), (8, 8), 0);
+        for _ in 0..50 {
+            press(&mut app, ']');
+        }
+        assert_eq!(app.step_exponent, MAX_STEP_EXPONENT);
+    }
+
+    #[test]
+    fn q_quits() {
+        let mut app = blinker();
+        assert!(!app.should_quit);
+        press(&mut app, 'q');
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn soup_fills_the_view() {
+        let app = App::new(EngineKind::Grid, Rule::CONWAY, None, (30, 20), 1);
+        let population = app.engine.population();
+        assert!(population > 100 && population < 300, "{}", population);
+    }
+
+    #[test]
+    fn rule_tables_start_paused_in_edit_mode() {
+        let rule: Rule = "WireWorld".parse().unwrap();
+        let kind = EngineKind::default_for(&rule);
+        let mut app = App::new(kind, rule, None, (8, 8), 0);
+        assert!(app.paused);
+        assert_eq!(app.engine.population(), 0);
+        let (x, y) = app.cursor.unwrap();
+        press(&mut app, '3');
+        app.on_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.engine.cell(x, y), 3);
+        press(&mut app, '7');
+        assert_eq!(app.brush, 3);
+        app.on_key(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(app.brush, 0);
+        app.on_key(KeyEvent::from(KeyCode::BackTab));
+        app.on_key(KeyEvent::from(KeyCode::BackTab));
+        assert_eq!(app.brush, 2);
+        app.on_key(KeyEvent::from(KeyCode::Right));
+        app.on_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.engine.cell(x + 1, y), 2);
+        app.on_key(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(app.engine.cell(x + 1, y), 0);
+        press(&mut app, 'e');
+        assert_eq!(app.cursor, None);
+    }
+
+    #[test]
+    fn reversible_rules_run_backwards_to_generation_0() {
+        let mut blocks = Blocks::new("BBM".parse().unwrap());
+        blocks.set_cell(0, 0, 1);
+        let mut app = App::from_engine(Box::new(blocks), (8, 8), 0);
+        for _ in 0..3 {
+            app.on_tick();
+        }
+        press(&mut app, 'b');
+        for _ in 0..5 {
+            app.on_tick();
+        }
+        assert_eq!(app.engine.generation(), 0);
+        assert_eq!(app.engine.live_cells(), [(0, 0, 1)]);
+        assert!(app.paused && !app.backwards);
+
+        let mut app = blinker();
+        press(&mut app, 'b');
+        assert!(!app.backwards);
+        assert!(app.message.is_some());
+    }
+
+    #[test]
+    fn census_runs_in_the_background_and_is_shown() {
+        let dir = std::env::temp_dir().join(format!("census-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut app = blinker();
+        app.output_dir = dir.clone();
+        press(&mut app, ' ');
+        press(&mut app, 'a');
+        assert!(app.census.is_none());
+        // A second request waits for the first.
+        press(&mut app, 'a');
+        assert_eq!(
+            app.message.as_deref(),
+            Some("still taking the census of generation 0")
+        );
+        wait_for_census(&mut app);
+        let path = dir.join("census-0.txt");
+        let saved = fs::read_to_string(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(saved.unwrap().contains("xp2_7"));
+        assert_eq!(
+            app.message,
+            Some(format!("counted 1 objects, saved {}", path.display()))
+        );
+        let (generation, census) = app.census.as_ref().unwrap();
+        assert_eq!(*generation, 0);
+        assert_eq!(census.tally(), [("xp2_7".to_string(), 1)]);
+
+        press(&mut app, 'a');
+        assert!(app.census.is_none());
+
+        let mut app = App::new(
+            EngineKind::Grid,
+            "B3/S23/C3".parse().unwrap(),
+            None,
+            (8, 8),
+            0,
+        );
+        press(&mut app, 'a');
+        assert!(app.pending_census.is_none());
+        assert!(app.message.unwrap().contains("no apgcodes"));
+    }
+
+    #[test]
+    fn census_failures_are_reported() {
+        let mut app = blinker();
+        press(&mut app, ' ');
+        // Taking a census under a rule without apgcodes panics.
+        let rule: Rule = "B3/S23/C3".parse().unwrap();
+        app.pending_census = Some(PendingCensus::start(7, rule, vec![(0, 0)]).unwrap());
+        wait_for_census(&mut app);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("census of generation 7 failed: B3/S23/C3 has no apgcodes")
+        );
+        assert!(app.census.is_none());
+    }
+}
@@ -0,0 +1,337 @@
+//! A dense fixed-size board packing 64 cells into each `u64`, stepped with
+//! bitwise adders that count the neighbours of 64 cells at once.
+
+use std::any::Any;
+
+use crate::engine::Engine;
+use crate::rule::{Lattice, Rule};
+use crate::topology::Topology;
+
+/// A rectangular board of cells stored one bit per cell. Cells outside the
+/// rectangle are permanently dead.
+///
+/// Bit `i` of word `k` in a row holds the cell in column `64 * k + i`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+    rule: Rule,
+    generation: u64,
+}
+
+impl BitGrid {
+    /// Creates an empty board of `width` by `height` cells running `rule`.
+    /// A `:P` bounded-plane suffix on the rule overrides the size, like for
+    /// [`Grid::with_rule`](crate::grid::Grid::with_rule).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rule has dying states, is not totalistic, looks beyond
+    /// the eight surrounding cells, is not on square cells or its topology
+    /// joins edges; only two-state totalistic rules on planes are supported.
+    pub fn new(width: usize, height: usize, rule: Rule) -> Self {
+        assert!(
+            rule.states() == 2
+                && rule.is_totalistic()
+                && rule.larger_than_life().is_none()
+                && rule.lattice() == Lattice::Square,
+            "a bit grid cannot run {}",
+            rule
+        );
+        let (width, height) = match rule.topology() {
+            None => (width, height),
+            Some(Topology::Plane {
+                width: w,
+                height: h,
+            }) => (
+                if w == 0 { width } else { w as usize },
+                if h == 0 { height } else { h as usize },
+            ),
+            Some(topology) => panic!("a bit grid cannot run on {}", topology),
+        };
+        let words_per_row = width.div_ceil(64);
+        BitGrid {
+            width,
+            height,
+            words_per_row,
+            words: vec![0; words_per_row * height],
+            rule,
+            generation: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the cell at `(x, y)` is alive. Cells outside the board are
+    /// always dead.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width
+            && y < self.height
+            && self.words[y * self.words_per_row + x / 64] >> (x % 64) & 1 != 0
+    }
+
+    /// Sets the cell at `(x, y)` alive or dead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` lies outside the board.
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        assert!(
+            x < self.width && y < self.height,
+            "cell ({}, {}) is outside a {}x{} grid",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        let word = &mut self.words[y * self.words_per_row + x / 64];
+        if alive {
+            *word |= 1 << (x % 64);
+        } else {
+            *word &= !(1 << (x % 64));
+        }
+    }
+
+    /// Advances the board by one generation under its rule.
+    pub fn step(&mut self) {
+        let counts = CountMasks::new(&self.rule);
+        let mut next = vec![0; self.words.len()];
+        let stride = self.words_per_row;
+        let last_mask = match self.width % 64 {
+            0 => u64::MAX,
+            bits => (1 << bits) - 1,
+        };
+        for y in 0..self.height {
+            let row = |offset: isize| -> &[u64] {
+                let y = y as isize + offset;
+                if y < 0 || y as usize >= self.height {
+                    &[]
+                } else {
+                    &self.words[y as usize * stride..(y as usize + 1) * stride]
+                }
+            };
+            let (above, current, below) = (row(-1), row(0), row(1));
+            for k in 0..stride {
+                let word = |row: &[u64], k: isize| -> u64 {
+                    if k < 0 {
+                        0
+                    } else {
+                        row.get(k as usize).copied().unwrap_or(0)
+                    }
+                };
+                let k = k as isize;
+                let neighbours = [
+                    west(word(above, k), word(above, k - 1)),
+                    word(above, k),
+                    east(word(above, k), word(above, k + 1)),
+                    west(word(current, k), word(current, k - 1)),
+                    east(word(current, k), word(current, k + 1)),
+                    west(word(below, k), word(below, k - 1)),
+                    word(below, k),
+                    east(word(below, k), word(below, k + 1)),
+                ];
+                let mut result = counts.apply(word(current, k), sum(neighbours));
+                if k as usize == stride - 1 {
+                    result &= last_mask;
+                }
+                next[y * stride + k as usize] = result;
+            }
+        }
+        self.words = next;
+        self.generation += 1;
+    }
+}
+
+/// The west neighbours of the cells in `word`, pulling in the top bit of
+/// the word to its left.
+fn west(word: u64, left: u64) -> u64 {
+    word << 1 | left >> 63
+}
+
+/// The east neighbours of the cells in `word`, pulling in the bottom bit of
+/// the word to its right.
+fn east(word: u64, right: u64) -> u64 {
+    word >> 1 | right << 63
+}
+
+fn full_adder(a: u64, b: u64, c: u64) -> (u64, u64) {
+    (a ^ b ^ c, (a & b) | (c & (a ^ b)))
+}
+
+/// Adds eight one-bit neighbour masks lane by lane, giving the four bits of
+/// each cell's neighbour count from least to most significant.
+fn sum(n: [u64; 8]) -> [u64; 4] {
+    let (ones_a, twos_a) = full_adder(n[0], n[1], n[2]);
+    let (ones_b, twos_b) = full_adder(n[3], n[4], n[5]);
+    let (ones_c, twos_c) = (n[6] ^ n[7], n[6] & n[7]);
+    let (bit0, twos_d) = full_adder(ones_a, ones_b, ones_c);
+    let (twos, fours_a) = full_adder(twos_a, twos_b, twos_c);
+    let (bit1, fours_b) = (twos ^ twos_d, twos & twos_d);
+    [bit0, bit1, fours_a ^ fours_b, fours_a & fours_b]
+}
+
+/// The neighbour counts for which a rule gives birth and survival.
+struct CountMasks {
+    birth: Vec<u8>,
+    survival: Vec<u8>,
+}
+
+impl CountMasks {
+    fn new(rule: &Rule) -> Self {
+        CountMasks {
+            birth: (0..=8).filter(|&n| rule.is_birth(n)).collect(),
+            survival: (0..=8).filter(|&n| rule.is_survival(n)).collect(),
+        }
+    }
+
+    /// Next states of the 64 cells in `alive` given their neighbour counts.
+    fn apply(&self, alive: u64, count: [u64; 4]) -> u64 {
+        let equals = |n: u8| -> u64 {
+            (0..4).fold(u64::MAX, |mask, bit| {
+                mask & if n >> bit & 1 != 0 {
+                    count[bit]
+                } else {
+                    !count[bit]
+                }
+            })
+        };
+        let born = self.birth.iter().fold(0, |mask, &n| mask | equals(n));
+        let survives = self.survival.iter().fold(0, |mask, &n| mask | equals(n));
+        (!alive & born) | (alive & survives)
+    }
+}
+
+impl Engine for BitGrid {
+    fn name(&self) -> &'static str {
+        "bitgrid"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        self.words
+            .iter()
+            .map(|word| u64::from(word.count_ones()))
+            .sum()
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        (x >= 0 && y >= 0 && self.get(x as usize, y as usize)) as u8
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.set(x as usize, y as usize, state != 0);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        let mut cells = Vec::new();
+        for (index, &word) in self.words.iter().enumerate() {
+            let (y, k) = (index / self.words_per_row, index % self.words_per_row);
+            let mut bits = word;
+            while bits != 0 {
+                let bit = bits.trailing_zeros() as usize;
+                cells.push(((k * 64 + bit) as i64, y as i64, 1));
+                bits &= bits - 1;
+            }
+        }
+        cells
+    }
+
+    fn step(&mut self) {
+        BitGrid::step(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grid::Grid, random::Random};
+
+    /// A bit grid and a plain grid holding the same random soup.
+    fn soups(width: usize, height: usize, rule: &str, seed: u64) -> (BitGrid, Grid) {
+        let rule: Rule = rule.parse().unwrap();
+        let mut bits = BitGrid::new(width, height, rule.clone());
+        let mut grid = Grid::with_rule(width, height, rule);
+        let mut random = Random::new(seed);
+        for y in 0..bits.height() {
+            for x in 0..bits.width() {
+                let alive = random.chance(0.4);
+                bits.set(x, y, alive);
+                grid.set(x, y, alive);
+            }
+        }
+        (bits, grid)
+    }
+
+    fn assert_same(bits: &BitGrid, grid: &Grid, context: &str) {
+        assert_eq!((bits.width(), bits.height()), (grid.width(), grid.height()));
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                assert_eq!(bits.get(x, y), grid.get(x, y), "({}, {}) {}", x, y, context);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_a_plain_grid() {
+        for rule in &["B3/S23", "B36/S23", "B2/S", "B1357/S1357", "B3/S23:P70,20"] {
+            for &width in &[1, 63, 64, 65, 130] {
+                let (mut bits, mut grid) = soups(width, 24, rule, width as u64);
+                for generation in 0..12 {
+                    bits.step();
+                    grid.step();
+                    let context = format!("{} at width {}, generation {}", rule, width, generation);
+                    assert_same(&bits, &grid, &context);
+                }
+                assert_eq!(bits.population(), grid.population() as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn outside_the_board_is_dead() {
+        let bits = BitGrid::new(10, 10, Rule::CONWAY);
+        assert!(!bits.get(10, 0));
+        assert!(!bits.get(0, 10));
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_joined_edges() {
+        BitGrid::new(10, 10, "B3/S23:T10,10".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_multi_state_rules() {
+        BitGrid::new(10, 10, "B2/S/C3".parse().unwrap());
+    }
+}
@@ -0,0 +1,319 @@
+//! A universe of block rules on the Margolus neighbourhood, where 2x2
+//! blocks of cells are replaced as a whole and the blocks shift by one
+//! cell diagonally every other generation.
+
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+};
+
+use crate::engine::Engine;
+use crate::rule::{Margolus, Rule};
+use crate::topology::Topology;
+
+/// The blocks each block becomes, indexed as in [`Margolus`].
+type Table = [u8; 16];
+
+/// A universe running a [`Margolus`] rule, forwards or, when the rule is
+/// reversible, backwards.
+///
+/// On even generations the blocks have their top-left cell at even
+/// coordinates, on odd generations at odd ones.
+///
+/// Rules such as Critters that turn empty blocks full and full blocks
+/// empty are run as Golly runs `B0` rules: odd generations are stored and
+/// shown with every cell inverted, so that the empty background stays
+/// empty. Other rules that bring empty blocks to life only run on a torus,
+/// whose width and height must be even.
+#[derive(Clone, Debug)]
+pub struct Blocks {
+    rule: Rule,
+    /// The tables applied after even and after odd generations.
+    forward: [Table; 2],
+    /// The tables that undo `forward`, for reversible rules.
+    backward: Option<[Table; 2]>,
+    /// Width and height of the torus, `None` for the unbounded plane.
+    size: Option<(i64, i64)>,
+    /// Cells in state 1.
+    cells: HashSet<(i64, i64)>,
+    generation: u64,
+}
+
+impl Blocks {
+    /// Creates an empty universe running `rule`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rule` is not a block rule, if it brings empty blocks to
+    /// life on the unbounded plane other than by inverting them, or if it
+    /// runs on a topology other than an unshifted torus of even size.
+    pub fn new(rule: Rule) -> Self {
+        let margolus: Margolus = *rule
+            .margolus()
+            .expect("a block universe needs a block rule");
+        let size = match rule.topology() {
+            None => None,
+            Some(Topology::Torus {
+                width,
+                height,
+                shift: None,
+            }) if width > 0 && height > 0 && width % 2 == 0 && height % 2 == 0 => {
+                Some((i64::from(width), i64::from(height)))
+            }
+            Some(topology) => panic!("a block universe cannot run on {}", topology),
+        };
+        let strobing = margolus.next_block(0) == 15 && margolus.next_block(15) == 0;
+        assert!(
+            size.is_some() || strobing || margolus.next_block(0) == 0,
+            "an unbounded block universe cannot run rules that bring empty blocks to life"
+        );
+        let table = |f: &dyn Fn(u8) -> u8| {
+            let mut table = [0; 16];
+            for (block, output) in table.iter_mut().enumerate() {
+                *output = f(block as u8);
+            }
+            table
+        };
+        let inverse = margolus.inverse();
+        let (forward, backward) = if strobing {
+            let inverse = inverse.map(|inverse| {
+                [
+                    table(&|block| inverse.next_block(block ^ 15)),
+                    table(&|block| inverse.next_block(block) ^ 15),
+                ]
+            });
+            (
+                [
+                    table(&|block| margolus.next_block(block) ^ 15),
+                    table(&|block| margolus.next_block(block ^ 15)),
+                ],
+                inverse,
+            )
+        } else {
+            let forward = table(&|block| margolus.next_block(block));
+            let inverse = inverse.map(|inverse| {
+                let backward = table(&|block| inverse.next_block(block));
+                [backward, backward]
+            });
+            ([forward, forward], inverse)
+        };
+        Blocks {
+            rule,
+            forward,
+            backward,
+            size,
+            cells: HashSet::new(),
+            generation: 0,
+        }
+    }
+
+    /// Whether the rule can be run backwards.
+    pub fn is_reversible(&self) -> bool {
+        self.backward.is_some()
+    }
+
+    /// Goes back one generation, exactly undoing [`Engine::step`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rule is not reversible or the universe is at
+    /// generation 0.
+    pub fn step_back(&mut self) {
+        let backward = self
+            .backward
+            .expect("only reversible rules can run backwards");
+        assert!(self.generation > 0, "cannot go back before generation 0");
+        self.generation -= 1;
+        let phase = (self.generation % 2) as usize;
+        self.apply(&backward[phase], phase as i64);
+    }
+
+    /// Replaces every block whose top-left cell is `offset` cells down and
+    /// right of even coordinates with the block `table` gives.
+    fn apply(&mut self, table: &Table, offset: i64) {
+        let mut blocks: HashMap<(i64, i64), u8> = HashMap::new();
+        if let (Some((width, height)), true) = (self.size, table[0] != 0) {
+            for by in 0..height / 2 {
+                for bx in 0..width / 2 {
+                    blocks.insert((bx, by), 0);
+                }
+            }
+        }
+        for &(x, y) in &self.cells {
+            let (mut bx, dx) = ((x - offset).div_euclid(2), (x - offset).rem_euclid(2));
+            let (mut by, dy) = ((y - offset).div_euclid(2), (y - offset).rem_euclid(2));
+            if let Some((width, height)) = self.size {
+                bx = bx.rem_euclid(width / 2);
+                by = by.rem_euclid(height / 2);
+            }
+            *blocks.entry((bx, by)).or_insert(0) |= 1 << (dx + 2 * dy);
+        }
+        let mut cells = HashSet::with_capacity(self.cells.len());
+        for ((bx, by), block) in blocks {
+            let output = table[usize::from(block)];
+            for bit in (0..4).filter(|bit| output >> bit & 1 != 0) {
+                let (mut x, mut y) = (2 * bx + bit % 2 + offset, 2 * by + bit / 2 + offset);
+                if let Some((width, height)) = self.size {
+                    x = x.rem_euclid(width);
+                    y = y.rem_euclid(height);
+                }
+                cells.insert((x, y));
+            }
+        }
+        self.cells = cells;
+    }
+
+    fn contains(&self, x: i64, y: i64) -> bool {
+        match self.size {
+            Some((width, height)) => (0..width).contains(&x) && (0..height).contains(&y),
+            None => true,
+        }
+    }
+}
+
+impl Default for Blocks {
+    fn default() -> Self {
+        Blocks::new("Critters".parse().expect("Critters is a built-in rule"))
+    }
+}
+
+impl Engine for Blocks {
+    fn name(&self) -> &'static str {
+        "blocks"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        self.cells.len() as u64
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        u8::from(self.cells.contains(&(x, y)))
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        if !self.contains(x, y) {
+            return;
+        }
+        if state == 1 {
+            self.cells.insert((x, y));
+        } else {
+            self.cells.remove(&(x, y));
+        }
+    }
+
+    fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        let mut cells: Vec<_> = self.cells.iter().map(|&(x, y)| (x, y, 1)).collect();
+        cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+        cells
+    }
+
+    fn step(&mut self) {
+        let phase = (self.generation % 2) as usize;
+        let table = self.forward[phase];
+        self.apply(&table, phase as i64);
+        self.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+
+    /// A universe running `rule` with a random soup in the square from
+    /// `(0, 0)` to `(size, size)`.
+    fn soup(rule: &str, size: i64, seed: u64) -> Blocks {
+        let mut blocks = Blocks::new(rule.parse().unwrap());
+        let mut random = Random::new(seed);
+        for y in 0..size {
+            for x in 0..size {
+                if random.chance(0.3) {
+                    blocks.set_cell(x, y, 1);
+                }
+            }
+        }
+        blocks
+    }
+
+    #[test]
+    fn billiard_balls_travel_diagonally() {
+        let mut bbm = Blocks::new("BBM".parse().unwrap());
+        bbm.set_cell(0, 0, 1);
+        for _ in 0..10 {
+            bbm.step();
+        }
+        assert_eq!(bbm.live_cells(), [(10, 10, 1)]);
+    }
+
+    #[test]
+    fn stepping_back_restores_every_generation() {
+        for rule in [
+            "Critters",
+            "BBM",
+            "BBM:T16,12",
+            "M15,1,2,3,4,5,6,7,8,9,10,11,12,13,14,0:T8,8",
+        ] {
+            let mut blocks = soup(rule, 8, 5);
+            assert!(blocks.is_reversible());
+            let mut history = Vec::new();
+            for _ in 0..30 {
+                history.push(blocks.live_cells());
+                blocks.step();
+            }
+            while let Some(cells) = history.pop() {
+                blocks.step_back();
+                assert_eq!(blocks.live_cells(), cells, "{}", rule);
+            }
+            assert_eq!(blocks.generation(), 0);
+        }
+    }
+
+    #[test]
+    fn critters_keep_the_background_empty() {
+        // Cells spread at most one cell a generation, on odd generations
+        // as well, where they are stored inverted.
+        let mut critters = soup("Critters", 6, 9);
+        for generation in 1..=21 {
+            critters.step();
+            for (x, y, _) in critters.live_cells() {
+                assert!(x >= -generation && x < 6 + generation, "({}, {})", x, y);
+                assert!(y >= -generation && y < 6 + generation, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn cannot_step_back_irreversible_rules() {
+        let mut blocks = Blocks::new("M0,0,2,3,4,5,6,7,8,9,10,11,12,13,14,15".parse().unwrap());
+        assert!(!blocks.is_reversible());
+        blocks.step();
+        blocks.step_back();
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_unbounded_births() {
+        Blocks::new("M1,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15".parse().unwrap());
+    }
+}
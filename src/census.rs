@@ -0,0 +1,551 @@
+//! Telling the objects of a settled pattern apart and naming each one by
+//! its apgcode, as apgsearch does when it takes a census of soup ash.
+//!
+//! An apgcode is a prefix giving the kind of object, `xs` and the
+//! population for still lifes, `xp` or `xq` and the period for oscillators
+//! and spaceships, followed after `_` by the object drawn in extended
+//! Wechsler format: `xs4_33` is the block and `xq4_153` the glider.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::engine::Engine;
+use crate::period::{Detector, Periodicity};
+use crate::rule::{Lattice, Rule};
+use crate::sparse::Sparse;
+
+/// Most generations an object is run for while waiting for it to repeat.
+const MAX_PERIOD: u64 = 1 << 10;
+
+/// Generations whose cells count towards the space an object takes up,
+/// when the whole pattern never repeats in place, as when spaceships fly
+/// off.
+const FALLBACK_WINDOW: u64 = 4;
+
+/// Largest width and height of a phase that gets drawn in an apgcode.
+const MAX_DRAWN: i64 = 40;
+
+/// What objects that never settle into a cycle are counted as.
+const PATHOLOGICAL: &str = "PATHOLOGICAL";
+
+/// Digits of extended Wechsler format.
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Names of the commonest objects of Conway's Life.
+const COMMON_NAMES: [(&str, &str); 17] = [
+    ("xs4_33", "block"),
+    ("xs4_252", "tub"),
+    ("xs5_253", "boat"),
+    ("xs6_696", "beehive"),
+    ("xs6_356", "ship"),
+    ("xs6_25a4", "barge"),
+    ("xs7_2596", "loaf"),
+    ("xs7_25ac", "long boat"),
+    ("xs8_6996", "pond"),
+    ("xp2_7", "blinker"),
+    ("xp2_7e", "toad"),
+    ("xp2_318c", "beacon"),
+    ("xp15_4r4z4r4", "pentadecathlon"),
+    ("xq4_153", "glider"),
+    ("xq4_6frc", "lightweight spaceship"),
+    ("xq4_27dee6", "middleweight spaceship"),
+    ("xq4_27deee6", "heavyweight spaceship"),
+];
+
+/// Whether objects of `rule` can be told apart and given apgcodes: it must
+/// have two states, live on square cells, look no further than the eight
+/// surrounding ones and leave empty space empty.
+pub fn is_supported(rule: &Rule) -> bool {
+    rule.states() == 2
+        && rule.lattice() == Lattice::Square
+        && rule.range() == 1
+        && !rule.is_birth(0)
+        && rule.wolfram().is_none()
+        && rule.margolus().is_none()
+        && rule.cubic().is_none()
+}
+
+/// One object of a census, with the cells it had when the census was
+/// taken.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Object {
+    pub apgcode: String,
+    pub cells: Vec<(i64, i64)>,
+}
+
+/// The objects of a pattern and how many there are of each.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Census {
+    objects: Vec<Object>,
+    /// Each apgcode with its number of objects, commonest first.
+    tally: Vec<(String, usize)>,
+    /// Whether the rule is Conway's, whose common objects have names.
+    conway: bool,
+}
+
+impl Census {
+    /// Splits the live cells of `engine` into objects and names each one,
+    /// treating the universe as the unbounded plane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the engine's rule is not [supported](is_supported).
+    pub fn of(engine: &dyn Engine) -> Self {
+        let cells: Vec<_> = engine
+            .live_cells()
+            .into_iter()
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        Census::of_cells(&engine.rule(), &cells)
+    }
+
+    /// Splits `cells`, live under `rule` on the unbounded plane, into
+    /// objects and names each one. Unlike [`Census::of`] it needs no
+    /// engine, so it can run on another thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rule` is not [supported](is_supported).
+    pub fn of_cells(rule: &Rule, cells: &[(i64, i64)]) -> Self {
+        let rule = rule.clone().with_topology(None);
+        assert!(is_supported(&rule), "{} has no apgcodes", rule);
+        let objects: Vec<_> = separate(&rule, cells)
+            .into_iter()
+            .map(|cells| Object {
+                apgcode: apgcode(&rule, &cells),
+                cells,
+            })
+            .collect();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for object in &objects {
+            *counts.entry(&object.apgcode).or_default() += 1;
+        }
+        let mut tally: Vec<_> = counts
+            .into_iter()
+            .map(|(apgcode, count)| (apgcode.to_string(), count))
+            .collect();
+        tally.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Census {
+            objects,
+            tally,
+            conway: rule == Rule::CONWAY,
+        }
+    }
+
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    /// Each apgcode found with its number of objects, commonest first.
+    pub fn tally(&self) -> &[(String, usize)] {
+        &self.tally
+    }
+}
+
+impl fmt::Display for Census {
+    /// Writes the tally as a table, with the names of common objects of
+    /// Conway's Life alongside their apgcodes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |apgcode: &str| {
+            COMMON_NAMES
+                .iter()
+                .find(|&&(code, _)| code == apgcode)
+                .map(|&(_, name)| name)
+                .filter(|_| self.conway)
+        };
+        let width = self
+            .tally
+            .iter()
+            .map(|(apgcode, _)| apgcode.len())
+            .fold("Object".len(), usize::max);
+        writeln!(f, "{:<width$}  {:>7}", "Object", "Count", width = width)?;
+        for (apgcode, count) in &self.tally {
+            write!(f, "{:<width$}  {:>7}", apgcode, count, width = width)?;
+            match name(apgcode) {
+                Some(name) => writeln!(f, "  {}", name)?,
+                None => writeln!(f)?,
+            }
+        }
+        write!(f, "{} objects", self.objects.len())
+    }
+}
+
+/// Splits `cells` into the objects they are made of, each with its cells
+/// sorted.
+///
+/// Cells belong together when the space they take up over the pattern's
+/// period comes within two cells, so that some cell neighbours both. Such
+/// a cluster is a pseudo-object, and is split again, when its separately
+/// connected parts run exactly as they would apart: two blocks side by
+/// side are two objects, but the two halves of an aircraft carrier are
+/// one.
+pub fn separate(rule: &Rule, cells: &[(i64, i64)]) -> Vec<Vec<(i64, i64)>> {
+    let (envelope, window) = envelope(rule, cells);
+    let start: HashSet<_> = cells.iter().copied().collect();
+    let mut objects = Vec::new();
+    for cluster in components(&envelope, 2) {
+        let mut groups: Vec<Vec<(i64, i64)>> = components(&cluster, 1);
+        // Merge interacting parts until every pair left runs independently.
+        'merging: loop {
+            for i in 0..groups.len() {
+                for j in i + 1..groups.len() {
+                    if near(&groups[i], &groups[j])
+                        && interact(
+                            rule,
+                            &live(&groups[i], &start),
+                            &live(&groups[j], &start),
+                            window,
+                        )
+                    {
+                        let merged = groups.swap_remove(j);
+                        groups[i].extend(merged);
+                        continue 'merging;
+                    }
+                }
+            }
+            break;
+        }
+        objects.extend(groups.iter().map(|group| {
+            let mut cells = live(group, &start);
+            cells.sort_unstable_by_key(|&(x, y)| (y, x));
+            cells
+        }));
+    }
+    objects
+}
+
+/// The apgcode of the object made of `cells`, or `PATHOLOGICAL` for one
+/// that does not come back to its starting generation within
+/// [`MAX_PERIOD`] generations. Objects too large to draw in any phase get
+/// an `ov_` code giving just their population or period.
+pub fn apgcode(rule: &Rule, cells: &[(i64, i64)]) -> String {
+    let mut universe = universe(rule, cells);
+    let mut detector = Detector::new(true);
+    let mut phases = Vec::new();
+    let periodicity = loop {
+        if let Some(periodicity) = detector.observe(&universe) {
+            break periodicity;
+        }
+        if universe.generation() > MAX_PERIOD {
+            return PATHOLOGICAL.to_string();
+        }
+        phases.push(sorted_cells(&universe));
+        universe.step();
+    };
+    // The repeat must be of the starting generation for the object to
+    // have settled.
+    if periodicity == Periodicity::Extinct || periodicity.period() != universe.generation() {
+        return PATHOLOGICAL.to_string();
+    }
+
+    let drawing = phases
+        .iter()
+        .filter_map(|phase| wechsler(phase))
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    let (prefix, size) = match periodicity {
+        Periodicity::StillLife => ("s", cells.len() as u64),
+        Periodicity::Oscillator { period } => ("p", period),
+        _ => ("q", periodicity.period()),
+    };
+    match drawing {
+        Some(drawing) => format!("x{}{}_{}", prefix, size, drawing),
+        None => format!("ov_{}{}", prefix, size),
+    }
+}
+
+/// The cells `cells` cover over a cycle of the pattern they make, with the
+/// number of generations in it: up to the first repeat in place, or
+/// [`FALLBACK_WINDOW`] generations when none comes.
+fn envelope(rule: &Rule, cells: &[(i64, i64)]) -> (Vec<(i64, i64)>, u64) {
+    let mut universe = universe(rule, cells);
+    let mut detector = Detector::new(false);
+    let mut covered: HashSet<(i64, i64)> = HashSet::new();
+    let mut fallback = None;
+    while detector.observe(&universe).is_none() {
+        if universe.generation() == FALLBACK_WINDOW {
+            fallback = Some(covered.clone());
+        }
+        if universe.generation() > MAX_PERIOD {
+            let covered = fallback.unwrap_or(covered);
+            return (covered.into_iter().collect(), FALLBACK_WINDOW);
+        }
+        covered.extend(universe.live_cells().into_iter().map(|(x, y, _)| (x, y)));
+        universe.step();
+    }
+    (covered.into_iter().collect(), universe.generation())
+}
+
+/// Whether patterns `a` and `b` run any differently together than apart
+/// within `window` generations.
+fn interact(rule: &Rule, a: &[(i64, i64)], b: &[(i64, i64)], window: u64) -> bool {
+    let mut together = universe(rule, a.iter().chain(b));
+    let mut apart = [universe(rule, a), universe(rule, b)];
+    for _ in 0..window {
+        together.step();
+        apart.iter_mut().for_each(Sparse::step);
+        let mut joined = sorted_cells(&apart[0]);
+        joined.extend(sorted_cells(&apart[1]));
+        joined.sort_unstable();
+        if sorted_cells(&together) != joined {
+            return true;
+        }
+    }
+    false
+}
+
+/// Splits `cells` into groups in which every cell can be reached from any
+/// other in moves of at most `reach` cells in each direction, each group
+/// in the order its cells were found.
+fn components(cells: &[(i64, i64)], reach: i64) -> Vec<Vec<(i64, i64)>> {
+    let mut sorted = cells.to_vec();
+    sorted.sort_unstable_by_key(|&(x, y)| (y, x));
+    let mut unvisited: HashSet<_> = sorted.iter().copied().collect();
+    let mut groups = Vec::new();
+    for &cell in &sorted {
+        if !unvisited.remove(&cell) {
+            continue;
+        }
+        let mut group = vec![cell];
+        let mut next = 0;
+        while let Some(&(x, y)) = group.get(next) {
+            next += 1;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    if unvisited.remove(&(x + dx, y + dy)) {
+                        group.push((x + dx, y + dy));
+                    }
+                }
+            }
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+/// Whether some cell of `a` neighbours some cell of `b` on a shared cell.
+fn near(a: &[(i64, i64)], b: &[(i64, i64)]) -> bool {
+    a.iter().any(|&(ax, ay)| {
+        b.iter()
+            .any(|&(bx, by)| (ax - bx).abs() <= 2 && (ay - by).abs() <= 2)
+    })
+}
+
+/// The cells of `area` that are alive in `cells`.
+fn live(area: &[(i64, i64)], cells: &HashSet<(i64, i64)>) -> Vec<(i64, i64)> {
+    area.iter()
+        .copied()
+        .filter(|cell| cells.contains(cell))
+        .collect()
+}
+
+fn universe<'a>(rule: &Rule, cells: impl IntoIterator<Item = &'a (i64, i64)>) -> Sparse {
+    let mut universe = Sparse::new(rule.clone());
+    for &(x, y) in cells {
+        universe.set_cell(x, y, 1);
+    }
+    universe
+}
+
+fn sorted_cells(universe: &Sparse) -> Vec<(i64, i64)> {
+    let mut cells: Vec<_> = universe
+        .live_cells()
+        .into_iter()
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    cells.sort_unstable();
+    cells
+}
+
+/// A rotation or reflection of the plane.
+type Orientation = fn((i64, i64)) -> (i64, i64);
+
+/// The shortest drawing of `cells` in extended Wechsler format among its
+/// eight rotations and reflections, the first in alphabetical order among
+/// equally short ones, or `None` if it is too large to draw.
+fn wechsler(cells: &[(i64, i64)]) -> Option<String> {
+    let orientations: [Orientation; 8] = [
+        |(x, y)| (x, y),
+        |(x, y)| (-x, y),
+        |(x, y)| (x, -y),
+        |(x, y)| (-x, -y),
+        |(x, y)| (y, x),
+        |(x, y)| (-y, x),
+        |(x, y)| (y, -x),
+        |(x, y)| (-y, -x),
+    ];
+    orientations
+        .iter()
+        .filter_map(|orient| {
+            let cells: Vec<_> = cells.iter().map(|&cell| orient(cell)).collect();
+            draw(&cells)
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+}
+
+/// Draws `cells` from the top-left corner of their bounding box in strips
+/// five rows high, separated by `z`. Each column of a strip is a digit
+/// whose bits are its cells from the top down; runs of empty columns are
+/// shortened to `w` for two, `x` for three and `y` and a digit for four or
+/// more, and dropped at the end of a strip.
+fn draw(cells: &[(i64, i64)]) -> Option<String> {
+    let left = cells.iter().map(|&(x, _)| x).min()?;
+    let right = cells.iter().map(|&(x, _)| x).max()?;
+    let top = cells.iter().map(|&(_, y)| y).min()?;
+    let bottom = cells.iter().map(|&(_, y)| y).max()?;
+    let (width, height) = (right - left + 1, bottom - top + 1);
+    if width > MAX_DRAWN || height > MAX_DRAWN {
+        return None;
+    }
+
+    let strips = (height as usize).div_ceil(5);
+    let mut columns = vec![vec![0usize; width as usize]; strips];
+    for &(x, y) in cells {
+        let (column, row) = ((x - left) as usize, (y - top) as usize);
+        columns[row / 5][column] |= 1 << (row % 5);
+    }
+    let mut drawing = String::new();
+    for (i, strip) in columns.iter().enumerate() {
+        if i > 0 {
+            drawing.push('z');
+        }
+        let mut blanks = 0;
+        for &column in strip {
+            if column == 0 {
+                blanks += 1;
+                continue;
+            }
+            match blanks {
+                0 => {}
+                1 => drawing.push('0'),
+                2 => drawing.push('w'),
+                3 => drawing.push('x'),
+                _ => {
+                    drawing.push('y');
+                    drawing.push(char::from(DIGITS[blanks - 4]));
+                }
+            }
+            blanks = 0;
+            drawing.push(char::from(DIGITS[column]));
+        }
+    }
+    Some(drawing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cells an apgcode draws, read back from its extended Wechsler
+    /// format.
+    fn cells_of(apgcode: &str) -> Vec<(i64, i64)> {
+        let (_, drawing) = apgcode.split_once('_').unwrap();
+        let mut cells = Vec::new();
+        for (strip, columns) in drawing.split('z').enumerate() {
+            let mut x = 0;
+            let mut digits = columns.chars();
+            while let Some(c) = digits.next() {
+                match c {
+                    'w' => x += 2,
+                    'x' => x += 3,
+                    'y' => x += 4 + digits.next().unwrap().to_digit(36).unwrap() as i64,
+                    _ => {
+                        let column = c.to_digit(36).unwrap();
+                        for row in 0..5 {
+                            if column & 1 << row != 0 {
+                                cells.push((x, strip as i64 * 5 + row));
+                            }
+                        }
+                        x += 1;
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn common_objects_get_their_own_apgcodes() {
+        for &(code, name) in &COMMON_NAMES {
+            assert_eq!(apgcode(&Rule::CONWAY, &cells_of(code)), code, "{}", name);
+        }
+        // Any phase and orientation of an object has the same code.
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let flipped: Vec<_> = glider.iter().map(|&(x, y)| (y, -x)).collect();
+        assert_eq!(apgcode(&Rule::CONWAY, &flipped), "xq4_153");
+        assert_eq!(apgcode(&Rule::CONWAY, &[(0, 0), (0, 1), (0, 2)]), "xp2_7");
+    }
+
+    #[test]
+    fn objects_that_never_settle_are_pathological() {
+        // A lone cell dies rather than coming back.
+        assert_eq!(apgcode(&Rule::CONWAY, &[(0, 0)]), PATHOLOGICAL);
+        // A pre-block settles, but never comes back to what it was.
+        assert_eq!(
+            apgcode(&Rule::CONWAY, &[(0, 0), (1, 0), (0, 1)]),
+            PATHOLOGICAL
+        );
+    }
+
+    #[test]
+    fn neighbouring_objects_split_only_if_they_run_apart() {
+        let bi_block = [
+            (0, 0),
+            (1, 0),
+            (0, 1),
+            (1, 1),
+            (3, 0),
+            (4, 0),
+            (3, 1),
+            (4, 1),
+        ];
+        assert_eq!(separate(&Rule::CONWAY, &bi_block).len(), 2);
+
+        // The two halves of an aircraft carrier would each die alone.
+        let aircraft_carrier = [(0, 0), (1, 0), (0, 1), (3, 1), (2, 2), (3, 2)];
+        assert_eq!(separate(&Rule::CONWAY, &aircraft_carrier).len(), 1);
+        assert_eq!(apgcode(&Rule::CONWAY, &aircraft_carrier), "xs6_39c");
+    }
+
+    #[test]
+    fn tallies_objects_commonest_first() {
+        let mut cells = cells_of("xs4_33");
+        for dx in [10, 20] {
+            cells.extend(cells_of("xp2_7").iter().map(|&(x, y)| (x + dx, y)));
+        }
+        cells.extend(cells_of("xs6_696").iter().map(|&(x, y)| (x + 40, y + 40)));
+        let census = Census::of_cells(&Rule::CONWAY, &cells);
+        assert_eq!(census.objects().len(), 4);
+        assert_eq!(
+            census.tally(),
+            [
+                ("xp2_7".to_string(), 2),
+                ("xs4_33".to_string(), 1),
+                ("xs6_696".to_string(), 1)
+            ]
+        );
+        let table = census.to_string();
+        assert!(table.contains("xp2_7          2  blinker"), "{}", table);
+        assert!(table.ends_with("4 objects"), "{}", table);
+
+        // The names are only for Conway's Life.
+        let highlife = Census::of_cells(&"B36/S23".parse().unwrap(), &cells_of("xs4_33"));
+        assert!(!highlife.to_string().contains("block"));
+    }
+
+    #[test]
+    fn only_plain_two_state_rules_are_supported() {
+        assert!(is_supported(&Rule::CONWAY));
+        assert!(is_supported(&"B36/S23:T20,20".parse().unwrap()));
+        for rule in [
+            "B3/S23/C3",
+            "B0/S8",
+            "B2/S34H",
+            "W110",
+            "R2,C0,M1,S2..3,B3..3,NM",
+        ] {
+            assert!(!is_supported(&rule.parse().unwrap()), "{}", rule);
+        }
+    }
+}
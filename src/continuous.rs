@@ -0,0 +1,336 @@
+//! A field of real-valued cells stepped with a continuous rule, the
+//! weighted sums over its kernel worked out by convolution through FFTs.
+
+use std::any::Any;
+
+use crate::engine::Engine;
+use crate::fft::{self, Complex};
+use crate::rule::{Lenia, Rule};
+use crate::topology::Topology;
+
+/// A `width` by `height` field of cells holding values between 0 and 1.
+/// Everything outside it is permanently 0, unless the rule's topology is a
+/// torus, in which case opposite edges are joined.
+///
+/// Through [`Engine`], a cell's value is seen as the nearest of the
+/// rule's 256 states, 0 for 0.0 and 255 for 1.0.
+#[derive(Clone, Debug)]
+pub struct Continuous {
+    width: usize,
+    height: usize,
+    /// Cell values indexed by `y * width + x`.
+    values: Vec<f64>,
+    rule: Rule,
+    lenia: Lenia,
+    wrap: bool,
+    /// Size of the arrays transformed: enough to hold the field and the
+    /// kernel's reach on either side, rounded up to powers of two.
+    padded: (usize, usize),
+    /// Transform of the kernel, its weights adding up to 1.
+    kernel: Vec<Complex>,
+    generation: u64,
+}
+
+impl Continuous {
+    /// Creates an empty field of `width` by `height` cells running `rule`.
+    /// When the rule has a torus suffix, the field takes the width and
+    /// height it gives instead, except along unbounded axes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rule` is not a continuous rule or runs on a topology
+    /// other than an unshifted torus.
+    pub fn new(width: usize, height: usize, rule: Rule) -> Self {
+        let lenia = rule
+            .lenia()
+            .expect("a continuous field needs a continuous rule")
+            .clone();
+        let (width, height, wrap) = match rule.topology() {
+            None => (width, height, false),
+            Some(Topology::Torus {
+                width: w,
+                height: h,
+                shift: None,
+            }) => (
+                if w == 0 { width } else { w as usize },
+                if h == 0 { height } else { h as usize },
+                true,
+            ),
+            Some(topology) => panic!("a continuous field cannot run on {}", topology),
+        };
+        let radius = lenia.radius() as usize;
+        // A torus whose sides are powers of two wraps the way the
+        // transform does; anything else needs room for the kernel to reach
+        // past the edges without coming round the other side.
+        let pad = |size: usize| {
+            if wrap && size.is_power_of_two() {
+                size
+            } else {
+                (size + 2 * radius).next_power_of_two()
+            }
+        };
+        let padded = (pad(width), pad(height));
+        let kernel = Self::kernel(&lenia, padded);
+        Continuous {
+            width,
+            height,
+            values: vec![0.0; width * height],
+            rule,
+            lenia,
+            wrap,
+            padded,
+            kernel,
+            generation: 0,
+        }
+    }
+
+    /// The transform of `lenia`'s kernel, laid out with the cell itself
+    /// at the origin of a `padded` array and the cells around it wrapping
+    /// round its edges.
+    fn kernel(lenia: &Lenia, padded: (usize, usize)) -> Vec<Complex> {
+        let (width, height) = padded;
+        let radius = i64::from(lenia.radius());
+        let mut kernel = vec![Complex::default(); width * height];
+        let mut total = 0.0;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let weight = lenia.kernel_weight(((dx * dx + dy * dy) as f64).sqrt());
+                let x = dx.rem_euclid(width as i64) as usize;
+                let y = dy.rem_euclid(height as i64) as usize;
+                kernel[y * width + x].re += weight;
+                total += weight;
+            }
+        }
+        if total > 0.0 {
+            for weight in &mut kernel {
+                weight.re /= total;
+            }
+        }
+        fft::transform_2d(&mut kernel, width, height, false);
+        kernel
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        let (x, y) = if self.wrap {
+            (
+                x.rem_euclid(self.width as i64),
+                y.rem_euclid(self.height as i64),
+            )
+        } else {
+            (x, y)
+        };
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            Some(y as usize * self.width + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Value of the cell at `(x, y)`; 0 outside the field.
+    pub fn value(&self, x: i64, y: i64) -> f64 {
+        self.index(x, y).map_or(0.0, |i| self.values[i])
+    }
+
+    /// Sets the cell at `(x, y)` to `value`, kept between 0 and 1.
+    pub fn set_value(&mut self, x: i64, y: i64, value: f64) {
+        if let Some(i) = self.index(x, y) {
+            self.values[i] = value.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Sum of the values of every cell.
+    pub fn mass(&self) -> f64 {
+        self.values.iter().sum()
+    }
+
+    /// The state a cell holding `value` is seen in.
+    fn state(value: f64) -> u8 {
+        (value * 255.0).round() as u8
+    }
+}
+
+impl Engine for Continuous {
+    fn name(&self) -> &'static str {
+        "continuous"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        self.values
+            .iter()
+            .filter(|&&value| Self::state(value) != 0)
+            .count() as u64
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        Self::state(self.value(x, y))
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        self.set_value(x, y, f64::from(state) / 255.0);
+    }
+
+    fn clear(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = 0.0);
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| {
+                let (x, y) = (i % self.width, i / self.width);
+                (x as i64, y as i64, Self::state(value))
+            })
+            .filter(|&(_, _, state)| state != 0)
+            .collect()
+    }
+
+    fn step(&mut self) {
+        let (width, height) = self.padded;
+        // Along each axis, index `i` of the padded array holds the cell at
+        // `i`, except that the last `radius` indices hold the cells just
+        // before the field, which the kernel reaches by wrapping round.
+        let source = |i: usize, size: usize, padded: usize| {
+            if i < padded - (padded - size).min(self.lenia.radius() as usize) {
+                i as i64
+            } else {
+                i as i64 - padded as i64
+            }
+        };
+        let mut field = vec![Complex::default(); width * height];
+        for y in 0..height {
+            let sy = source(y, self.height, height);
+            for x in 0..width {
+                let sx = source(x, self.width, width);
+                field[y * width + x].re = self.value(sx, sy);
+            }
+        }
+        fft::transform_2d(&mut field, width, height, false);
+        for (value, weight) in field.iter_mut().zip(&self.kernel) {
+            *value = *value * *weight;
+        }
+        fft::transform_2d(&mut field, width, height, true);
+        let time_step = self.lenia.time_step();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let potential = field[y * width + x].re;
+                let value = &mut self.values[y * self.width + x];
+                *value = (*value + time_step * self.lenia.growth_rate(potential)).clamp(0.0, 1.0);
+            }
+        }
+        self.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+
+    /// A field running `rule` with random values in a blob in its middle.
+    fn field(width: usize, height: usize, rule: &str, seed: u64) -> Continuous {
+        let mut field = Continuous::new(width, height, rule.parse().unwrap());
+        let mut random = Random::new(seed);
+        for y in height / 4..height * 3 / 4 {
+            for x in width / 4..width * 3 / 4 {
+                field.set_value(x as i64, y as i64, random.next_f64());
+            }
+        }
+        field
+    }
+
+    /// The next values of `field`, convolving with the kernel cell by
+    /// cell.
+    fn next_values(field: &Continuous) -> Vec<f64> {
+        let lenia = &field.lenia;
+        let radius = i64::from(lenia.radius());
+        let weight = |dx: i64, dy: i64| lenia.kernel_weight(((dx * dx + dy * dy) as f64).sqrt());
+        let total: f64 = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| weight(dx, dy)))
+            .sum();
+        let mut next = Vec::new();
+        for y in 0..field.height as i64 {
+            for x in 0..field.width as i64 {
+                let mut potential = 0.0;
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        potential += weight(dx, dy) * field.value(x + dx, y + dy);
+                    }
+                }
+                let growth = lenia.growth_rate(potential / total);
+                let value = field.value(x, y) + lenia.time_step() * growth;
+                next.push(value.clamp(0.0, 1.0));
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn matches_direct_convolution() {
+        for rule in [
+            "R=3;T=5;m=0.2;s=0.05",
+            "R=4;T=10;b=1,1/2;m=0.15;s=0.03;kn=2;gn=2",
+            "R=3;T=5;m=0.2;s=0.05:T16,8",
+            "R=3;T=5;m=0.2;s=0.05:T13,10",
+        ] {
+            let mut field = field(13, 10, rule, 4);
+            for _ in 0..3 {
+                let expected = next_values(&field);
+                field.step();
+                for (value, expected) in field.values.iter().zip(&expected) {
+                    assert!(
+                        (value - expected).abs() < 1e-9,
+                        "{}: {} != {}",
+                        rule,
+                        value,
+                        expected
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_fields_stay_empty() {
+        let mut field = Continuous::new(16, 16, "R=5;m=0.2;s=0.03".parse().unwrap());
+        field.step();
+        assert_eq!(field.mass(), 0.0);
+        assert_eq!(field.population(), 0);
+    }
+
+    #[test]
+    fn cells_read_as_the_nearest_state() {
+        let mut field = Continuous::new(4, 4, "R=1;m=0.2;s=0.03".parse().unwrap());
+        field.set_value(1, 2, 0.5);
+        field.set_value(2, 2, 7.0);
+        assert_eq!(field.cell(1, 2), 128);
+        assert_eq!(field.value(2, 2), 1.0);
+        assert_eq!(field.live_cells(), [(1, 2, 128), (2, 2, 255)]);
+        field.set_cell(0, 0, 51);
+        assert!((field.value(0, 0) - 0.2).abs() < 1e-12);
+    }
+}
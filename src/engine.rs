@@ -0,0 +1,68 @@
+//! The interface shared by the simulation backends, so that the viewer and
+//! other tools can drive any of them.
+
+use std::any::Any;
+
+use crate::pattern::{Bounds, Pattern};
+use crate::rule::Rule;
+
+/// A universe of cells that can be inspected, edited and stepped.
+///
+/// Coordinates are signed; each backend decides which of them hold cells.
+/// Cells a backend cannot hold read as state 0 and ignore writes.
+pub trait Engine {
+    /// Short name of the backend, for status displays.
+    fn name(&self) -> &'static str;
+
+    /// The engine as [`Any`], to reach features only one backend has.
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn rule(&self) -> Rule;
+
+    /// Number of generations computed so far.
+    fn generation(&self) -> u64;
+
+    /// Number of cells in a non-zero state.
+    fn population(&self) -> u64;
+
+    /// State of the cell at `(x, y)`; 0 is dead.
+    fn cell(&self, x: i64, y: i64) -> u8;
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8);
+
+    /// Kills every cell, keeping the rule and generation count.
+    fn clear(&mut self);
+
+    /// Every cell in a non-zero state, as `(x, y, state)`.
+    fn live_cells(&self) -> Vec<(i64, i64, u8)>;
+
+    /// Smallest rectangle holding every live cell, `None` when there are
+    /// none.
+    fn bounding_box(&self) -> Option<Bounds> {
+        Pattern {
+            cells: self.live_cells(),
+            ..Pattern::default()
+        }
+        .bounds()
+    }
+
+    /// Advances one generation.
+    fn step(&mut self);
+
+    /// Advances `2^exponent` generations. Backends that can jump ahead
+    /// faster than one generation at a time override this.
+    fn step_pow2(&mut self, exponent: u32) {
+        for _ in 0..1u64 << exponent {
+            self.step();
+        }
+    }
+
+    /// Writes the cells of `pattern` at their own coordinates.
+    fn load(&mut self, pattern: &Pattern) {
+        for &(x, y, state) in &pattern.cells {
+            self.set_cell(x, y, state);
+        }
+    }
+}
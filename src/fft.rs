@@ -0,0 +1,193 @@
+//! Fast Fourier transforms of power-of-two sizes, for convolving fields of
+//! cells with large kernels.
+
+use std::{
+    f64::consts::PI,
+    ops::{Add, Mul, Sub},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// Transforms `data` in place with the iterative radix-2 Cooley-Tukey
+/// algorithm. The inverse transform divides by the length, so that it
+/// undoes the forward one.
+///
+/// # Panics
+///
+/// Panics if the length of `data` is not a power of two.
+pub(crate) fn transform(data: &mut [Complex], inverse: bool) {
+    let n = data.len();
+    assert!(
+        n.is_power_of_two(),
+        "FFT length {} is not a power of two",
+        n
+    );
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i
+            .reverse_bits()
+            .checked_shr(usize::BITS - bits)
+            .unwrap_or(0);
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut size = 2;
+    while size <= n {
+        let angle = sign * 2.0 * PI / size as f64;
+        let step = Complex::new(angle.cos(), angle.sin());
+        for start in (0..n).step_by(size) {
+            let mut twiddle = Complex::new(1.0, 0.0);
+            for i in start..start + size / 2 {
+                let odd = data[i + size / 2] * twiddle;
+                data[i + size / 2] = data[i] - odd;
+                data[i] = data[i] + odd;
+                twiddle = twiddle * step;
+            }
+        }
+        size *= 2;
+    }
+    if inverse {
+        let scale = 1.0 / n as f64;
+        for value in data.iter_mut() {
+            *value = Complex::new(value.re * scale, value.im * scale);
+        }
+    }
+}
+
+/// Transforms a `width` by `height` array stored row by row, one row and
+/// then one column at a time.
+pub(crate) fn transform_2d(data: &mut [Complex], width: usize, height: usize, inverse: bool) {
+    for row in data.chunks_mut(width) {
+        transform(row, inverse);
+    }
+    let mut column = vec![Complex::default(); height];
+    for x in 0..width {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = data[y * width + x];
+        }
+        transform(&mut column, inverse);
+        for (y, value) in column.iter().enumerate() {
+            data[y * width + x] = *value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+
+    fn random_data(len: usize, seed: u64) -> Vec<Complex> {
+        let mut random = Random::new(seed);
+        (0..len)
+            .map(|_| Complex::new(random.next_f64() - 0.5, random.next_f64() - 0.5))
+            .collect()
+    }
+
+    /// The discrete Fourier transform of `data`, straight from its
+    /// definition.
+    fn dft(data: &[Complex]) -> Vec<Complex> {
+        let n = data.len();
+        (0..n)
+            .map(|k| {
+                data.iter()
+                    .enumerate()
+                    .fold(Complex::default(), |sum, (j, &value)| {
+                        let angle = -2.0 * PI * (j * k) as f64 / n as f64;
+                        sum + value * Complex::new(angle.cos(), angle.sin())
+                    })
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: &[Complex], expected: &[Complex]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a.re - e.re).abs() < 1e-9 && (a.im - e.im).abs() < 1e-9,
+                "{:?} != {:?}",
+                a,
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn matches_the_definition() {
+        for &len in &[1, 2, 8, 64] {
+            let data = random_data(len, len as u64);
+            let mut fast = data.clone();
+            transform(&mut fast, false);
+            assert_close(&fast, &dft(&data));
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let data = random_data(32, 3);
+        let mut round_trip = data.clone();
+        transform(&mut round_trip, false);
+        transform(&mut round_trip, true);
+        assert_close(&round_trip, &data);
+    }
+
+    #[test]
+    fn transforms_rows_then_columns() {
+        let (width, height) = (8, 4);
+        let data = random_data(width * height, 9);
+        let mut fast = data.clone();
+        transform_2d(&mut fast, width, height, false);
+        let mut expected: Vec<Complex> = data.chunks(width).flat_map(dft).collect();
+        for x in 0..width {
+            let column: Vec<_> = (0..height).map(|y| expected[y * width + x]).collect();
+            for (y, value) in dft(&column).into_iter().enumerate() {
+                expected[y * width + x] = value;
+            }
+        }
+        assert_close(&fast, &expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_other_lengths() {
+        transform(&mut [Complex::default(); 6], false);
+    }
+}
@@ -0,0 +1,148 @@
+//! The Life 1.05 format: a `#Life 1.05` header, `#D` description lines,
+//! an optional `#N` (normal Conway rules) or `#R` survival/birth rule, and
+//! blocks of `.`/`*` rows each placed by a `#P x y` line.
+//!
+//! ```text
+//! #Life 1.05
+//! #D Glider
+//! #N
+//! #P -1 -1
+//! .*.
+//! ..*
+//! ***
+//! ```
+
+use super::{ErrorKind, ParseError};
+use crate::pattern::Pattern;
+use crate::rule::{Lattice, Rule};
+
+pub const HEADER: &str = "#Life 1.05";
+
+/// Reads a Life 1.05 file.
+pub fn parse(text: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let (mut left, mut y) = (0, 0);
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim_end();
+        if line.starts_with(HEADER) {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('#') {
+            let text = rest.get(1..).unwrap_or("").trim();
+            match rest.chars().next() {
+                Some('D') | Some('C') => pattern.comments.push(text.to_string()),
+                Some('N') => pattern.rule = Some(Rule::CONWAY.to_string()),
+                Some('R') => pattern.rule = Some(text.to_string()),
+                Some('P') => {
+                    let column = line.len() - text.len() + 1;
+                    let position = super::parse_coordinates(number, column, text)?;
+                    left = position.0;
+                    y = position.1;
+                }
+                _ => pattern.comments.push(rest.trim().to_string()),
+            }
+            continue;
+        }
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                '*' | 'O' => pattern.cells.push((left + x as i64, y, 1)),
+                _ => return Err(ParseError::new(number, x + 1, ErrorKind::UnexpectedChar(c))),
+            }
+        }
+        y += 1;
+    }
+    Ok(pattern)
+}
+
+/// Writes `pattern` as a single Life 1.05 block positioned at its own
+/// coordinates.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER);
+    for line in pattern.name.iter().chain(&pattern.comments) {
+        out.push_str(&format!("#D {}\n", line));
+    }
+    match pattern.rule.as_deref().map(str::parse::<Rule>) {
+        Some(Ok(rule)) if rule == Rule::CONWAY => out.push_str("#N\n"),
+        Some(Ok(rule))
+            if rule.is_totalistic()
+                && rule.larger_than_life().is_none()
+                && rule.lattice() == Lattice::Square =>
+        {
+            out.push_str(&format!("#R {}\n", survival_birth(rule)))
+        }
+        Some(Ok(rule)) => out.push_str(&format!("#R {}\n", rule)),
+        Some(Err(_)) => out.push_str(&format!("#R {}\n", pattern.rule.as_ref().unwrap())),
+        None => {}
+    }
+    if let Some(bounds) = pattern.bounds() {
+        out.push_str(&format!("#P {} {}\n", bounds.left, bounds.top));
+        for row in super::rows(pattern, '.', '*') {
+            out.push_str(&row);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Prints `rule` in the survival-first `23/3` notation Life 1.05 expects,
+/// with the number of states appended for Generations rules.
+fn survival_birth(rule: Rule) -> String {
+    let counts = |pred: &dyn Fn(u8) -> bool| -> String {
+        (0..=8)
+            .filter(|&n| pred(n))
+            .map(|n| n.to_string())
+            .collect()
+    };
+    let mut notation = format!(
+        "{}/{}",
+        counts(&|n| rule.is_survival(n)),
+        counts(&|n| rule.is_birth(n))
+    );
+    if rule.states() > 2 {
+        notation.push_str(&format!("/{}", rule.states()));
+    }
+    notation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_at_its_position() {
+        let text = "#Life 1.05\n#D Glider\n#N\n#P -1 -1\n.*\n..*\n***\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!(pattern.comments, ["Glider"]);
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(
+            pattern.cells,
+            [(0, -1, 1), (1, 0, 1), (-1, 1, 1), (0, 1, 1), (1, 1, 1)]
+        );
+        assert_eq!(write(&pattern), text);
+    }
+
+    #[test]
+    fn blocks_are_placed_separately() {
+        let pattern = parse("#Life 1.05\n#P 0 0\n*\n#P 10 -5\n.*\n").unwrap();
+        assert_eq!(pattern.cells, [(0, 0, 1), (11, -5, 1)]);
+    }
+
+    #[test]
+    fn other_rules_are_written_survival_first() {
+        let pattern = Pattern {
+            rule: Some("B36/S23".to_string()),
+            cells: vec![(0, 0, 1)],
+            ..Pattern::default()
+        };
+        assert!(write(&pattern).contains("#R 23/36\n"));
+    }
+
+    #[test]
+    fn reports_bad_positions() {
+        let error = parse("#Life 1.05\n#P 1 x\n").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 6));
+        assert_eq!(error.kind, ErrorKind::InvalidNumber);
+    }
+}
@@ -0,0 +1,67 @@
+//! The Life 1.06 format: a `#Life 1.06` header followed by the `x y`
+//! coordinates of each live cell, one per line.
+//!
+//! ```text
+//! #Life 1.06
+//! 0 -1
+//! 1 0
+//! -1 1
+//! 0 1
+//! 1 1
+//! ```
+
+use super::ParseError;
+use crate::pattern::Pattern;
+
+pub const HEADER: &str = "#Life 1.06";
+
+/// Reads a Life 1.06 file. Other `#` lines are kept as comments.
+pub fn parse(text: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    for (index, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(HEADER) {
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            pattern.comments.push(comment.trim().to_string());
+            continue;
+        }
+        let column = line.len() - line.trim_start().len() + 1;
+        let (x, y) = super::parse_coordinates(index + 1, column, trimmed)?;
+        pattern.cells.push((x, y, 1));
+    }
+    Ok(pattern)
+}
+
+/// Writes the live cells of `pattern` at their own coordinates.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER);
+    let mut cells: Vec<_> = pattern.cells.iter().filter(|cell| cell.2 != 0).collect();
+    cells.sort_by_key(|&&(x, y, _)| (y, x));
+    for (x, y, _) in cells {
+        out.push_str(&format!("{} {}\n", x, y));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::ErrorKind;
+
+    #[test]
+    fn round_trips() {
+        let text = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!(pattern.cells.len(), 5);
+        assert_eq!(write(&pattern), text);
+    }
+
+    #[test]
+    fn rejects_lines_without_two_numbers() {
+        let error = parse("#Life 1.06\n0 1 2\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.kind, ErrorKind::ExpectedCoordinates);
+    }
+}
@@ -0,0 +1,459 @@
+//! Golly's Macrocell format, which stores a Hashlife quadtree node by node
+//! so that huge but regular patterns stay small on disk.
+//!
+//! ```text
+//! [M2] (golly 2.0)
+//! #R B3/S23
+//! #G 0
+//! .*$..*$***$
+//! 4 1 0 0 0
+//! ```
+//!
+//! Nodes are numbered from 1 in file order and 0 stands for an empty node.
+//! Two-state files spell out 8x8 leaves as rows of `.` and `*` ended by
+//! `$`; multi-state files instead list level 1 nodes as `1 nw ne sw se`
+//! with the four cell states. Every other line is `level nw ne sw se` with
+//! the numbers of the four child nodes. The last node is the root, centred
+//! on the origin.
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use super::{ErrorKind, ParseError};
+use crate::engine::Engine;
+use crate::hashlife::{Hashlife, NodeId};
+use crate::rule::{Lattice, Rule};
+
+pub const HEADER: &str = "[M2]";
+
+/// Reads a Macrocell file straight into a Hashlife universe.
+pub fn parse(text: &str) -> Result<Hashlife, ParseError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line));
+    match lines.next() {
+        Some((_, line)) if line.starts_with(HEADER) => {}
+        _ => {
+            return Err(ParseError::new(
+                1,
+                1,
+                ErrorKind::InvalidHeader(format!("expected '{}'", HEADER)),
+            ))
+        }
+    }
+
+    let mut rule = Rule::CONWAY;
+    let mut generation = 0;
+    // Node 0 is the empty node, whose level depends on where it is used.
+    let mut nodes: Vec<Option<NodeId>> = vec![None];
+    let mut universe = Hashlife::new(Rule::CONWAY);
+    for (number, line) in lines {
+        let line = line.trim_end();
+        if let Some(rest) = line.strip_prefix('#') {
+            let value = rest.get(1..).unwrap_or("").trim();
+            let column = line.len() - value.len() + 1;
+            match rest.chars().next() {
+                Some('R') => {
+                    rule = value
+                        .parse()
+                        .map_err(|e| ParseError::new(number, column, ErrorKind::InvalidRule(e)))?;
+                }
+                Some('G') => {
+                    generation = value
+                        .parse()
+                        .map_err(|_| ParseError::new(number, column, ErrorKind::InvalidNumber))?;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let node = if line.starts_with(['.', '*', '$']) {
+            parse_leaf(number, line, &mut universe)?
+        } else {
+            parse_node(number, line, &nodes, &mut universe)?
+        };
+        nodes.push(Some(node));
+    }
+
+    if rule.is_birth(0) {
+        return Err(ParseError::new(
+            1,
+            1,
+            ErrorKind::InvalidHeader("Hashlife cannot run B0 rules".to_string()),
+        ));
+    }
+    if rule.larger_than_life().is_some() {
+        return Err(ParseError::new(
+            1,
+            1,
+            ErrorKind::InvalidHeader("Hashlife cannot run Larger than Life rules".to_string()),
+        ));
+    }
+    if rule.lattice() == Lattice::Triangular {
+        return Err(ParseError::new(
+            1,
+            1,
+            ErrorKind::InvalidHeader("Hashlife cannot run triangular rules".to_string()),
+        ));
+    }
+    let mut result = Hashlife::new(rule);
+    if let Some(&Some(root)) = nodes.last() {
+        // Rebuild the tree in the universe that owns the right rule.
+        let root = copy_tree(&universe, root, &mut result, &mut HashMap::new());
+        result.set_root(root);
+    }
+    result.set_generation(generation);
+    Ok(result)
+}
+
+/// Writes a Hashlife universe as Macrocell, sharing every repeated subtree.
+/// Rules with more than two states are written with level 1 nodes even
+/// when no cell is in a state above 1.
+pub fn write(universe: &Hashlife) -> String {
+    let root = universe.root();
+    // A step can leave the root smaller than the 8x8 leaves of two-state
+    // files, so it is surrounded with empty space first, in a store of its
+    // own holding just its few cells.
+    if universe.node_level(root) < 3 {
+        let mut padded = Hashlife::new(universe.rule());
+        let copy = copy_tree(universe, root, &mut padded, &mut HashMap::new());
+        padded.set_root(copy);
+        padded.set_generation(universe.generation());
+        return write(&padded);
+    }
+    let mut writer = Writer {
+        universe,
+        multi_state: universe.rule().states() > 2,
+        numbers: HashMap::new(),
+        lines: Vec::new(),
+    };
+    writer.write_node(root);
+
+    let mut out = format!(
+        "{} (rs-game-of-life {})\n",
+        HEADER,
+        env!("CARGO_PKG_VERSION")
+    );
+    out.push_str(&format!("#R {}\n", universe.rule()));
+    if universe.generation() != 0 {
+        out.push_str(&format!("#G {}\n", universe.generation()));
+    }
+    for line in writer.lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Builds a level 3 node from an 8x8 leaf line such as `.*$..*$***$`.
+fn parse_leaf(number: usize, line: &str, universe: &mut Hashlife) -> Result<NodeId, ParseError> {
+    let mut cells = [[0u8; 8]; 8];
+    let (mut x, mut y) = (0, 0);
+    for (index, c) in line.chars().enumerate() {
+        match c {
+            '.' | '*' if x < 8 && y < 8 => {
+                cells[y][x] = u8::from(c == '*');
+                x += 1;
+            }
+            '$' => {
+                x = 0;
+                y += 1;
+            }
+            _ => {
+                return Err(ParseError::new(
+                    number,
+                    index + 1,
+                    ErrorKind::UnexpectedChar(c),
+                ))
+            }
+        }
+    }
+    Ok(build_square(universe, &cells, 0, 0, 3))
+}
+
+/// Joins the `2^level` square of `cells` at `(left, top)` into a node.
+fn build_square(
+    universe: &mut Hashlife,
+    cells: &[[u8; 8]; 8],
+    left: usize,
+    top: usize,
+    level: u8,
+) -> NodeId {
+    if level == 0 {
+        return Hashlife::leaf(cells[top][left]);
+    }
+    let half = 1 << (level - 1);
+    let children = [
+        build_square(universe, cells, left, top, level - 1),
+        build_square(universe, cells, left + half, top, level - 1),
+        build_square(universe, cells, left, top + half, level - 1),
+        build_square(universe, cells, left + half, top + half, level - 1),
+    ];
+    universe.join(children)
+}
+
+/// Parses a `level nw ne sw se` node line.
+fn parse_node(
+    number: usize,
+    line: &str,
+    nodes: &[Option<NodeId>],
+    universe: &mut Hashlife,
+) -> Result<NodeId, ParseError> {
+    let mut fields = [0u64; 5];
+    let mut count = 0;
+    for field in line.split_whitespace() {
+        let column = field.as_ptr() as usize - line.as_ptr() as usize + 1;
+        if count == fields.len() {
+            return Err(ParseError::new(
+                number,
+                column,
+                ErrorKind::InvalidNode("expected a level and four children".to_string()),
+            ));
+        }
+        fields[count] = field
+            .parse()
+            .map_err(|_| ParseError::new(number, column, ErrorKind::InvalidNumber))?;
+        count += 1;
+    }
+    let invalid = |reason: String| ParseError::new(number, 1, ErrorKind::InvalidNode(reason));
+    if count != fields.len() {
+        return Err(invalid("expected a level and four children".to_string()));
+    }
+
+    let level = fields[0];
+    if level == 0 || level > 62 {
+        return Err(invalid(format!("level {} is out of range", level)));
+    }
+    let level = level as u8;
+    let mut children = [0; 4];
+    for (child, &field) in children.iter_mut().zip(&fields[1..]) {
+        *child = if level == 1 {
+            let state = u8::try_from(field)
+                .map_err(|_| invalid(format!("cell state {} is out of range", field)))?;
+            Hashlife::leaf(state)
+        } else if field == 0 {
+            universe.empty_node(level - 1)
+        } else {
+            let id = nodes
+                .get(field as usize)
+                .copied()
+                .flatten()
+                .ok_or_else(|| invalid(format!("node {} is not defined yet", field)))?;
+            if universe.node_level(id) != level - 1 {
+                return Err(invalid(format!(
+                    "node {} has level {}, expected {}",
+                    field,
+                    universe.node_level(id),
+                    level - 1
+                )));
+            }
+            id
+        };
+    }
+    Ok(universe.join(children))
+}
+
+fn copy_tree(
+    from: &Hashlife,
+    id: NodeId,
+    to: &mut Hashlife,
+    copied: &mut HashMap<NodeId, NodeId>,
+) -> NodeId {
+    if from.node_level(id) == 0 {
+        return id;
+    }
+    if let Some(&copy) = copied.get(&id) {
+        return copy;
+    }
+    let children = from
+        .node_children(id)
+        .map(|child| copy_tree(from, child, to, copied));
+    let copy = to.join(children);
+    copied.insert(id, copy);
+    copy
+}
+
+struct Writer<'a> {
+    universe: &'a Hashlife,
+    multi_state: bool,
+    /// Line number given to each node already written.
+    numbers: HashMap<NodeId, usize>,
+    lines: Vec<String>,
+}
+
+impl<'a> Writer<'a> {
+    /// Writes `id` after its children, returning its number, or 0 when it
+    /// is empty.
+    fn write_node(&mut self, id: NodeId) -> usize {
+        if self.universe.node_population(id) == 0 {
+            return 0;
+        }
+        if let Some(&number) = self.numbers.get(&id) {
+            return number;
+        }
+        let level = self.universe.node_level(id);
+        let children = self.universe.node_children(id);
+        let line = if self.multi_state && level == 1 {
+            format!(
+                "1 {} {} {} {}",
+                children[0], children[1], children[2], children[3]
+            )
+        } else if !self.multi_state && level == 3 {
+            self.leaf_line(id)
+        } else {
+            let numbers = children.map(|child| self.write_node(child));
+            format!(
+                "{} {} {} {} {}",
+                level, numbers[0], numbers[1], numbers[2], numbers[3]
+            )
+        };
+        self.lines.push(line);
+        self.numbers.insert(id, self.lines.len());
+        self.lines.len()
+    }
+
+    /// Spells out a level 3 node as rows of `.` and `*`, dropping trailing
+    /// dead cells and trailing empty rows.
+    fn leaf_line(&self, id: NodeId) -> String {
+        let mut cells = [[false; 8]; 8];
+        self.fill(id, 0, 0, &mut cells);
+        let mut rows: Vec<String> = cells
+            .iter()
+            .map(|row| {
+                let length = row.iter().rposition(|&alive| alive).map_or(0, |x| x + 1);
+                row[..length]
+                    .iter()
+                    .map(|&alive| if alive { '*' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        while rows.last().is_some_and(String::is_empty) {
+            rows.pop();
+        }
+        rows.iter().map(|row| format!("{}$", row)).collect()
+    }
+
+    fn fill(&self, id: NodeId, left: usize, top: usize, cells: &mut [[bool; 8]; 8]) {
+        let level = self.universe.node_level(id);
+        if level == 0 {
+            cells[top][left] = id != 0;
+            return;
+        }
+        let half = 1 << (level - 1);
+        let quadrants = [(0, 0), (half, 0), (0, half), (half, half)];
+        for (&child, &(dx, dy)) in self.universe.node_children(id).iter().zip(&quadrants) {
+            self.fill(child, left + dx, top + dy, cells);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_cells(universe: &Hashlife) -> Vec<(i64, i64, u8)> {
+        let mut cells = universe.live_cells();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Checks that writing `universe` and reading it back gives the same
+    /// cells, rule and generation.
+    fn assert_round_trips(universe: &Hashlife) -> String {
+        let text = write(universe);
+        let read = parse(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+        assert_eq!(sorted_cells(&read), sorted_cells(universe), "{}", text);
+        assert_eq!(read.rule(), universe.rule());
+        assert_eq!(read.generation(), universe.generation());
+        text
+    }
+
+    #[test]
+    fn reads_the_documented_glider() {
+        let text = "[M2] (golly 2.0)\n#R B3/S23\n#G 7\n.*$..*$***$\n4 1 0 0 0\n";
+        let universe = parse(text).unwrap();
+        assert_eq!(universe.generation(), 7);
+        assert_eq!(
+            sorted_cells(&universe),
+            [
+                (-8, -6, 1),
+                (-7, -8, 1),
+                (-7, -6, 1),
+                (-6, -7, 1),
+                (-6, -6, 1)
+            ]
+        );
+        assert_round_trips(&universe);
+    }
+
+    #[test]
+    fn round_trips_after_the_root_shrinks() {
+        // Stepping a level 3 root leaves a level 2 one.
+        let mut universe = Hashlife::default();
+        for &(x, y) in &[(-1, -1), (0, -1), (-1, 0), (0, 0)] {
+            universe.set_cell(x, y, 1);
+        }
+        universe.advance();
+        assert!(universe.node_level(universe.root()) < 3);
+        assert_round_trips(&universe);
+    }
+
+    #[test]
+    fn round_trips_spread_out_patterns() {
+        let mut universe = Hashlife::default();
+        for &(x, y) in &[(-1000, 3), (0, 0), (1, 0), (2, 0), (517, -900)] {
+            universe.set_cell(x, y, 1);
+        }
+        universe.step_pow2(5);
+        assert_round_trips(&universe);
+    }
+
+    #[test]
+    fn multi_state_rules_use_level_one_nodes() {
+        let mut universe = Hashlife::new("B2/S/C3".parse().unwrap());
+        universe.set_cell(0, 0, 1);
+        universe.set_cell(1, 0, 1);
+        let text = assert_round_trips(&universe);
+        assert!(!text.contains('$'), "{}", text);
+        assert!(text.lines().any(|line| line.starts_with("1 ")), "{}", text);
+
+        universe.step();
+        assert!(sorted_cells(&universe)
+            .iter()
+            .any(|&(_, _, state)| state == 2));
+        assert_round_trips(&universe);
+    }
+
+    #[test]
+    fn repeated_subtrees_are_written_once() {
+        let mut universe = Hashlife::default();
+        for i in 0..4 {
+            for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                universe.set_cell(x + i * 64, y, 1);
+            }
+        }
+        let text = assert_round_trips(&universe);
+        assert_eq!(text.lines().filter(|line| line.ends_with('$')).count(), 1);
+    }
+
+    #[test]
+    fn rejects_malformed_nodes() {
+        let error = |text: &str| parse(text).unwrap_err();
+        assert_eq!(
+            error("[M2]\n0 0 0 0 0\n").kind,
+            ErrorKind::InvalidNode("level 0 is out of range".to_string())
+        );
+        assert_eq!(
+            error("[M2]\n4 1 0 0 0\n").kind,
+            ErrorKind::InvalidNode("node 1 is not defined yet".to_string())
+        );
+        assert_eq!(error("[M2]\n.*x$\n").kind, ErrorKind::UnexpectedChar('x'));
+        assert_eq!(
+            error("#Life 1.06\n").kind,
+            ErrorKind::InvalidHeader("expected '[M2]'".to_string())
+        );
+    }
+}
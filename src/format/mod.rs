@@ -0,0 +1,271 @@
+//! Readers and writers for the pattern file formats in common use, and a
+//! reader for Golly's rule files.
+//!
+//! [`parse`] works out the format of a file from its content, so patterns
+//! can be loaded whatever their file is called.
+
+pub mod life105;
+pub mod life106;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+pub mod rulefile;
+
+use std::{error::Error, fmt};
+
+use crate::engine::Engine;
+use crate::hashlife::Hashlife;
+use crate::pattern::Pattern;
+use crate::rule::ParseRuleError;
+
+/// The pattern file formats understood by [`parse`] and [`write`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+    Macrocell,
+}
+
+impl Format {
+    /// Conventional file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Rle => "rle",
+            Format::Plaintext => "cells",
+            Format::Life105 | Format::Life106 => "lif",
+            Format::Macrocell => "mc",
+        }
+    }
+}
+
+/// Guesses the format of a pattern file from its first meaningful lines.
+/// Anything unrecognised is assumed to be RLE, so that its parser reports
+/// what is wrong with it.
+pub fn detect(text: &str) -> Format {
+    let mut lines = text
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty());
+    let first = match lines.next() {
+        Some(first) => first,
+        None => return Format::Rle,
+    };
+    if first.starts_with(life106::HEADER) {
+        return Format::Life106;
+    }
+    if first.starts_with(life105::HEADER) {
+        return Format::Life105;
+    }
+    if first.starts_with(macrocell::HEADER) {
+        return Format::Macrocell;
+    }
+    if first.starts_with('!') {
+        return Format::Plaintext;
+    }
+    let body = match std::iter::once(first)
+        .chain(lines)
+        .find(|line| !line.starts_with('#'))
+    {
+        Some(body) => body,
+        None => return Format::Rle,
+    };
+    if body.starts_with('x') && body.contains('=') {
+        Format::Rle
+    } else if body.chars().all(|c| matches!(c, '.' | 'O' | '*')) {
+        Format::Plaintext
+    } else if parse_coordinates(1, 1, body).is_ok() {
+        Format::Life106
+    } else {
+        Format::Rle
+    }
+}
+
+/// Reads a pattern file in any supported format.
+///
+/// Macrocell files are flattened into a list of cells, which can be
+/// enormous; read them with [`macrocell::parse`] to keep them as a tree.
+pub fn parse(text: &str) -> Result<Pattern, ParseError> {
+    match detect(text) {
+        Format::Macrocell => {
+            let universe = macrocell::parse(text)?;
+            Ok(Pattern {
+                rule: Some(universe.rule().to_string()),
+                cells: universe.live_cells(),
+                ..Pattern::default()
+            })
+        }
+        Format::Rle => rle::parse(text),
+        Format::Plaintext => plaintext::parse(text),
+        Format::Life105 => life105::parse(text),
+        Format::Life106 => life106::parse(text),
+    }
+}
+
+/// Writes `pattern` in `format`.
+///
+/// # Panics
+///
+/// Panics when writing Macrocell for a pattern whose rule Hashlife cannot
+/// run.
+pub fn write(format: Format, pattern: &Pattern) -> String {
+    match format {
+        Format::Macrocell => {
+            let rule = pattern
+                .rule
+                .as_deref()
+                .and_then(|rule| rule.parse().ok())
+                .unwrap_or_default();
+            let mut universe = Hashlife::new(rule);
+            universe.load(pattern);
+            macrocell::write(&universe)
+        }
+        Format::Rle => rle::write(pattern),
+        Format::Plaintext => plaintext::write(pattern),
+        Format::Life105 => life105::write(pattern),
+        Format::Life106 => life106::write(pattern),
+    }
+}
+
+/// A pattern file that could not be read, with the 1-based line and column
+/// of the offending text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+/// What was wrong with a pattern file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The file ended before the header line.
+    MissingHeader,
+    /// A malformed field in the header line.
+    InvalidHeader(String),
+    /// A number that is malformed or too large.
+    InvalidNumber,
+    /// A character that has no meaning at this point of the file.
+    UnexpectedChar(char),
+    /// A multi-state cell letter that names no state.
+    InvalidState,
+    /// A line that should hold an `x y` pair but does not.
+    ExpectedCoordinates,
+    /// A rulestring the engine cannot run.
+    InvalidRule(ParseRuleError),
+    /// A malformed Macrocell or rule tree node line.
+    InvalidNode(String),
+    /// A rule table line that cannot be read.
+    InvalidTransition(String),
+}
+
+impl ParseError {
+    pub(crate) fn new(line: usize, column: usize, kind: ErrorKind) -> Self {
+        ParseError { line, column, kind }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ErrorKind::MissingHeader => write!(f, "missing header line"),
+            ErrorKind::InvalidHeader(reason) => write!(f, "invalid header: {}", reason),
+            ErrorKind::InvalidNumber => write!(f, "invalid number"),
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected '{}'", c),
+            ErrorKind::InvalidState => write!(f, "invalid cell state"),
+            ErrorKind::ExpectedCoordinates => write!(f, "expected 'x y' coordinates"),
+            ErrorKind::InvalidRule(e) => write!(f, "invalid rule: {}", e),
+            ErrorKind::InvalidNode(reason) => write!(f, "invalid node: {}", reason),
+            ErrorKind::InvalidTransition(reason) => write!(f, "invalid transition: {}", reason),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parses an `x y` pair of integers separated by whitespace, as used by the
+/// Life 1.05 `#P` lines and Life 1.06 bodies. `column` is where `text`
+/// starts on its line.
+fn parse_coordinates(line: usize, column: usize, text: &str) -> Result<(i64, i64), ParseError> {
+    let mut fields = text.split_whitespace();
+    let mut coordinate = || {
+        let field = fields
+            .next()
+            .ok_or_else(|| ParseError::new(line, column, ErrorKind::ExpectedCoordinates))?;
+        let offset = field.as_ptr() as usize - text.as_ptr() as usize;
+        field
+            .parse()
+            .map_err(|_| ParseError::new(line, column + offset, ErrorKind::InvalidNumber))
+    };
+    let position = (coordinate()?, coordinate()?);
+    match fields.next() {
+        Some(_) => Err(ParseError::new(
+            line,
+            column,
+            ErrorKind::ExpectedCoordinates,
+        )),
+        None => Ok(position),
+    }
+}
+
+/// Renders the cells of `pattern` as text rows covering its bounding box,
+/// with trailing dead cells trimmed.
+fn rows(pattern: &Pattern, dead: char, alive: char) -> Vec<String> {
+    let bounds = match pattern.bounds() {
+        Some(bounds) => bounds,
+        None => return Vec::new(),
+    };
+    let mut rows = vec![Vec::new(); bounds.height as usize];
+    for &(x, y, state) in &pattern.cells {
+        if state != 0 {
+            let row = &mut rows[(y - bounds.top) as usize];
+            let x = (x - bounds.left) as usize;
+            if row.len() <= x {
+                row.resize(x + 1, dead);
+            }
+            row[x] = alive;
+        }
+    }
+    rows.into_iter()
+        .map(|row| row.into_iter().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_from_content() {
+        assert_eq!(detect("#N Glider\nx = 3, y = 3\nbo$2bo$3o!"), Format::Rle);
+        assert_eq!(detect("!Name: Glider\n.O\n..O\nOOO"), Format::Plaintext);
+        assert_eq!(detect(".O\n..O\nOOO"), Format::Plaintext);
+        assert_eq!(detect("#Life 1.05\n#P 0 0\n*"), Format::Life105);
+        assert_eq!(detect("#Life 1.06\n0 0"), Format::Life106);
+        assert_eq!(detect("0 0\n1 1"), Format::Life106);
+        assert_eq!(detect("[M2] (golly 4.0)\n"), Format::Macrocell);
+        assert_eq!(detect(""), Format::Rle);
+    }
+
+    #[test]
+    fn every_format_keeps_the_cells() {
+        let pattern = Pattern {
+            cells: vec![(0, 0, 1), (3, 1, 1), (1, 4, 1)],
+            rule: Some("B3/S23".to_string()),
+            ..Pattern::default()
+        };
+        for format in [
+            Format::Rle,
+            Format::Plaintext,
+            Format::Life105,
+            Format::Life106,
+            Format::Macrocell,
+        ] {
+            let mut cells = parse(&write(format, &pattern)).unwrap().cells;
+            cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+            assert_eq!(cells, pattern.cells, "{:?}", format);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! The plaintext `.cells` format: `!` comment lines followed by rows of
+//! `.` for dead and `O` for live cells.
+//!
+//! ```text
+//! !Name: Glider
+//! .O
+//! ..O
+//! OOO
+//! ```
+
+use super::{ErrorKind, ParseError};
+use crate::pattern::Pattern;
+
+/// Reads a `.cells` file. `*` is accepted as a live cell as well.
+pub fn parse(text: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+    for (index, line) in text.lines().enumerate() {
+        if let Some(comment) = line.strip_prefix('!') {
+            let comment = comment.trim();
+            if let Some(name) = comment.strip_prefix("Name:") {
+                pattern.name = Some(name.trim().to_string());
+            } else if let Some(author) = comment.strip_prefix("Author:") {
+                pattern.author = Some(author.trim().to_string());
+            } else {
+                pattern.comments.push(comment.to_string());
+            }
+            continue;
+        }
+        for (x, c) in line.trim_end().chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | '*' => pattern.cells.push((x as i64, y, 1)),
+                _ => {
+                    return Err(ParseError::new(
+                        index + 1,
+                        x + 1,
+                        ErrorKind::UnexpectedChar(c),
+                    ))
+                }
+            }
+        }
+        y += 1;
+    }
+    Ok(pattern)
+}
+
+/// Writes `pattern` as `.cells`, moving its top-left corner to the origin.
+/// The format has no room for a rule or for more than two states, so every
+/// non-zero cell is written as live.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("!Name: {}\n", name));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("!Author: {}\n", author));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("!{}\n", comment));
+    }
+    for row in super::rows(pattern, '.', 'O') {
+        out.push_str(&row);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str =
+        "!Name: Glider\n!Author: Richard K. Guy\n!The smallest spaceship.\n.O\n..O\nOOO\n";
+
+    #[test]
+    fn round_trips() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.author.as_deref(), Some("Richard K. Guy"));
+        assert_eq!(pattern.comments, ["The smallest spaceship."]);
+        assert_eq!(pattern.cells.len(), 5);
+        assert_eq!(write(&pattern), GLIDER);
+    }
+
+    #[test]
+    fn blank_rows_count() {
+        let pattern = parse("O\n\n*").unwrap();
+        assert_eq!(pattern.cells, [(0, 0, 1), (0, 2, 1)]);
+    }
+
+    #[test]
+    fn rejects_other_characters() {
+        let error = parse(".O\n.x").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 2));
+        assert_eq!(error.kind, ErrorKind::UnexpectedChar('x'));
+    }
+}
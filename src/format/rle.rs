@@ -0,0 +1,451 @@
+//! The run-length encoded format used by Golly and LifeWiki.
+//!
+//! ```text
+//! #N Glider
+//! #C The smallest spaceship.
+//! x = 3, y = 3, rule = B3/S23
+//! bob$2bo$3o!
+//! ```
+//!
+//! Two-state patterns use `b` and `o`; multi-state patterns use `.` for
+//! state 0, `A`..`X` for states 1-24 and a `p`..`y` prefix for the states
+//! above that, up to `yO` for 255.
+
+use std::convert::TryFrom;
+
+use super::{ErrorKind, ParseError};
+use crate::pattern::Pattern;
+
+/// Longest line the writer produces, as recommended by the format.
+const LINE_WIDTH: usize = 70;
+
+/// Longest run of live cells the reader accepts. Each cell of a run is
+/// stored, so a corrupt count would otherwise exhaust memory; runs of dead
+/// cells only move the position and may be as long as fits.
+const MAX_LIVE_RUN: i64 = 1 << 20;
+
+/// Reads an RLE file.
+pub fn parse(text: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line));
+
+    loop {
+        let (number, line) = lines.next().ok_or_else(|| {
+            ParseError::new(text.lines().count() + 1, 1, ErrorKind::MissingHeader)
+        })?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            parse_comment(trimmed, &mut pattern);
+            continue;
+        }
+        parse_header(number, line, &mut pattern)?;
+        break;
+    }
+
+    let mut body = Body::default();
+    for (number, line) in lines {
+        if line.trim_start().starts_with('#') {
+            parse_comment(line.trim(), &mut pattern);
+            continue;
+        }
+        if body.parse_line(number, line, &mut pattern)? {
+            break;
+        }
+    }
+    Ok(pattern)
+}
+
+/// Writes `pattern` as RLE, moving its top-left corner to the origin.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("#O {}\n", author));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+
+    let bounds = pattern.bounds();
+    let (left, top, width, height) =
+        bounds.map_or((0, 0, 0, 0), |b| (b.left, b.top, b.width, b.height));
+    out.push_str(&format!("x = {}, y = {}", width, height));
+    if let Some(rule) = &pattern.rule {
+        out.push_str(&format!(", rule = {}", rule));
+    }
+    out.push('\n');
+
+    let multi_state = pattern.max_state() > 1;
+    let mut cells = pattern.cells.clone();
+    cells.sort_by_key(|&(x, y, _)| (y, x));
+
+    let mut writer = LineWriter::default();
+    let (mut row, mut column) = (0, 0);
+    let mut run: Option<(u8, u64)> = None;
+    for &(x, y, state) in &cells {
+        if state == 0 {
+            continue;
+        }
+        let (x, y) = (x - left, y - top);
+        if y > row {
+            writer.flush_run(run.take(), multi_state);
+            writer.push(run_token((y - row) as u64, "$"));
+            row = y;
+            column = 0;
+        }
+        if x > column {
+            writer.flush_run(run.take(), multi_state);
+            writer.push(run_token((x - column) as u64, state_tag(0, multi_state)));
+        }
+        run = match run {
+            Some((run_state, length)) if run_state == state && x == column => {
+                Some((state, length + 1))
+            }
+            other => {
+                writer.flush_run(other, multi_state);
+                Some((state, 1))
+            }
+        };
+        column = x + 1;
+    }
+    writer.flush_run(run, multi_state);
+    writer.push("!".to_string());
+    out.push_str(&writer.finish());
+    out
+}
+
+fn parse_comment(line: &str, pattern: &mut Pattern) {
+    let mut chars = line[1..].chars();
+    let kind = chars.next();
+    let text = chars.as_str().trim().to_string();
+    match kind {
+        Some('N') => pattern.name = Some(text),
+        Some('O') => pattern.author = Some(text),
+        Some('r') => pattern.rule = Some(text),
+        Some('C') | Some('c') => pattern.comments.push(text),
+        _ => pattern.comments.push(line[1..].trim().to_string()),
+    }
+}
+
+/// Parses `x = 3, y = 3, rule = B3/S23`. The size fields are required and
+/// checked, but cells outside the declared size are still accepted. The
+/// rule runs to the end of the line, since rulestrings such as
+/// `B3/S23:T64,64` contain commas of their own.
+fn parse_header(number: usize, line: &str, pattern: &mut Pattern) -> Result<(), ParseError> {
+    let mut seen_x = false;
+    let mut seen_y = false;
+    let mut offset = 0;
+    for field in line.split(',') {
+        let start = offset;
+        let column = offset + field.len() - field.trim_start().len() + 1;
+        offset += field.len() + 1;
+        let field = field.trim();
+        let error = |reason: &str| {
+            ParseError::new(number, column, ErrorKind::InvalidHeader(reason.to_string()))
+        };
+        let equals = field
+            .find('=')
+            .ok_or_else(|| error("expected 'key = value'"))?;
+        let key = field[..equals].trim();
+        let value = field[equals + 1..].trim();
+        match key {
+            "x" | "y" => {
+                if value.parse::<u64>().is_err() {
+                    let value_column =
+                        column + field.len() - field[equals + 1..].trim_start().len();
+                    return Err(ParseError::new(
+                        number,
+                        value_column,
+                        ErrorKind::InvalidNumber,
+                    ));
+                }
+                if key == "x" {
+                    seen_x = true;
+                } else {
+                    seen_y = true;
+                }
+            }
+            "rule" => {
+                let value = &line[start..];
+                let value = &value[value.find('=').unwrap_or(0) + 1..];
+                pattern.rule = Some(value.trim().to_string());
+                break;
+            }
+            _ => return Err(error(&format!("unknown field '{}'", key))),
+        }
+    }
+    match (seen_x, seen_y) {
+        (true, true) => Ok(()),
+        (false, _) => Err(ParseError::new(
+            number,
+            1,
+            ErrorKind::InvalidHeader("missing 'x'".into()),
+        )),
+        (_, false) => Err(ParseError::new(
+            number,
+            1,
+            ErrorKind::InvalidHeader("missing 'y'".into()),
+        )),
+    }
+}
+
+/// Decoding state carried across the lines of the pattern body.
+#[derive(Default)]
+struct Body {
+    x: i64,
+    y: i64,
+    count: Option<u64>,
+    /// Column of the first digit of `count`, for error reporting.
+    count_column: usize,
+}
+
+impl Body {
+    /// Decodes one line of runs, returning `true` once `!` has been read.
+    fn parse_line(
+        &mut self,
+        number: usize,
+        line: &str,
+        pattern: &mut Pattern,
+    ) -> Result<bool, ParseError> {
+        let mut chars = line
+            .chars()
+            .enumerate()
+            .map(|(index, c)| (index + 1, c))
+            .peekable();
+        while let Some((column, c)) = chars.next() {
+            let state = match c {
+                '0'..='9' => {
+                    if self.count.is_none() {
+                        self.count_column = column;
+                    }
+                    let digit = u64::from(c as u8 - b'0');
+                    self.count = Some(
+                        self.count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(digit))
+                            .ok_or_else(|| {
+                                ParseError::new(number, self.count_column, ErrorKind::InvalidNumber)
+                            })?,
+                    );
+                    continue;
+                }
+                c if c.is_whitespace() => continue,
+                '!' => return Ok(true),
+                '$' => {
+                    let count = self.take_count(number)?;
+                    self.y = self
+                        .y
+                        .checked_add(count)
+                        .ok_or_else(|| self.overflow(number))?;
+                    self.x = 0;
+                    continue;
+                }
+                'b' | '.' => 0,
+                'o' => 1,
+                'A'..='X' => c as u8 - b'A' + 1,
+                'p'..='y' => match chars.next() {
+                    Some((_, low @ 'A'..='X')) => {
+                        let high = u32::from(c as u8 - b'p' + 1);
+                        let state = high * 24 + u32::from(low as u8 - b'A') + 1;
+                        if state > 255 {
+                            return Err(ParseError::new(number, column, ErrorKind::InvalidState));
+                        }
+                        state as u8
+                    }
+                    _ => return Err(ParseError::new(number, column, ErrorKind::InvalidState)),
+                },
+                _ => {
+                    return Err(ParseError::new(
+                        number,
+                        column,
+                        ErrorKind::UnexpectedChar(c),
+                    ))
+                }
+            };
+            let count = self.take_count(number)?;
+            if state != 0 && count > MAX_LIVE_RUN {
+                return Err(self.overflow(number));
+            }
+            let end = self
+                .x
+                .checked_add(count)
+                .ok_or_else(|| self.overflow(number))?;
+            if state != 0 {
+                pattern
+                    .cells
+                    .extend((self.x..end).map(|x| (x, self.y, state)));
+            }
+            self.x = end;
+        }
+        Ok(false)
+    }
+
+    /// The count read before a tag, 1 if there was none.
+    fn take_count(&mut self, number: usize) -> Result<i64, ParseError> {
+        match self.count.take() {
+            None => Ok(1),
+            Some(count) => i64::try_from(count).map_err(|_| self.overflow(number)),
+        }
+    }
+
+    /// An error for a count on line `number` too large to use.
+    fn overflow(&self, number: usize) -> ParseError {
+        ParseError::new(number, self.count_column, ErrorKind::InvalidNumber)
+    }
+}
+
+/// Collects run tokens into lines no longer than [`LINE_WIDTH`].
+#[derive(Default)]
+struct LineWriter {
+    out: String,
+    line_length: usize,
+}
+
+impl LineWriter {
+    fn push(&mut self, token: String) {
+        if self.line_length + token.len() > LINE_WIDTH {
+            self.out.push('\n');
+            self.line_length = 0;
+        }
+        self.line_length += token.len();
+        self.out.push_str(&token);
+    }
+
+    fn flush_run(&mut self, run: Option<(u8, u64)>, multi_state: bool) {
+        if let Some((state, length)) = run {
+            self.push(run_token(length, state_tag(state, multi_state)));
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push('\n');
+        self.out
+    }
+}
+
+fn run_token(length: u64, tag: impl AsRef<str>) -> String {
+    if length == 1 {
+        tag.as_ref().to_string()
+    } else {
+        format!("{}{}", length, tag.as_ref())
+    }
+}
+
+fn state_tag(state: u8, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (state, true) => {
+            let high = (state - 1) / 24;
+            let low = (b'A' + (state - 1) % 24) as char;
+            if high == 0 {
+                low.to_string()
+            } else {
+                format!("{}{}", (b'p' + high - 1) as char, low)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str =
+        "#N Glider\n#C The smallest spaceship.\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+
+    #[test]
+    fn parses_header_comments_and_cells() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, ["The smallest spaceship."]);
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(
+            pattern.cells,
+            [(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1)]
+        );
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        assert_eq!(write(&parse(GLIDER).unwrap()), GLIDER);
+    }
+
+    #[test]
+    fn rule_keeps_its_commas() {
+        let pattern = parse("x = 1, y = 1, rule = B3/S23:T64,64\no!").unwrap();
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23:T64,64"));
+    }
+
+    #[test]
+    fn multi_state_round_trips() {
+        let pattern = Pattern {
+            cells: vec![(0, 0, 1), (1, 0, 24), (3, 0, 25), (0, 2, 255)],
+            ..Pattern::default()
+        };
+        let text = write(&pattern);
+        assert!(text.contains("AX.pA2$yO!"), "{}", text);
+        assert_eq!(parse(&text).unwrap().cells, pattern.cells);
+    }
+
+    #[test]
+    fn long_runs_wrap_and_moves_to_the_origin() {
+        let pattern = Pattern {
+            cells: (0..100).map(|i| (i * 2 - 50, i % 3 - 7, 1)).collect(),
+            ..Pattern::default()
+        };
+        let text = write(&pattern);
+        assert!(text.lines().all(|line| line.len() <= LINE_WIDTH));
+        let mut cells = parse(&text).unwrap().cells;
+        let mut expected: Vec<_> = pattern
+            .cells
+            .iter()
+            .map(|&(x, y, state)| (x + 50, y + 7, state))
+            .collect();
+        cells.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        let error = parse("#C only a comment\n").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::MissingHeader);
+        let error = parse("x = 3, y = 3\nbo$2bz!").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 6));
+        assert_eq!(error.kind, ErrorKind::UnexpectedChar('z'));
+        let error = parse("x = 3, y = three\n").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidNumber);
+        let error = parse("x = 1, y = 1\n9223372036854775807o9o!").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+        assert_eq!(error.kind, ErrorKind::InvalidNumber);
+        let error = parse("x = 1, y = 1\nbo$4000000000o!").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 4));
+        assert_eq!(error.kind, ErrorKind::InvalidNumber);
+        let error = parse("x = 1, y = 1\n9223372036854775807$9$o!").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 21));
+        let error = parse("x = 1, y = 1\n99999999999999999999b!").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+        // Long gaps are fine, as they take no room.
+        let pattern = parse("x = 1, y = 1\n4000000000bo$4000000000$o!").unwrap();
+        assert_eq!(
+            pattern.cells,
+            [(4_000_000_000, 0, 1), (0, 4_000_000_001, 1)]
+        );
+        let error = parse("x = 3\n").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ErrorKind::InvalidHeader("missing 'y'".to_string())
+        );
+    }
+}
@@ -0,0 +1,774 @@
+//! Golly's `.rule` files, which define a rule by a table of transitions or
+//! a decision tree, and may give each state a colour.
+//!
+//! ```text
+//! @RULE WireWorld
+//! @TABLE
+//! n_states:4
+//! neighborhood:Moore
+//! symmetries:permute
+//! var a={0,1,2,3}
+//! var b={0,1,2,3}
+//! ...
+//! 1,2,a,b,c,d,e,f,g,h,2
+//! @COLORS
+//! 1 255 255 0
+//! ```
+//!
+//! A `@TABLE` line lists the states a transition needs for the cell and
+//! then for each neighbour clockwise from north, followed by the cell's new
+//! state. A variable used more than once in a line takes the same value
+//! everywhere in it. Each line also stands for its rotations or reflections
+//! as the `symmetries` line says. The first line that matches wins, and
+//! cells no line matches keep their state.
+//!
+//! A `@TREE` has one node per line, `level child...` with a child for each
+//! state, where level 1 children are new states and the others number
+//! earlier nodes from 0. The last node is the root, whose children are
+//! picked by the first neighbour.
+//!
+//! `@COLORS` lines are either `state red green blue` or a gradient over the
+//! live states, `red green blue red green blue`, and `@NAMES` lines are
+//! `state name`. Other sections such as `@ICONS` are skipped.
+
+use std::collections::HashMap;
+
+use super::{ErrorKind, ParseError};
+use crate::rule::{Rule, RuleTable, TableNeighbourhood, Transition};
+
+pub const HEADER: &str = "@RULE";
+
+/// Reads a `.rule` file into a rule.
+pub fn parse(text: &str) -> Result<Rule, ParseError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, strip_comment(line).trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let name = match lines.next() {
+        Some((number, line)) => match line.strip_prefix(HEADER) {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => {
+                return Err(ParseError::new(
+                    number,
+                    1,
+                    ErrorKind::InvalidHeader(format!("expected '{} name'", HEADER)),
+                ))
+            }
+        },
+        None => return Err(ParseError::new(1, 1, ErrorKind::MissingHeader)),
+    };
+
+    let mut sections: HashMap<&str, Vec<(usize, &str)>> = HashMap::new();
+    let mut current = None;
+    let mut last = 1;
+    for (number, line) in lines {
+        last = number;
+        if line.starts_with('@') {
+            let section = line.split_whitespace().next().unwrap();
+            current = Some(section);
+            sections.entry(section).or_default();
+        } else if let Some(section) = current {
+            sections.get_mut(section).unwrap().push((number, line));
+        }
+    }
+
+    let mut table = match (sections.get("@TABLE"), sections.get("@TREE")) {
+        (Some(lines), _) => parse_table(name, lines)?,
+        (None, Some(lines)) => parse_tree(name, lines)?,
+        (None, None) => {
+            return Err(ParseError::new(
+                last,
+                1,
+                ErrorKind::InvalidHeader("no @TABLE or @TREE section".to_string()),
+            ))
+        }
+    };
+    if let Some(lines) = sections.get("@COLORS") {
+        parse_colors(&mut table, lines)?;
+    }
+    for &(number, line) in sections.get("@NAMES").into_iter().flatten() {
+        let (state, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match state.parse::<u8>() {
+            Ok(state) if u16::from(state) < table.states() && !name.trim().is_empty() => {
+                table.set_name(state, name.trim().to_string())
+            }
+            _ => return Err(ParseError::new(number, 1, ErrorKind::InvalidState)),
+        }
+    }
+    Ok(Rule::from_table(table))
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap()
+}
+
+/// The value of a `key:value` or `key=value` setting line, if `line` is one
+/// for `key`.
+fn setting<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(key)?.trim_start();
+    rest.strip_prefix([':', '=']).map(str::trim)
+}
+
+fn parse_states(number: usize, value: &str) -> Result<u16, ParseError> {
+    match value.parse() {
+        Ok(states) if (2..=256).contains(&states) => Ok(states),
+        _ => Err(ParseError::new(
+            number,
+            1,
+            ErrorKind::InvalidHeader(format!("state count '{}' is out of range 2-256", value)),
+        )),
+    }
+}
+
+/// One entry of a transition line before its variables are bound.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    State(u8),
+    Variable(String),
+    /// An inline `{...}` list, which is never bound.
+    Set(Vec<u8>),
+}
+
+fn parse_table(name: String, lines: &[(usize, &str)]) -> Result<RuleTable, ParseError> {
+    let mut states = None;
+    let mut neighbourhood = None;
+    let mut symmetries = None;
+    let mut variables: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut transitions = Vec::new();
+    for &(number, line) in lines {
+        if let Some(value) = setting(line, "n_states") {
+            states = Some(parse_states(number, value)?);
+        } else if let Some(value) = setting(line, "neighborhood") {
+            neighbourhood = Some(match value {
+                "Moore" => TableNeighbourhood::Moore,
+                "vonNeumann" => TableNeighbourhood::VonNeumann,
+                "hexagonal" => TableNeighbourhood::Hexagonal,
+                _ => {
+                    return Err(ParseError::new(
+                        number,
+                        1,
+                        ErrorKind::InvalidHeader(format!("unsupported neighborhood '{}'", value)),
+                    ))
+                }
+            });
+        } else if let Some(value) = setting(line, "symmetries") {
+            symmetries = Some((number, value));
+        } else {
+            let invalid = |reason: &str| {
+                ParseError::new(number, 1, ErrorKind::InvalidTransition(reason.to_string()))
+            };
+            let states = states.ok_or_else(|| invalid("n_states must come first"))?;
+            if let Some(definition) = line.strip_prefix("var ") {
+                let (variable, values) = definition
+                    .split_once('=')
+                    .ok_or_else(|| invalid("expected 'var name={...}'"))?;
+                let values = parse_set(number, values.trim(), states, &variables)?;
+                variables.insert(variable.trim().to_string(), values);
+            } else {
+                let neighbourhood =
+                    neighbourhood.ok_or_else(|| invalid("neighborhood must come first"))?;
+                let symmetries = match symmetries {
+                    Some((number, value)) => {
+                        Symmetries::new(neighbourhood, value).ok_or_else(|| {
+                            ParseError::new(
+                                number,
+                                1,
+                                ErrorKind::InvalidHeader(format!(
+                                    "unsupported symmetries '{}'",
+                                    value
+                                )),
+                            )
+                        })?
+                    }
+                    None => return Err(invalid("symmetries must come first")),
+                };
+                let entries = parse_entries(number, line, states, &variables)?;
+                if entries.len() != neighbourhood.clockwise().len() + 2 {
+                    return Err(invalid(&format!(
+                        "expected {} entries, found {}",
+                        neighbourhood.clockwise().len() + 2,
+                        entries.len()
+                    )));
+                }
+                for transition in bind(number, &entries, &variables)? {
+                    symmetries.expand(transition, &mut transitions);
+                }
+            }
+        }
+    }
+    let missing = |what: &str| {
+        let number = lines.first().map_or(1, |&(number, _)| number);
+        ParseError::new(
+            number,
+            1,
+            ErrorKind::InvalidHeader(format!("missing {}", what)),
+        )
+    };
+    Ok(RuleTable::from_transitions(
+        name,
+        states.ok_or_else(|| missing("n_states"))?,
+        neighbourhood.ok_or_else(|| missing("neighborhood"))?,
+        &transitions,
+    ))
+}
+
+/// Parses a state number or a variable name.
+fn parse_entry(
+    number: usize,
+    column: usize,
+    text: &str,
+    states: u16,
+    variables: &HashMap<String, Vec<u8>>,
+) -> Result<Entry, ParseError> {
+    if text.starts_with('{') {
+        return Ok(Entry::Set(parse_set(number, text, states, variables)?));
+    }
+    if text.starts_with(|c: char| c.is_ascii_digit()) {
+        return match text.parse::<u16>() {
+            Ok(state) if state < states => Ok(Entry::State(state as u8)),
+            Ok(_) => Err(ParseError::new(number, column, ErrorKind::InvalidState)),
+            Err(_) => Err(ParseError::new(number, column, ErrorKind::InvalidNumber)),
+        };
+    }
+    if variables.contains_key(text) {
+        Ok(Entry::Variable(text.to_string()))
+    } else {
+        Err(ParseError::new(
+            number,
+            column,
+            ErrorKind::InvalidTransition(format!("unknown variable '{}'", text)),
+        ))
+    }
+}
+
+/// Parses a `{...}` list of states and variables into the states it holds.
+fn parse_set(
+    number: usize,
+    text: &str,
+    states: u16,
+    variables: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<u8>, ParseError> {
+    let inner = text
+        .strip_prefix('{')
+        .and_then(|text| text.strip_suffix('}'))
+        .ok_or_else(|| {
+            ParseError::new(
+                number,
+                1,
+                ErrorKind::InvalidTransition(format!("expected '{{...}}', found '{}'", text)),
+            )
+        })?;
+    let mut values = Vec::new();
+    for item in inner.split(',').map(str::trim) {
+        match parse_entry(number, 1, item, states, variables)? {
+            Entry::State(state) => values.push(state),
+            Entry::Variable(name) => values.extend(&variables[&name]),
+            Entry::Set(set) => values.extend(set),
+        }
+    }
+    Ok(values)
+}
+
+/// Splits a transition line into its entries: comma-separated, or one
+/// state digit per character when there are no commas.
+fn parse_entries(
+    number: usize,
+    line: &str,
+    states: u16,
+    variables: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<Entry>, ParseError> {
+    if !line.contains([',', '{']) {
+        return line
+            .char_indices()
+            .filter(|(_, c)| !c.is_whitespace())
+            .map(|(offset, c)| match c.to_digit(10) {
+                Some(state) if state < u32::from(states) => Ok(Entry::State(state as u8)),
+                Some(_) => Err(ParseError::new(number, offset + 1, ErrorKind::InvalidState)),
+                None => Err(ParseError::new(
+                    number,
+                    offset + 1,
+                    ErrorKind::UnexpectedChar(c),
+                )),
+            })
+            .collect();
+    }
+    let mut entries = Vec::new();
+    let (mut start, mut depth) = (0, 0);
+    for (offset, c) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ',')))
+    {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                let text = &line[start..offset];
+                let column = start + text.len() - text.trim_start().len() + 1;
+                entries.push(parse_entry(number, column, text.trim(), states, variables)?);
+                start = offset + 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// Expands the variables used more than once in a line into every
+/// combination of their values, giving transitions with the cell's input
+/// first.
+fn bind(
+    number: usize,
+    entries: &[Entry],
+    variables: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<Transition>, ParseError> {
+    let mut bound: Vec<&str> = Vec::new();
+    for entry in entries {
+        if let Entry::Variable(name) = entry {
+            let uses = entries.iter().filter(|&other| other == entry).count();
+            if uses > 1 && !bound.contains(&name.as_str()) {
+                bound.push(name);
+            }
+        }
+    }
+    let (inputs, output) = entries.split_at(entries.len() - 1);
+    let mut transitions = Vec::new();
+    let mut choice = vec![0; bound.len()];
+    loop {
+        let value = |name: &str| -> Option<u8> {
+            let index = bound.iter().position(|&bound| bound == name)?;
+            Some(variables[name][choice[index]])
+        };
+        let output = match &output[0] {
+            Entry::State(state) => *state,
+            Entry::Variable(name) => value(name).ok_or_else(|| {
+                ParseError::new(
+                    number,
+                    1,
+                    ErrorKind::InvalidTransition(format!(
+                        "output variable '{}' does not appear in the inputs",
+                        name
+                    )),
+                )
+            })?,
+            Entry::Set(_) => {
+                return Err(ParseError::new(
+                    number,
+                    1,
+                    ErrorKind::InvalidTransition("output cannot be a list".to_string()),
+                ))
+            }
+        };
+        transitions.push(Transition {
+            inputs: inputs
+                .iter()
+                .map(|entry| match entry {
+                    Entry::State(state) => vec![*state],
+                    Entry::Variable(name) => {
+                        value(name).map_or_else(|| variables[name].clone(), |value| vec![value])
+                    }
+                    Entry::Set(set) => set.clone(),
+                })
+                .collect(),
+            output,
+        });
+        // Step to the next combination, like an odometer.
+        let mut index = 0;
+        loop {
+            if index == bound.len() {
+                return Ok(transitions);
+            }
+            choice[index] += 1;
+            if choice[index] < variables[bound[index]].len() {
+                break;
+            }
+            choice[index] = 0;
+            index += 1;
+        }
+    }
+}
+
+/// The rearrangements of the neighbours a transition stands for.
+enum Symmetries {
+    /// Each permutation gives, for every position clockwise from north, the
+    /// position whose input moves there.
+    Permutations(Vec<Vec<usize>>),
+    /// Every order of the neighbours.
+    Permute,
+}
+
+impl Symmetries {
+    fn new(neighbourhood: TableNeighbourhood, name: &str) -> Option<Self> {
+        use TableNeighbourhood::{Hexagonal, Moore, VonNeumann};
+        let (rotations, reflect) = match (neighbourhood, name) {
+            (_, "permute") => return Some(Symmetries::Permute),
+            (_, "none") => (1, false),
+            (Moore | VonNeumann, "reflect_horizontal") => (1, true),
+            (Moore | VonNeumann, "rotate4") => (4, false),
+            (Moore | VonNeumann, "rotate4reflect") => (4, true),
+            (Moore, "rotate8") => (8, false),
+            (Moore, "rotate8reflect") => (8, true),
+            (Hexagonal, "rotate2") => (2, false),
+            (Hexagonal, "rotate3") => (3, false),
+            (Hexagonal, "rotate6") => (6, false),
+            (Hexagonal, "rotate6reflect") => (6, true),
+            _ => return None,
+        };
+        let size = neighbourhood.clockwise().len();
+        let step = size / rotations;
+        let mut permutations = Vec::new();
+        for rotation in 0..rotations {
+            let rotated: Vec<usize> = (0..size).map(|i| (i + rotation * step) % size).collect();
+            if reflect {
+                permutations.push((0..size).map(|i| rotated[(size - i) % size]).collect());
+            }
+            permutations.push(rotated);
+        }
+        Some(Symmetries::Permutations(permutations))
+    }
+
+    /// Adds `transition` and its distinct rearrangements to `out`.
+    fn expand(&self, transition: Transition, out: &mut Vec<Transition>) {
+        let (cell, neighbours) = transition.inputs.split_first().unwrap();
+        let mut variants: Vec<Vec<Vec<u8>>> = Vec::new();
+        match self {
+            Symmetries::Permutations(permutations) => {
+                for permutation in permutations {
+                    let variant = permutation.iter().map(|&i| neighbours[i].clone()).collect();
+                    if !variants.contains(&variant) {
+                        variants.push(variant);
+                    }
+                }
+            }
+            Symmetries::Permute => {
+                // Walk the orders of the neighbours' inputs by their indices
+                // among the distinct inputs, so that each comes up once.
+                let mut distinct: Vec<&Vec<u8>> = neighbours.iter().collect();
+                distinct.sort();
+                distinct.dedup();
+                let mut order: Vec<usize> = neighbours
+                    .iter()
+                    .map(|input| distinct.binary_search(&input).unwrap())
+                    .collect();
+                order.sort_unstable();
+                loop {
+                    variants.push(order.iter().map(|&i| distinct[i].clone()).collect());
+                    if !next_permutation(&mut order) {
+                        break;
+                    }
+                }
+            }
+        }
+        for neighbours in variants {
+            out.push(Transition {
+                inputs: std::iter::once(cell.clone()).chain(neighbours).collect(),
+                output: transition.output,
+            });
+        }
+    }
+}
+
+/// Rearranges `items` into the next greater order, returning false once
+/// they are in descending order.
+fn next_permutation(items: &mut [usize]) -> bool {
+    let pivot = match items.windows(2).rposition(|pair| pair[0] < pair[1]) {
+        Some(pivot) => pivot,
+        None => return false,
+    };
+    let successor = items.iter().rposition(|&item| item > items[pivot]).unwrap();
+    items.swap(pivot, successor);
+    items[pivot + 1..].reverse();
+    true
+}
+
+fn parse_tree(name: String, lines: &[(usize, &str)]) -> Result<RuleTable, ParseError> {
+    let mut states = None;
+    let mut neighbours = None;
+    let mut expected_nodes = None;
+    let mut levels: Vec<u32> = Vec::new();
+    let mut children = Vec::new();
+    for &(number, line) in lines {
+        if let Some(value) = setting(line, "num_states") {
+            states = Some(parse_states(number, value)?);
+            continue;
+        }
+        if let Some(value) = setting(line, "num_neighbors") {
+            neighbours = Some(match value {
+                "4" => TableNeighbourhood::VonNeumann,
+                "8" => TableNeighbourhood::Moore,
+                _ => {
+                    return Err(ParseError::new(
+                        number,
+                        1,
+                        ErrorKind::InvalidHeader(format!("unsupported num_neighbors '{}'", value)),
+                    ))
+                }
+            });
+            continue;
+        }
+        if let Some(value) = setting(line, "num_nodes") {
+            expected_nodes = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| ParseError::new(number, 1, ErrorKind::InvalidNumber))?,
+            );
+            continue;
+        }
+        let invalid = |reason: String| ParseError::new(number, 1, ErrorKind::InvalidNode(reason));
+        let states = states.ok_or_else(|| invalid("num_states must come first".to_string()))?;
+        let mut fields = line.split_whitespace().map(|field| {
+            field
+                .parse::<u32>()
+                .map_err(|_| ParseError::new(number, 1, ErrorKind::InvalidNumber))
+        });
+        let level = fields.next().unwrap()?;
+        let node: Vec<u32> = fields.collect::<Result<_, _>>()?;
+        if node.len() != usize::from(states) {
+            return Err(invalid(format!(
+                "expected {} children, found {}",
+                states,
+                node.len()
+            )));
+        }
+        for &child in &node {
+            let valid = match level {
+                0 => false,
+                1 => child < u32::from(states),
+                _ => levels.get(child as usize) == Some(&(level - 1)),
+            };
+            if !valid {
+                return Err(invalid(format!(
+                    "child {} does not fit a level {} node",
+                    child, level
+                )));
+            }
+        }
+        levels.push(level);
+        children.extend(node);
+    }
+
+    let first = lines.first().map_or(1, |&(number, _)| number);
+    let missing = |what: &str| {
+        ParseError::new(
+            first,
+            1,
+            ErrorKind::InvalidHeader(format!("missing {}", what)),
+        )
+    };
+    let states = states.ok_or_else(|| missing("num_states"))?;
+    let neighbourhood = neighbours.ok_or_else(|| missing("num_neighbors"))?;
+    let root_level = neighbourhood.clockwise().len() as u32 + 1;
+    if expected_nodes.is_some_and(|expected| expected != levels.len()) {
+        return Err(ParseError::new(
+            first,
+            1,
+            ErrorKind::InvalidNode(format!(
+                "expected {} nodes, found {}",
+                expected_nodes.unwrap(),
+                levels.len()
+            )),
+        ));
+    }
+    if levels.last() != Some(&root_level) {
+        return Err(ParseError::new(
+            first,
+            1,
+            ErrorKind::InvalidNode(format!("the last node must have level {}", root_level)),
+        ));
+    }
+    Ok(RuleTable::from_tree(name, states, neighbourhood, children))
+}
+
+fn parse_colors(table: &mut RuleTable, lines: &[(usize, &str)]) -> Result<(), ParseError> {
+    for &(number, line) in lines {
+        let values = line
+            .split_whitespace()
+            .map(|field| {
+                field
+                    .parse::<u8>()
+                    .map_err(|_| ParseError::new(number, 1, ErrorKind::InvalidNumber))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match values[..] {
+            [state, red, green, blue] => {
+                if u16::from(state) >= table.states() {
+                    return Err(ParseError::new(number, 1, ErrorKind::InvalidState));
+                }
+                table.set_color(state, (red, green, blue));
+            }
+            [r1, g1, b1, r2, g2, b2] => {
+                let last = table.states() - 1;
+                for state in 1..=last {
+                    let mix = |from: u8, to: u8| {
+                        let (from, to) = (i32::from(from), i32::from(to));
+                        let span = i32::from(last.max(2) - 1);
+                        (from + (to - from) * i32::from(state - 1) / span) as u8
+                    };
+                    table.set_color(state as u8, (mix(r1, r2), mix(g1, g2), mix(b1, b2)));
+                }
+            }
+            _ => {
+                return Err(ParseError::new(
+                    number,
+                    1,
+                    ErrorKind::InvalidHeader("expected 'state red green blue'".to_string()),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIREWORLD: &str = "\
+@RULE WireWorld
+# Electron heads are 1, tails 2 and wire 3.
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,i,j,k,l,m,n,a,1,1
+3,i,j,k,l,m,n,1,1,1
+@COLORS
+1 0 128 255
+2 255 255 255
+3 255 128 0
+@NAMES
+1 electron head
+";
+
+    /// Neighbours with the first `count` of north, east, south and west in
+    /// `state`.
+    fn around(state: u8, count: usize) -> [u8; 8] {
+        let mut neighbours = [0; 8];
+        for &i in &[1, 4, 6, 3][..count] {
+            neighbours[i] = state;
+        }
+        neighbours
+    }
+
+    #[test]
+    fn reads_wireworld() {
+        let rule = parse(WIREWORLD).unwrap();
+        assert_eq!(rule.to_string(), "WireWorld");
+        assert_eq!(rule.states(), 4);
+        let next = |state, neighbours| rule.next_state_with_states(state, neighbours);
+        assert_eq!(next(1, around(3, 2)), 2);
+        assert_eq!(next(2, around(1, 1)), 3);
+        assert_eq!(next(3, around(1, 0)), 3);
+        assert_eq!(next(3, around(1, 1)), 1);
+        assert_eq!(next(3, around(1, 2)), 1);
+        assert_eq!(next(3, around(1, 3)), 3);
+        assert_eq!(next(0, around(1, 2)), 0);
+        assert_eq!(rule.color(1), Some((0, 128, 255)));
+        assert_eq!(rule.state_name(1), "electron head");
+        assert_eq!(rule.state_name(2), "state 2");
+    }
+
+    #[test]
+    fn symmetries_expand_each_line() {
+        let table = |symmetries: &str| {
+            let text = format!(
+                "@RULE Test\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n\
+                 symmetries:{}\n0,1,0,0,0,1\n",
+                symmetries
+            );
+            parse(&text).unwrap()
+        };
+        // Only a live north neighbour, or one of its rotations, gives birth.
+        let none = table("none");
+        let rotate4 = table("rotate4");
+        for &i in &[1, 4, 6, 3] {
+            let mut neighbours = [0; 8];
+            neighbours[i] = 1;
+            assert_eq!(none.next_state_with_states(0, neighbours), u8::from(i == 1));
+            assert_eq!(rotate4.next_state_with_states(0, neighbours), 1);
+        }
+        assert_eq!(rotate4.next_state_with_states(0, around(1, 2)), 0);
+    }
+
+    #[test]
+    fn variables_used_twice_are_bound() {
+        let rule = parse(
+            "@RULE Pair\n@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:none\n\
+             var a={1,2}\n0,a,a,0,0,a\n",
+        )
+        .unwrap();
+        let next = |north, east| {
+            let mut neighbours = [0; 8];
+            neighbours[1] = north;
+            neighbours[4] = east;
+            rule.next_state_with_states(0, neighbours)
+        };
+        assert_eq!((next(1, 1), next(2, 2), next(1, 2)), (1, 2, 0));
+    }
+
+    #[test]
+    fn reads_trees() {
+        // A cell is alive next when exactly one of its four neighbours is.
+        let rule = parse(
+            "@RULE One\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=12\n\
+             1 0 0\n1 1 1\n2 0 1\n2 1 0\n2 0 0\n3 2 3\n3 3 4\n3 4 4\n\
+             4 5 6\n4 6 7\n4 7 7\n5 8 9\n@COLORS\n255 0 0 255 0 0\n",
+        )
+        .unwrap();
+        let next = |state, neighbours| rule.next_state_with_states(state, neighbours);
+        assert_eq!(next(0, around(1, 1)), 1);
+        assert_eq!(next(1, around(1, 0)), 0);
+        assert_eq!(next(1, around(1, 2)), 0);
+        let mut south = [0; 8];
+        south[6] = 1;
+        assert_eq!(next(0, south), 1);
+        assert_eq!(rule.color(1), Some((255, 0, 0)));
+    }
+
+    #[test]
+    fn reports_what_is_wrong() {
+        let kind = |text: &str| parse(text).unwrap_err().kind;
+        assert_eq!(kind(""), ErrorKind::MissingHeader);
+        assert!(matches!(kind("@RULE\n@TABLE"), ErrorKind::InvalidHeader(_)));
+        assert!(matches!(
+            kind("@RULE X\n@ICONS"),
+            ErrorKind::InvalidHeader(_)
+        ));
+        assert!(matches!(
+            kind("@RULE X\n@TABLE\nn_states:300"),
+            ErrorKind::InvalidHeader(_)
+        ));
+        assert!(matches!(
+            kind("@RULE X\n@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:spin\n0,1,1"),
+            ErrorKind::InvalidHeader(_)
+        ));
+        assert!(matches!(
+            kind("@RULE X\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:none\n0,1,1"),
+            ErrorKind::InvalidTransition(_)
+        ));
+        assert_eq!(
+            kind("@RULE X\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:none\n0,1,0,0,0,2"),
+            ErrorKind::InvalidState
+        );
+        assert!(matches!(
+            kind("@RULE X\n@TREE\nnum_states=2\nnum_neighbors=4\n1 0 0\n2 0 5"),
+            ErrorKind::InvalidNode(_)
+        ));
+    }
+}
@@ -0,0 +1,901 @@
+//! A fixed-size board of cells stepped with a Life-like rule, whose edges
+//! are joined according to the rule's topology.
+//!
+//! The board is split into square tiles, and a step only recomputes the
+//! tiles that changed in the previous generation or lie within the rule's
+//! range of one that did; the rest are known to stay as they are.
+//!
+//! Larger than Life rules count their neighbourhoods from prefix sums of
+//! the board, so a step costs the same whatever the range for the square
+//! Moore neighbourhood, and grows only linearly with it for the others.
+//!
+//! Hexagonal rules read the same eight cells as square ones and leave two
+//! out, while triangular rules count twelve cells over two columns either
+//! side; see [`Lattice`](crate::rule::Lattice).
+//!
+//! Besides the usual synchronous update, a board can update its cells in
+//! random order or by chance; see [`Update`].
+
+use std::{any::Any, thread};
+
+use crate::engine::Engine;
+use crate::random::Random;
+use crate::rule::{self, LargerThanLife, Lattice, Rule, Shape, NEIGHBOURS};
+use crate::topology::Topology;
+use crate::update::Update;
+
+/// Side in cells of the tiles whose changes are tracked between steps.
+const TILE_SIZE: usize = 16;
+
+/// A rectangular board of cells. Unless the rule names a [`Topology`] that
+/// joins its edges, everything outside the rectangle is permanently dead.
+///
+/// [`Topology`]: crate::topology::Topology
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    /// Cell states in row-major order: 0 is dead, 1 alive, and anything
+    /// above is dying under a Generations rule.
+    cells: Vec<u8>,
+    rule: Rule,
+    generation: u64,
+    /// Worker threads used by [`Grid::step`].
+    threads: usize,
+    /// Whether each tile, in row-major order, changed since the previous
+    /// step, and so must be looked at again along with its neighbours.
+    changed: Vec<bool>,
+    /// Tiles the last step left alone.
+    skipped_tiles: usize,
+    update: Update,
+    /// Where stochastic update schemes get their chances from.
+    random: Random,
+}
+
+impl Grid {
+    /// Creates an empty board of `width` by `height` cells running
+    /// Conway's Game of Life.
+    pub fn new(width: usize, height: usize) -> Self {
+        Grid::with_rule(width, height, Rule::CONWAY)
+    }
+
+    /// Creates an empty board of `width` by `height` cells running `rule`.
+    /// When the rule has a bounded-grid suffix, the board takes the size the
+    /// suffix gives it instead, except along unbounded axes.
+    pub fn with_rule(width: usize, height: usize, rule: Rule) -> Self {
+        let (width, height) = match rule.topology().map(|topology| topology.size()) {
+            Some((w, h)) => (
+                if w == 0 { width } else { w as usize },
+                if h == 0 { height } else { h as usize },
+            ),
+            None => (width, height),
+        };
+        Grid {
+            width,
+            height,
+            cells: vec![0; width * height],
+            rule,
+            generation: 0,
+            threads: 1,
+            changed: vec![true; width.div_ceil(TILE_SIZE) * height.div_ceil(TILE_SIZE)],
+            skipped_tiles: 0,
+            update: Update::Synchronous,
+            random: Random::new(0),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    /// Changes the rule used by subsequent steps. The board keeps its size
+    /// whatever the new rule's topology says.
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.changed.fill(true);
+    }
+
+    /// Number of threads each step is spread across.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Spreads each step across `threads` threads, each computing a band
+    /// of rows. The result does not depend on the thread count. 0 uses
+    /// one thread per available core.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |cores| cores.get()),
+            threads => threads,
+        };
+    }
+
+    pub fn update(&self) -> Update {
+        self.update
+    }
+
+    /// Changes how subsequent steps apply the rule.
+    pub fn set_update(&mut self, update: Update) {
+        self.update = update;
+        self.changed.fill(true);
+    }
+
+    /// Restarts the random numbers stochastic update schemes draw on, so
+    /// that a run from the same board and seed repeats exactly. Boards
+    /// start with seed 0.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+
+    /// Number of tiles the board is split into for change tracking.
+    pub fn tile_count(&self) -> usize {
+        self.changed.len()
+    }
+
+    /// Number of tiles the last step skipped because nothing in or around
+    /// them had changed.
+    pub fn skipped_tiles(&self) -> usize {
+        self.skipped_tiles
+    }
+
+    /// Number of generations computed since the board was created.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns whether the cell at `(x, y)` is alive, that is in state 1.
+    /// Cells outside the board are always dead.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.state(x, y) == 1
+    }
+
+    /// State of the cell at `(x, y)`, 0 outside the board.
+    pub fn state(&self, x: usize, y: usize) -> u8 {
+        if x < self.width && y < self.height {
+            self.cells[self.index(x, y)]
+        } else {
+            0
+        }
+    }
+
+    /// Sets the cell at `(x, y)` alive or dead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` lies outside the board.
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        self.set_state(x, y, u8::from(alive));
+    }
+
+    /// Puts the cell at `(x, y)` in `state`. States the rule does not have
+    /// die out on the next step.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` lies outside the board.
+    pub fn set_state(&mut self, x: usize, y: usize, state: u8) {
+        assert!(
+            x < self.width && y < self.height,
+            "cell ({}, {}) is outside a {}x{} grid",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        let index = self.index(x, y);
+        if self.cells[index] != state {
+            self.cells[index] = state;
+            let tile = self.tile(x, y);
+            self.changed[tile] = true;
+        }
+    }
+
+    /// Kills every cell on the board.
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|cell| *cell = 0);
+        self.changed.fill(true);
+    }
+
+    /// Number of cells on the board that are not dead, counting dying
+    /// cells.
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&state| state != 0).count()
+    }
+
+    /// Advances the board by one generation under its rule and update
+    /// scheme.
+    pub fn step(&mut self) {
+        if self.width == 0 || self.height == 0 {
+            self.generation += 1;
+            return;
+        }
+        if self.update == Update::RandomSequential {
+            self.step_sequential();
+            self.generation += 1;
+            return;
+        }
+        let mut next = self.next_generation();
+        match self.update {
+            Update::Asynchronous(alpha) => {
+                for (cell, &state) in next.iter_mut().zip(&self.cells) {
+                    if *cell != state && !self.random.chance(alpha) {
+                        *cell = state;
+                    }
+                }
+            }
+            Update::Probabilistic { birth, survival } => {
+                let death = self.rule.death_state();
+                for (i, cell) in next.iter_mut().enumerate() {
+                    let state = self.cells[i];
+                    if state == 0 && *cell != 0 && !self.random.chance(birth) {
+                        *cell = 0;
+                    } else if state == 1 && *cell == 1 && !self.random.chance(survival) {
+                        // A death the rule did not call for is a change
+                        // the tile tracking has not seen.
+                        *cell = death;
+                        let tile = self.tile(i % self.width, i / self.width);
+                        self.changed[tile] = true;
+                    }
+                }
+            }
+            Update::Synchronous | Update::RandomSequential => {}
+        }
+        self.cells = next;
+        self.generation += 1;
+    }
+
+    /// Updates every cell in place, one at a time in a random order.
+    fn step_sequential(&mut self) {
+        let mut order: Vec<usize> = (0..self.cells.len()).collect();
+        self.random.shuffle(&mut order);
+        for i in order {
+            self.cells[i] = self.next_state_at(i % self.width, i / self.width);
+        }
+        self.changed.fill(true);
+        self.skipped_tiles = 0;
+    }
+
+    /// Next state of the cell at `(x, y)` given the board as it is now.
+    fn next_state_at(&self, x: usize, y: usize) -> u8 {
+        let state = self.state(x, y);
+        match self.rule.larger_than_life() {
+            Some(ltl) => {
+                let range = i64::from(ltl.range());
+                let count = (-range..=range)
+                    .filter_map(|dy| Some((dy, i64::from(ltl.row_reach(dy)?))))
+                    .flat_map(|(dy, reach)| (-reach..=reach).map(move |dx| (dx, dy)))
+                    .filter(|&(dx, dy)| (dx, dy) != (0, 0) || ltl.includes_middle())
+                    .filter(|&(dx, dy)| self.state_at(x, y, (dx as isize, dy as isize)) == 1)
+                    .count() as u32;
+                self.rule.next_state_with_count(state, count)
+            }
+            None if self.rule.lattice() == Lattice::Triangular => self
+                .rule
+                .next_state_with_count(state, self.triangular_count(x, y)),
+            None => self
+                .rule
+                .next_state_with_states(state, self.neighbours(x, y)),
+        }
+    }
+
+    /// Works out the next state of every cell at once, and which tiles it
+    /// changes.
+    fn next_generation(&mut self) -> Vec<u8> {
+        let active = self.active_tiles();
+        let counts = self
+            .rule
+            .larger_than_life()
+            .map(|ltl| RangeCounts::new(self, *ltl));
+        let counts = counts.as_ref();
+        let mut next = vec![0; self.cells.len()];
+        // Bands hold whole rows of tiles.
+        let band_height = self
+            .height
+            .div_ceil(self.threads.max(1))
+            .next_multiple_of(TILE_SIZE);
+        if self.threads <= 1 || self.width == 0 || band_height >= self.height {
+            self.step_rows(0, &active, counts, &mut next);
+        } else {
+            let (grid, active) = (&*self, &active);
+            thread::scope(|scope| {
+                for (band, rows) in next.chunks_mut(band_height * grid.width).enumerate() {
+                    scope.spawn(move || grid.step_rows(band * band_height, active, counts, rows));
+                }
+            });
+        }
+
+        let tiles_x = self.width.div_ceil(TILE_SIZE);
+        self.changed = (0..active.len())
+            .map(|tile| {
+                let (left, top) = ((tile % tiles_x) * TILE_SIZE, (tile / tiles_x) * TILE_SIZE);
+                let right = (left + TILE_SIZE).min(self.width);
+                active[tile]
+                    && (top..(top + TILE_SIZE).min(self.height)).any(|y| {
+                        let row = y * self.width + left..y * self.width + right;
+                        self.cells[row.clone()] != next[row]
+                    })
+            })
+            .collect();
+        self.skipped_tiles = active.iter().filter(|&&active| !active).count();
+        next
+    }
+
+    /// Computes the next state of the whole rows starting at row `top` into
+    /// `out`, reading Larger than Life neighbourhoods from `counts`. Cells
+    /// in tiles that are not `active` are copied as they are.
+    fn step_rows(&self, top: usize, active: &[bool], counts: Option<&RangeCounts>, out: &mut [u8]) {
+        let tiles_x = self.width.div_ceil(TILE_SIZE);
+        for (offset, row) in out.chunks_mut(self.width).enumerate() {
+            let y = top + offset;
+            let tile_row = &active[(y / TILE_SIZE) * tiles_x..][..tiles_x];
+            for ((tile, &active), cells) in
+                tile_row.iter().enumerate().zip(row.chunks_mut(TILE_SIZE))
+            {
+                for (offset, cell) in cells.iter_mut().enumerate() {
+                    let x = tile * TILE_SIZE + offset;
+                    let state = self.state(x, y);
+                    *cell = match counts {
+                        _ if !active => state,
+                        Some(counts) => self.rule.next_state_with_count(state, counts.count(x, y)),
+                        None if self.rule.lattice() == Lattice::Triangular => self
+                            .rule
+                            .next_state_with_count(state, self.triangular_count(x, y)),
+                        None => self
+                            .rule
+                            .next_state_with_states(state, self.neighbours(x, y)),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Marks the tiles the next step has to compute: those that changed and
+    /// the tiles within the rule's range of them. When the topology joins
+    /// the edges, a change near any border may reach the others, so the
+    /// tiles near the borders all become active together.
+    fn active_tiles(&self) -> Vec<bool> {
+        let tiles_x = self.width.div_ceil(TILE_SIZE);
+        let tiles_y = self.height.div_ceil(TILE_SIZE);
+        let reach = (self.rule.range() as usize).div_ceil(TILE_SIZE);
+        let mut active = vec![false; self.changed.len()];
+        let on_border = |tx: usize, ty: usize| {
+            tx < reach || ty < reach || tx + reach >= tiles_x || ty + reach >= tiles_y
+        };
+        let mut border_changed = false;
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                if !self.changed[ty * tiles_x + tx] {
+                    continue;
+                }
+                border_changed |= on_border(tx, ty);
+                for ny in ty.saturating_sub(reach)..(ty + reach + 1).min(tiles_y) {
+                    for nx in tx.saturating_sub(reach)..(tx + reach + 1).min(tiles_x) {
+                        active[ny * tiles_x + nx] = true;
+                    }
+                }
+            }
+        }
+        let joined = !matches!(self.rule.topology(), None | Some(Topology::Plane { .. }));
+        if joined && border_changed {
+            for ty in 0..tiles_y {
+                for tx in 0..tiles_x {
+                    if on_border(tx, ty) {
+                        active[ty * tiles_x + tx] = true;
+                    }
+                }
+            }
+        }
+        active
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    /// Index of the tile holding the cell at `(x, y)`.
+    fn tile(&self, x: usize, y: usize) -> usize {
+        (y / TILE_SIZE) * self.width.div_ceil(TILE_SIZE) + x / TILE_SIZE
+    }
+
+    /// States of the cells around `(x, y)`, in the order of
+    /// [`NEIGHBOURS`].
+    fn neighbours(&self, x: usize, y: usize) -> [u8; 8] {
+        let mut neighbours = [0; 8];
+        for (neighbour, &offset) in neighbours.iter_mut().zip(&NEIGHBOURS) {
+            *neighbour = self.state_at(x, y, offset);
+        }
+        neighbours
+    }
+
+    /// Live neighbours of the triangle at `(x, y)`.
+    fn triangular_count(&self, x: usize, y: usize) -> u32 {
+        rule::triangular_neighbours(x as i64, y as i64)
+            .iter()
+            .filter(|&&offset| self.state_at(x, y, offset) == 1)
+            .count() as u32
+    }
+
+    /// State of the cell `(dx, dy)` away from `(x, y)`, following the
+    /// topology across the edges.
+    fn state_at(&self, x: usize, y: usize, (dx, dy): (isize, isize)) -> u8 {
+        let nx = x as i64 + dx as i64;
+        let ny = y as i64 + dy as i64;
+        let wrapped = match self.rule.topology() {
+            Some(topology) => topology.wrap(nx, ny, self.width as i64, self.height as i64),
+            None => Some((nx, ny)).filter(|&(nx, ny)| nx >= 0 && ny >= 0),
+        };
+        wrapped.map_or(0, |(nx, ny)| self.state(nx as usize, ny as usize))
+    }
+}
+
+/// Counts of cells in state 1 over a Larger than Life neighbourhood, read
+/// off prefix sums of a copy of the board padded by the range on every
+/// side, with the padding filled in from the topology.
+struct RangeCounts {
+    rule: LargerThanLife,
+    /// Width of the padded board plus one, the length of a row of sums.
+    stride: usize,
+    /// For each padded row, the number of live cells left of each column.
+    rows: Vec<u32>,
+    /// The number of live cells above and left of each padded position,
+    /// only kept for the Moore neighbourhood.
+    area: Vec<u32>,
+    /// Live cells of the unpadded board, to take the middle cell out.
+    alive: Vec<bool>,
+    width: usize,
+}
+
+impl RangeCounts {
+    fn new(grid: &Grid, rule: LargerThanLife) -> Self {
+        let range = rule.range() as usize;
+        let (width, height) = (grid.width + 2 * range, grid.height + 2 * range);
+        let stride = width + 1;
+        let topology = grid.rule.topology();
+        let mut rows = vec![0; stride * height];
+        for py in 0..height {
+            let y = py as i64 - range as i64;
+            for px in 0..width {
+                let x = px as i64 - range as i64;
+                let wrapped = match topology {
+                    Some(topology) => topology.wrap(x, y, grid.width as i64, grid.height as i64),
+                    None => Some((x, y)).filter(|&(x, y)| x >= 0 && y >= 0),
+                };
+                let alive = wrapped.is_some_and(|(x, y)| grid.get(x as usize, y as usize));
+                rows[py * stride + px + 1] = rows[py * stride + px] + u32::from(alive);
+            }
+        }
+        let mut area = Vec::new();
+        if rule.shape() == Shape::Moore {
+            area = vec![0; stride * (height + 1)];
+            for py in 0..height {
+                for px in 0..stride {
+                    area[(py + 1) * stride + px] = area[py * stride + px] + rows[py * stride + px];
+                }
+            }
+        }
+        RangeCounts {
+            rule,
+            stride,
+            rows,
+            area,
+            alive: grid.cells.iter().map(|&state| state == 1).collect(),
+            width: grid.width,
+        }
+    }
+
+    /// Live cells in the neighbourhood of the cell at `(x, y)`.
+    fn count(&self, x: usize, y: usize) -> u32 {
+        let range = self.rule.range() as usize;
+        let total = if self.rule.shape() == Shape::Moore {
+            // The square spans padded rows y..=y + 2r and columns x..=x + 2r.
+            let (top, bottom) = (y * self.stride, (y + 2 * range + 1) * self.stride);
+            let (left, right) = (x, x + 2 * range + 1);
+            self.area[bottom + right] + self.area[top + left]
+                - self.area[top + right]
+                - self.area[bottom + left]
+        } else {
+            (-(range as i64)..=range as i64)
+                .filter_map(|dy| {
+                    let reach = self.rule.row_reach(dy)? as usize;
+                    let row = (y as i64 + range as i64 + dy) as usize * self.stride;
+                    let centre = x + range;
+                    Some(self.rows[row + centre + reach + 1] - self.rows[row + centre - reach])
+                })
+                .sum()
+        };
+        if self.rule.includes_middle() {
+            total
+        } else {
+            total - u32::from(self.alive[y * self.width + x])
+        }
+    }
+}
+
+impl Engine for Grid {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        Grid::population(self) as u64
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        if x >= 0 && y >= 0 {
+            self.state(x as usize, y as usize)
+        } else {
+            0
+        }
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.set_state(x as usize, y as usize, state);
+        }
+    }
+
+    fn clear(&mut self) {
+        Grid::clear(self)
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        let mut cells = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let state = self.state(x, y);
+                if state != 0 {
+                    cells.push((x as i64, y as i64, state));
+                }
+            }
+        }
+        cells
+    }
+
+    fn step(&mut self) {
+        Grid::step(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board of `width` by `height` with the cells at `cells` alive.
+    fn grid(width: usize, height: usize, cells: &[(usize, usize)]) -> Grid {
+        let mut grid = Grid::new(width, height);
+        for &(x, y) in cells {
+            grid.set(x, y, true);
+        }
+        grid
+    }
+
+    fn alive(grid: &Grid) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                if grid.get(x, y) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    /// A `width` by `height` board running `rule`, with each cell alive
+    /// with probability 1/2.
+    fn soup(width: usize, height: usize, rule: &str, seed: u64) -> Grid {
+        let mut grid = Grid::with_rule(width, height, rule.parse().unwrap());
+        let mut random = Random::new(seed);
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                grid.set(x, y, random.chance(0.5));
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn block_is_still() {
+        let cells = [(1, 1), (2, 1), (1, 2), (2, 2)];
+        let mut grid = grid(4, 4, &cells);
+        grid.step();
+        assert_eq!(alive(&grid), cells);
+        assert_eq!(grid.generation(), 1);
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let mut grid = grid(5, 5, &[(1, 2), (2, 2), (3, 2)]);
+        grid.step();
+        assert_eq!(alive(&grid), [(2, 1), (2, 2), (2, 3)]);
+        grid.step();
+        assert_eq!(alive(&grid), [(1, 2), (2, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn glider_moves_diagonally() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut grid = grid(8, 8, &glider);
+        for _ in 0..4 {
+            grid.step();
+        }
+        let expected: Vec<_> = glider.iter().map(|&(x, y)| (x + 1, y + 1)).collect();
+        assert_eq!(alive(&grid), expected);
+        assert_eq!(grid.population(), 5);
+    }
+
+    #[test]
+    fn outside_the_board_is_dead() {
+        // A blinker against the top edge loses the cell it would grow
+        // beyond it, leaving two cells that die next.
+        let mut grid = grid(3, 3, &[(0, 0), (1, 0), (2, 0)]);
+        assert!(!grid.get(3, 0));
+        grid.step();
+        assert_eq!(alive(&grid), [(1, 0), (1, 1)]);
+        grid.step();
+        assert_eq!(grid.population(), 0);
+
+        // Boards without cells still count their generations.
+        for (width, height) in [(0, 5), (5, 0), (0, 0)] {
+            let mut empty = Grid::new(width, height);
+            empty.set_threads(4);
+            empty.step();
+            assert_eq!((empty.generation(), empty.population()), (1, 0));
+        }
+    }
+
+    #[test]
+    fn clear_kills_everything() {
+        let mut grid = grid(4, 4, &[(0, 0), (3, 3)]);
+        grid.clear();
+        assert_eq!(grid.population(), 0);
+    }
+
+    #[test]
+    fn threads_do_not_change_the_result() {
+        for rule in &["B3/S23", "B2/S/C3", "B3/S23:T100,90"] {
+            let single = soup(100, 90, rule, 7);
+            for &threads in &[2, 3, 4, 7, 32] {
+                let mut single = single.clone();
+                let mut threaded = single.clone();
+                threaded.set_threads(threads);
+                for _ in 0..20 {
+                    single.step();
+                    threaded.step();
+                    assert_eq!(
+                        threaded.cells, single.cells,
+                        "{} on {} threads",
+                        rule, threads
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn skipping_tiles_matches_a_full_recompute() {
+        for rule in &[
+            "B3/S23",
+            "B2/S/C3",
+            "B3/S23:T100,90",
+            "B3/S23:K100*,90",
+            "R5,C0,M1,S34..58,B34..45,NM",
+        ] {
+            let mut skipping = soup(100, 90, rule, 3);
+            let mut full = skipping.clone();
+            for generation in 0..40 {
+                full.changed.fill(true);
+                full.step();
+                skipping.step();
+                assert_eq!(
+                    skipping.cells, full.cells,
+                    "{} at generation {}",
+                    rule, generation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn still_tiles_are_skipped() {
+        // A blinker well inside a 10 by 10 block of tiles keeps the nine
+        // tiles around its own active.
+        let mut blinker = grid(160, 160, &[(39, 40), (40, 40), (41, 40)]);
+        assert_eq!(blinker.tile_count(), 100);
+        blinker.step();
+        assert_eq!(blinker.skipped_tiles(), 0);
+        blinker.step();
+        assert_eq!(blinker.skipped_tiles(), 91);
+        assert_eq!(alive(&blinker), [(39, 40), (40, 40), (41, 40)]);
+
+        // Once a block has settled, nothing is left to compute.
+        let mut block = grid(64, 64, &[(10, 10), (11, 10), (10, 11), (11, 11)]);
+        block.step();
+        block.step();
+        assert_eq!(block.skipped_tiles(), block.tile_count());
+    }
+
+    #[test]
+    fn brians_brain_cells_die_through_a_dying_state() {
+        let mut grid = grid(6, 6, &[(2, 2), (3, 2)]);
+        grid.set_rule("B2/S/C3".parse().unwrap());
+        grid.step();
+        assert_eq!((grid.state(2, 2), grid.state(3, 2)), (2, 2));
+        assert_eq!(alive(&grid), [(2, 1), (3, 1), (2, 3), (3, 3)]);
+        assert_eq!(grid.population(), 6);
+        grid.step();
+        assert_eq!((grid.state(2, 2), grid.state(3, 2)), (0, 0));
+    }
+
+    #[test]
+    fn larger_than_life_counts_the_whole_range() {
+        for rule in [
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R3,C0,M0,S2..6,B4..5,NC",
+            "R2,C4,M0,S3..8,B4..6,NN",
+        ] {
+            let mut grid = soup(40, 30, rule, 11);
+            let rule = grid.rule();
+            let ltl = *rule.larger_than_life().unwrap();
+            let range = ltl.range() as i64;
+            for _ in 0..3 {
+                let mut expected = Vec::new();
+                for y in 0..grid.height() as i64 {
+                    for x in 0..grid.width() as i64 {
+                        let mut count = 0;
+                        for dy in -range..=range {
+                            for dx in -range..=range {
+                                let inside = match ltl.shape() {
+                                    Shape::Moore => true,
+                                    Shape::VonNeumann => dx.abs() + dy.abs() <= range,
+                                    Shape::Circular => dx * dx + dy * dy <= range * range + range,
+                                };
+                                let (nx, ny) = (x + dx, y + dy);
+                                if inside
+                                    && ((dx, dy) != (0, 0) || ltl.includes_middle())
+                                    && nx >= 0
+                                    && ny >= 0
+                                    && grid.get(nx as usize, ny as usize)
+                                {
+                                    count += 1;
+                                }
+                            }
+                        }
+                        let state = grid.state(x as usize, y as usize);
+                        expected.push(rule.next_state_with_count(state, count));
+                    }
+                }
+                grid.step();
+                assert_eq!(grid.cells, expected, "{}", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn single_cells_seed_their_whole_neighbourhood() {
+        let mut hexagon = grid(9, 9, &[(4, 4)]);
+        hexagon.set_rule("B1/SH".parse().unwrap());
+        hexagon.step();
+        assert_eq!(
+            alive(&hexagon),
+            [(3, 3), (4, 3), (3, 4), (5, 4), (4, 5), (5, 5)]
+        );
+
+        let mut triangle = grid(9, 9, &[(4, 4)]);
+        triangle.set_rule("B1/SL".parse().unwrap());
+        triangle.step();
+        let mut expected: Vec<_> = rule::triangular_neighbours(4, 4)
+            .iter()
+            .map(|&(dx, dy)| ((4 + dx) as usize, (4 + dy) as usize))
+            .collect();
+        expected.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(alive(&triangle), expected);
+    }
+
+    #[test]
+    fn certain_updates_match_synchronous_ones() {
+        for update in ["async:1", "prob:1,1"] {
+            let mut sync = soup(40, 30, "B3/S23", 11);
+            let mut other = soup(40, 30, "B3/S23", 11);
+            other.set_update(update.parse().unwrap());
+            for _ in 0..20 {
+                sync.step();
+                other.step();
+                assert_eq!(other.cells, sync.cells, "{}", update);
+            }
+        }
+
+        let mut frozen = soup(40, 30, "B3/S23", 11);
+        let before = frozen.cells.clone();
+        frozen.set_update(Update::Asynchronous(0.0));
+        frozen.step();
+        assert_eq!(frozen.cells, before);
+    }
+
+    #[test]
+    fn stochastic_runs_repeat_from_the_same_seed() {
+        for update in ["sequential", "async:0.5", "prob:0.9,0.95"] {
+            let run = |seed| {
+                let mut grid = soup(40, 30, "B3/S23", 5);
+                grid.set_update(update.parse().unwrap());
+                grid.set_seed(seed);
+                for _ in 0..10 {
+                    grid.step();
+                }
+                grid.cells
+            };
+            assert_eq!(run(1), run(1), "{}", update);
+            assert_ne!(run(1), run(2), "{}", update);
+        }
+    }
+
+    #[test]
+    fn sequential_updates_see_earlier_cells() {
+        // A block is still whatever order its cells update in.
+        let mut block = grid(6, 6, &[(2, 2), (3, 2), (2, 3), (3, 3)]);
+        block.set_update(Update::RandomSequential);
+        for seed in 0..8 {
+            block.set_seed(seed);
+            block.step();
+            assert_eq!(alive(&block), [(2, 2), (3, 2), (2, 3), (3, 3)]);
+        }
+        assert_eq!(block.generation(), 8);
+
+        // A blinker's first updated cell changes what the rest see, so it
+        // never just flips as it does synchronously.
+        let mut blinker = grid(5, 5, &[(1, 2), (2, 2), (3, 2)]);
+        blinker.set_update(Update::RandomSequential);
+        blinker.step();
+        assert_ne!(alive(&blinker), [(2, 1), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn probabilistic_updates_only_cancel_what_they_are_given() {
+        let mut no_births = soup(40, 30, "B3/S23", 2);
+        no_births.set_update("prob:0,1".parse().unwrap());
+        let mut population = no_births.population();
+        for _ in 0..10 {
+            no_births.step();
+            assert!(no_births.population() <= population);
+            population = no_births.population();
+        }
+
+        // Without survivals, the only live cells are the ones just born.
+        let mut sync = soup(40, 30, "B3/S23", 2);
+        let mut no_survivals = soup(40, 30, "B3/S23", 2);
+        no_survivals.set_update("prob:1,0".parse().unwrap());
+        let before = sync.cells.clone();
+        sync.step();
+        no_survivals.step();
+        let births: Vec<u8> = before
+            .iter()
+            .zip(&sync.cells)
+            .map(|(&was, &is)| u8::from(was == 0 && is == 1))
+            .collect();
+        assert_eq!(no_survivals.cells, births);
+    }
+}
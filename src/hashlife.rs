@@ -0,0 +1,712 @@
+//! Gosper's Hashlife: the universe is a quadtree whose identical subtrees
+//! are shared, and the future of every subtree is memoized, so regular
+//! patterns can be advanced by huge powers of two in one go.
+//!
+//! A node of level `k` covers a `2^k` by `2^k` square. Its result is the
+//! central `2^(k-1)` square advanced by `2^min(e, k-2)` generations, where
+//! `e` is the step exponent the memo table was built for.
+
+use std::{any::Any, collections::HashMap};
+
+use crate::engine::Engine;
+use crate::pattern::Bounds;
+use crate::rule::{Lattice, Rule};
+
+pub(crate) type NodeId = u32;
+
+/// Leaves are single cells; leaf `s` holds state `s` and has id `s`.
+const LEAF_COUNT: NodeId = 256;
+
+/// Node count above which [`Hashlife::advance`] collects garbage.
+const DEFAULT_MAX_NODES: usize = 1 << 22;
+
+/// Deepest tree allowed, so coordinates of every cell fit in an `i64`.
+const MAX_LEVEL: u8 = 62;
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    level: u8,
+    /// North-west, north-east, south-west and south-east quadrants.
+    children: [NodeId; 4],
+    population: u64,
+    result: Option<NodeId>,
+}
+
+/// Canonicalizing storage for quadtree nodes: joining the same four
+/// children twice yields the same id.
+#[derive(Clone, Debug)]
+struct NodeStore {
+    nodes: Vec<Node>,
+    index: HashMap<[NodeId; 4], NodeId>,
+    /// Cached empty node of each level.
+    empty: Vec<NodeId>,
+}
+
+impl NodeStore {
+    fn new() -> Self {
+        let nodes = (0..LEAF_COUNT)
+            .map(|state| Node {
+                level: 0,
+                children: [0; 4],
+                population: u64::from(state != 0),
+                result: None,
+            })
+            .collect();
+        NodeStore {
+            nodes,
+            index: HashMap::new(),
+            empty: vec![0],
+        }
+    }
+
+    fn get(&self, id: NodeId) -> &Node {
+        &self.nodes[id as usize]
+    }
+
+    fn level(&self, id: NodeId) -> u8 {
+        self.get(id).level
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.get(id).children
+    }
+
+    fn population(&self, id: NodeId) -> u64 {
+        self.get(id).population
+    }
+
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.index.get(&children) {
+            return id;
+        }
+        let level = self.level(children[0]) + 1;
+        let population = children.iter().fold(0u64, |total, &child| {
+            total.saturating_add(self.population(child))
+        });
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level,
+            children,
+            population,
+            result: None,
+        });
+        self.index.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let id = self.join([below; 4]);
+            self.empty.push(id);
+        }
+        self.empty[level as usize]
+    }
+
+    /// The central quadrant-sized node of `id`.
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        self.join([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ])
+    }
+
+    /// The node straddling the boundary between side-by-side `west` and
+    /// `east`.
+    fn centre_horizontal(&mut self, west: NodeId, east: NodeId) -> NodeId {
+        let [_, w_ne, _, w_se] = self.children(west);
+        let [e_nw, _, e_sw, _] = self.children(east);
+        self.join([w_ne, e_nw, w_se, e_sw])
+    }
+
+    /// The node straddling the boundary between stacked `north` and
+    /// `south`.
+    fn centre_vertical(&mut self, north: NodeId, south: NodeId) -> NodeId {
+        let [_, _, n_sw, n_se] = self.children(north);
+        let [s_nw, s_ne, _, _] = self.children(south);
+        self.join([n_sw, n_se, s_nw, s_ne])
+    }
+
+    fn clear_results(&mut self) {
+        self.nodes.iter_mut().for_each(|node| node.result = None);
+    }
+}
+
+/// A Life-like universe on the unbounded plane, stepped with Hashlife.
+///
+/// Rules with `B0` are not supported, since they turn the infinite empty
+/// background on in a single generation.
+#[derive(Clone, Debug)]
+pub struct Hashlife {
+    rule: Rule,
+    store: NodeStore,
+    /// Root node, centred on the origin.
+    root: NodeId,
+    generation: u64,
+    step_exponent: u32,
+    max_nodes: usize,
+}
+
+impl Hashlife {
+    /// Creates an empty universe running `rule`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rule` has birth on 0 neighbours, is a Larger than Life
+    /// rule or runs on triangular cells.
+    pub fn new(rule: Rule) -> Self {
+        assert!(!rule.is_birth(0), "Hashlife cannot run B0 rules");
+        assert!(
+            rule.larger_than_life().is_none(),
+            "Hashlife cannot run Larger than Life rules"
+        );
+        assert!(
+            rule.lattice() != Lattice::Triangular,
+            "Hashlife cannot run triangular rules"
+        );
+        let mut store = NodeStore::new();
+        let root = store.empty(3);
+        Hashlife {
+            rule,
+            store,
+            root,
+            generation: 0,
+            step_exponent: 0,
+            max_nodes: DEFAULT_MAX_NODES,
+        }
+    }
+
+    /// Advances `2^step_exponent` generations.
+    pub fn advance(&mut self) {
+        while u32::from(self.store.level(self.root)) < self.step_exponent + 3
+            || !self.fits_inner_quarter()
+        {
+            self.expand();
+        }
+        self.root = self.successor(self.root);
+        self.generation += 1 << self.step_exponent;
+        if self.store.nodes.len() > self.max_nodes {
+            self.collect_garbage();
+        }
+    }
+
+    /// Exponent of the step size [`advance`](Hashlife::advance) uses.
+    pub fn step_exponent(&self) -> u32 {
+        self.step_exponent
+    }
+
+    /// Changes the step size. Memoized results only hold for one step
+    /// size, so changing it discards them.
+    pub fn set_step_exponent(&mut self, exponent: u32) {
+        assert!(exponent < 64, "step exponent {} is too large", exponent);
+        if exponent != self.step_exponent {
+            self.store.clear_results();
+            self.step_exponent = exponent;
+        }
+    }
+
+    /// Number of nodes currently stored, live or not.
+    pub fn node_count(&self) -> usize {
+        self.store.nodes.len()
+    }
+
+    /// Node count above which garbage is collected after a step.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Drops every node that is unreachable from the current universe,
+    /// keeping memoized results whose targets survive.
+    pub fn collect_garbage(&mut self) {
+        let old = &self.store.nodes;
+        let mut marked = vec![false; old.len()];
+        marked[..LEAF_COUNT as usize]
+            .iter_mut()
+            .for_each(|mark| *mark = true);
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            if !marked[id as usize] {
+                marked[id as usize] = true;
+                stack.extend_from_slice(&old[id as usize].children);
+            }
+        }
+
+        // Children always precede their parents, so one ordered pass can
+        // renumber them.
+        let mut remap = vec![NodeId::MAX; old.len()];
+        let mut store = NodeStore::new();
+        for (id, node) in old.iter().enumerate() {
+            if !marked[id] {
+                continue;
+            }
+            remap[id] = if id < LEAF_COUNT as usize {
+                id as NodeId
+            } else {
+                store.join(node.children.map(|child| remap[child as usize]))
+            };
+        }
+        for (id, node) in old.iter().enumerate() {
+            if let Some(result) = node.result.filter(|_| marked[id]) {
+                if marked[result as usize] {
+                    store.nodes[remap[id] as usize].result = Some(remap[result as usize]);
+                }
+            }
+        }
+        self.root = remap[self.root as usize];
+        self.store = store;
+    }
+
+    /// The leaf holding a single cell in `state`.
+    pub(crate) fn leaf(state: u8) -> NodeId {
+        NodeId::from(state)
+    }
+
+    pub(crate) fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Makes `root` the whole universe, centred on the origin.
+    pub(crate) fn set_root(&mut self, root: NodeId) {
+        self.root = root;
+        while self.store.level(self.root) < 3 {
+            self.expand();
+        }
+    }
+
+    pub(crate) fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    pub(crate) fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        self.store.join(children)
+    }
+
+    pub(crate) fn empty_node(&mut self, level: u8) -> NodeId {
+        self.store.empty(level)
+    }
+
+    pub(crate) fn node_level(&self, id: NodeId) -> u8 {
+        self.store.level(id)
+    }
+
+    pub(crate) fn node_children(&self, id: NodeId) -> [NodeId; 4] {
+        self.store.children(id)
+    }
+
+    pub(crate) fn node_population(&self, id: NodeId) -> u64 {
+        self.store.population(id)
+    }
+
+    fn half_size(&self) -> i64 {
+        1 << (self.store.level(self.root) - 1)
+    }
+
+    /// Surrounds the universe with empty space, doubling its side.
+    fn expand(&mut self) {
+        let level = self.store.level(self.root);
+        assert!(level < MAX_LEVEL, "universe is too large to expand");
+        let empty = self.store.empty(level - 1);
+        let [nw, ne, sw, se] = self.store.children(self.root);
+        let children = [
+            self.store.join([empty, empty, empty, nw]),
+            self.store.join([empty, empty, ne, empty]),
+            self.store.join([empty, sw, empty, empty]),
+            self.store.join([se, empty, empty, empty]),
+        ];
+        self.root = self.store.join(children);
+    }
+
+    /// Whether every live cell lies in the central quarter of the root, the
+    /// area a step can safely start from.
+    fn fits_inner_quarter(&self) -> bool {
+        let store = &self.store;
+        let [nw, ne, sw, se] = store.children(self.root);
+        let inner = [(nw, 3), (ne, 2), (sw, 1), (se, 0)]
+            .iter()
+            .map(|&(child, corner)| {
+                let grandchild = store.children(child)[corner];
+                store.population(store.children(grandchild)[corner])
+            })
+            .fold(0u64, u64::saturating_add);
+        inner == store.population(self.root)
+    }
+
+    /// The centre of `id` advanced by `2^min(step_exponent, level - 2)`
+    /// generations.
+    fn successor(&mut self, id: NodeId) -> NodeId {
+        if let Some(result) = self.store.get(id).result {
+            return result;
+        }
+        let level = self.store.level(id);
+        let result = if self.store.population(id) == 0 {
+            self.store.empty(level - 1)
+        } else if level == 2 {
+            self.base_successor(id)
+        } else {
+            self.recursive_successor(id, level)
+        };
+        self.store.nodes[id as usize].result = Some(result);
+        result
+    }
+
+    fn recursive_successor(&mut self, id: NodeId, level: u8) -> NodeId {
+        let store = &mut self.store;
+        let [nw, ne, sw, se] = store.children(id);
+        let nine = [
+            nw,
+            store.centre_horizontal(nw, ne),
+            ne,
+            store.centre_vertical(nw, sw),
+            store.centre(id),
+            store.centre_vertical(ne, se),
+            sw,
+            store.centre_horizontal(sw, se),
+            se,
+        ];
+
+        // At full speed both halves of the jump happen below; otherwise the
+        // nine parts are only trimmed and the whole jump happens below.
+        let full_speed = self.step_exponent >= u32::from(level - 2);
+        let mut parts = [0; 9];
+        for (part, &node) in parts.iter_mut().zip(nine.iter()) {
+            *part = if full_speed {
+                self.successor(node)
+            } else {
+                self.store.centre(node)
+            };
+        }
+
+        let [a, b, c, d, e, f, g, h, i] = parts;
+        let quadrants = [[a, b, d, e], [b, c, e, f], [d, e, g, h], [e, f, h, i]];
+        let mut result = [0; 4];
+        for (slot, quadrant) in result.iter_mut().zip(quadrants.iter()) {
+            let joined = self.store.join(*quadrant);
+            *slot = self.successor(joined);
+        }
+        self.store.join(result)
+    }
+
+    /// Steps the central 2x2 cells of a 4x4 node by one generation.
+    fn base_successor(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[0; 4]; 4];
+        for (quadrant, &child) in self.store.children(id).iter().enumerate() {
+            for (corner, &leaf) in self.store.children(child).iter().enumerate() {
+                let x = (quadrant % 2) * 2 + corner % 2;
+                let y = (quadrant / 2) * 2 + corner / 2;
+                cells[y][x] = leaf as u8;
+            }
+        }
+        let mut next = [0; 4];
+        for (corner, state) in next.iter_mut().enumerate() {
+            let (x, y) = (1 + corner % 2, 1 + corner / 2);
+            let block = cells[y - 1..=y + 1]
+                .iter()
+                .flat_map(|row| &row[x - 1..=x + 1]);
+            *state = NodeId::from(self.rule.next_state_in_block(block));
+        }
+        self.store.join(next)
+    }
+
+    fn set_recursive(&mut self, id: NodeId, x: i64, y: i64, state: u8) -> NodeId {
+        let level = self.store.level(id);
+        if level == 0 {
+            return NodeId::from(state);
+        }
+        let half = 1 << (level - 1);
+        let quadrant = usize::from(x >= half) + 2 * usize::from(y >= half);
+        let mut children = self.store.children(id);
+        children[quadrant] = self.set_recursive(
+            children[quadrant],
+            x - if x >= half { half } else { 0 },
+            y - if y >= half { half } else { 0 },
+            state,
+        );
+        self.store.join(children)
+    }
+
+    fn collect_cells(&self, id: NodeId, left: i64, top: i64, cells: &mut Vec<(i64, i64, u8)>) {
+        let node = self.store.get(id);
+        if node.population == 0 {
+            return;
+        }
+        if node.level == 0 {
+            cells.push((left, top, id as u8));
+            return;
+        }
+        let half = 1 << (node.level - 1);
+        for (quadrant, &child) in node.children.iter().enumerate() {
+            let x = left + half * (quadrant % 2) as i64;
+            let y = top + half * (quadrant / 2) as i64;
+            self.collect_cells(child, x, y, cells);
+        }
+    }
+
+    /// Bounding box of the live cells of `id`, relative to its top-left
+    /// corner, memoized in `seen` so shared subtrees are visited once.
+    fn relative_bounds(
+        &self,
+        id: NodeId,
+        seen: &mut HashMap<NodeId, Option<(i64, i64, i64, i64)>>,
+    ) -> Option<(i64, i64, i64, i64)> {
+        let node = *self.store.get(id);
+        if node.population == 0 {
+            return None;
+        }
+        if node.level == 0 {
+            return Some((0, 0, 0, 0));
+        }
+        if let Some(&bounds) = seen.get(&id) {
+            return bounds;
+        }
+        let half = 1 << (node.level - 1);
+        let mut bounds: Option<(i64, i64, i64, i64)> = None;
+        for (quadrant, &child) in node.children.iter().enumerate() {
+            let (dx, dy) = (half * (quadrant % 2) as i64, half * (quadrant / 2) as i64);
+            if let Some((l, t, r, b)) = self.relative_bounds(child, seen) {
+                let (l, t, r, b) = (l + dx, t + dy, r + dx, b + dy);
+                bounds = Some(match bounds {
+                    Some((bl, bt, br, bb)) => (bl.min(l), bt.min(t), br.max(r), bb.max(b)),
+                    None => (l, t, r, b),
+                });
+            }
+        }
+        seen.insert(id, bounds);
+        bounds
+    }
+}
+
+impl Default for Hashlife {
+    fn default() -> Self {
+        Hashlife::new(Rule::CONWAY)
+    }
+}
+
+impl Engine for Hashlife {
+    fn name(&self) -> &'static str {
+        "hashlife"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        self.store.population(self.root)
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        let half = self.half_size();
+        if x < -half || y < -half || x >= half || y >= half {
+            return 0;
+        }
+        let (mut x, mut y) = (x + half, y + half);
+        let mut id = self.root;
+        loop {
+            let node = self.store.get(id);
+            if node.population == 0 {
+                return 0;
+            }
+            if node.level == 0 {
+                return id as u8;
+            }
+            let half = 1 << (node.level - 1);
+            id = node.children[usize::from(x >= half) + 2 * usize::from(y >= half)];
+            x %= half;
+            y %= half;
+        }
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        while {
+            let half = self.half_size();
+            x < -half || y < -half || x >= half || y >= half
+        } {
+            self.expand();
+        }
+        let half = self.half_size();
+        self.root = self.set_recursive(self.root, x + half, y + half, state);
+    }
+
+    fn clear(&mut self) {
+        self.store = NodeStore::new();
+        self.root = self.store.empty(3);
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        let mut cells = Vec::new();
+        let half = self.half_size();
+        self.collect_cells(self.root, -half, -half, &mut cells);
+        cells
+    }
+
+    fn bounding_box(&self) -> Option<Bounds> {
+        let half = self.half_size();
+        self.relative_bounds(self.root, &mut HashMap::new())
+            .map(|(left, top, right, bottom)| Bounds {
+                left: left - half,
+                top: top - half,
+                width: (right - left) as u64 + 1,
+                height: (bottom - top) as u64 + 1,
+            })
+    }
+
+    fn step(&mut self) {
+        self.step_pow2(0);
+    }
+
+    fn step_pow2(&mut self, exponent: u32) {
+        self.set_step_exponent(exponent);
+        self.advance();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::rle;
+    use crate::sparse::Sparse;
+
+    const GLIDER: &str = "x = 3, y = 3\nbo$2bo$3o!";
+    const R_PENTOMINO: &str = "x = 3, y = 3\nb2o$2o$bo!";
+    const REPLICATOR: &str = "x = 5, y = 5, rule = B36/S23\n2b3o$bo2bo$o3bo$o2bo$3o!";
+
+    /// Loads the RLE `text` into a Hashlife universe and a sparse one.
+    fn universes(text: &str) -> (Hashlife, Sparse) {
+        let pattern = rle::parse(text).unwrap();
+        let rule: Rule = pattern.rule.as_deref().unwrap_or("B3/S23").parse().unwrap();
+        let mut hashlife = Hashlife::new(rule.clone());
+        let mut sparse = Sparse::new(rule);
+        hashlife.load(&pattern);
+        sparse.load(&pattern);
+        (hashlife, sparse)
+    }
+
+    fn sorted_cells(engine: &dyn Engine) -> Vec<(i64, i64, u8)> {
+        let mut cells = engine.live_cells();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Checks that jumping `2^exponent` generations at a time lands where
+    /// stepping one at a time does.
+    fn assert_matches(text: &str, exponents: &[u32]) {
+        let (mut hashlife, mut sparse) = universes(text);
+        for &exponent in exponents {
+            hashlife.step_pow2(exponent);
+            for _ in 0..1 << exponent {
+                sparse.step();
+            }
+            assert_eq!(hashlife.generation(), sparse.generation());
+            assert_eq!(
+                sorted_cells(&hashlife),
+                sorted_cells(&sparse),
+                "generation {}",
+                sparse.generation()
+            );
+            assert_eq!(hashlife.population(), sparse.population());
+        }
+    }
+
+    #[test]
+    fn glider_matches_stepping_one_at_a_time() {
+        assert_matches(GLIDER, &[0, 1, 2, 3, 4, 5, 6, 2, 0]);
+    }
+
+    #[test]
+    fn r_pentomino_matches_stepping_one_at_a_time() {
+        assert_matches(R_PENTOMINO, &[0, 3, 5, 7, 2]);
+    }
+
+    #[test]
+    fn replicator_matches_stepping_one_at_a_time() {
+        assert_matches(REPLICATOR, &[0, 2, 4, 6, 7, 1]);
+    }
+
+    #[test]
+    fn garbage_collection_keeps_the_universe() {
+        let (mut hashlife, mut sparse) = universes(R_PENTOMINO);
+        let step = |hashlife: &mut Hashlife, sparse: &mut Sparse| {
+            hashlife.step_pow2(3);
+            for _ in 0..8 {
+                sparse.step();
+            }
+        };
+        for _ in 0..20 {
+            step(&mut hashlife, &mut sparse);
+        }
+        let before = hashlife.node_count();
+        hashlife.collect_garbage();
+        assert!(hashlife.node_count() < before);
+        assert_eq!(sorted_cells(&hashlife), sorted_cells(&sparse));
+
+        // Results kept through the collection must still be right, and so
+        // must collecting after every step.
+        hashlife.set_max_nodes(0);
+        for _ in 0..20 {
+            step(&mut hashlife, &mut sparse);
+            assert_eq!(sorted_cells(&hashlife), sorted_cells(&sparse));
+        }
+    }
+
+    #[test]
+    fn expands_for_patterns_near_the_edge() {
+        let mut hashlife = Hashlife::default();
+        let mut sparse = Sparse::default();
+        // A glider in the corner of the initial root, heading out of it.
+        for &(x, y) in &[(-4, -4), (-3, -4), (-2, -4), (-4, -3), (-3, -2)] {
+            hashlife.set_cell(x, y, 1);
+            sparse.set_cell(x, y, 1);
+        }
+        assert_eq!(hashlife.store.level(hashlife.root), 3);
+        assert!(!hashlife.fits_inner_quarter());
+        for exponent in [0, 4, 2] {
+            hashlife.step_pow2(exponent);
+            for _ in 0..1 << exponent {
+                sparse.step();
+            }
+            assert_eq!(sorted_cells(&hashlife), sorted_cells(&sparse));
+        }
+    }
+
+    #[test]
+    fn empty_universe_stays_empty() {
+        let mut hashlife = Hashlife::default();
+        hashlife.step_pow2(10);
+        assert_eq!(hashlife.population(), 0);
+        assert_eq!(hashlife.generation(), 1024);
+        assert_eq!(hashlife.bounding_box(), None);
+    }
+
+    #[test]
+    fn cells_read_back_and_bound() {
+        let mut hashlife = Hashlife::default();
+        hashlife.set_cell(-100, 7, 1);
+        hashlife.set_cell(50, -3, 1);
+        assert_eq!(hashlife.cell(-100, 7), 1);
+        assert_eq!(hashlife.cell(0, 0), 0);
+        assert_eq!(
+            hashlife.bounding_box(),
+            Some(Bounds {
+                left: -100,
+                top: -3,
+                width: 151,
+                height: 11
+            })
+        );
+    }
+}
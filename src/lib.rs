@@ -0,0 +1,39 @@
+//! Engine for Conway's Game of Life, other Life-like cellular automata,
+//! one-dimensional, block, 3D and continuous ones, shared by the terminal
+//! viewer and any other tool that needs to run a simulation.
+
+pub mod bitgrid;
+pub mod blocks;
+pub mod census;
+pub mod continuous;
+pub mod engine;
+mod fft;
+pub mod format;
+pub mod grid;
+pub mod hashlife;
+pub mod pattern;
+pub mod period;
+pub mod random;
+pub mod rule;
+pub mod spacetime;
+pub mod sparse;
+pub mod topology;
+pub mod update;
+pub mod volume;
+
+pub use bitgrid::BitGrid;
+pub use blocks::Blocks;
+pub use census::Census;
+pub use continuous::Continuous;
+pub use engine::Engine;
+pub use grid::Grid;
+pub use hashlife::Hashlife;
+pub use pattern::Pattern;
+pub use period::Detector;
+pub use random::Random;
+pub use rule::{ParseRuleError, Rule};
+pub use spacetime::SpaceTime;
+pub use sparse::Sparse;
+pub use topology::Topology;
+pub use update::Update;
+pub use volume::Volume;
@@ -1,3 +1,293 @@
-fn main() {
-    println!("Hello, world!");
+mod app;
+mod ui;
+
+use std::{
+    env,
+    error::Error,
+    fs, io, panic,
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+    thread,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::{
+    event::{self, Event},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use rs_game_of_life::{
+    format::{self, macrocell, rulefile, Format},
+    rule::builtin,
+    Pattern, Rule, Topology, Update,
+};
+use tui::{backend::CrosstermBackend, layout::Rect, Terminal};
+
+use app::{App, EngineKind};
+
+const USAGE: &str =
+    "usage: rs-game-of-life [--rule RULESTRING|NAME|FILE.rule] [--engine grid|bitgrid|hashlife|sparse|spacetime|blocks|volume|continuous] [--threads N] [--update sync|sequential|async:ALPHA|prob:BIRTH,SURVIVAL] [--seed N] [PATTERN]";
+
+/// Panics on threads other than the main one, held back while the viewer
+/// is still drawing and printed once the terminal is restored.
+static WORKER_PANICS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Settings taken from the command line.
+struct Options {
+    rule: Option<Rule>,
+    engine: Option<EngineKind>,
+    /// Threads per step for the grid engine, 0 for one per core.
+    threads: usize,
+    update: Update,
+    /// Seed for soups and stochastic updates, taken from the clock unless
+    /// given.
+    seed: u64,
+    pattern: Option<PathBuf>,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            rule: None,
+            engine: None,
+            threads: 1,
+            update: Update::Synchronous,
+            seed: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+            pattern: None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--rule" | "-r" => {
+                    let value = args.next().ok_or("--rule needs a rulestring")?;
+                    options.rule = Some(
+                        load_rule(&value, Path::new("."))
+                            .map_err(|e| format!("invalid rule '{}': {}", value, e))?,
+                    );
+                }
+                "--engine" | "-e" => {
+                    let value = args.next().ok_or("--engine needs a name")?;
+                    options.engine = Some(value.parse()?);
+                }
+                "--threads" | "-t" => {
+                    let value = args.next().ok_or("--threads needs a count")?;
+                    options.threads = value
+                        .parse()
+                        .map_err(|_| format!("invalid thread count '{}'", value))?;
+                }
+                "--update" | "-u" => {
+                    let value = args.next().ok_or("--update needs a scheme")?;
+                    options.update = value.parse().map_err(|e| format!("{}", e))?;
+                }
+                "--seed" | "-s" => {
+                    let value = args.next().ok_or("--seed needs a number")?;
+                    options.seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed '{}'", value))?;
+                }
+                "--help" | "-h" => {
+                    return Err(format!(
+                        "{}\nbuilt-in rules: {}",
+                        USAGE,
+                        builtin::NAMES.join(", ")
+                    ))
+                }
+                _ if !arg.starts_with('-') && options.pattern.is_none() => {
+                    options.pattern = Some(arg.into())
+                }
+                _ => return Err(format!("unexpected argument '{}'\n{}", arg, USAGE)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Builds the viewer the command line asks for: the pattern file if one
+/// was given, on the requested engine. Macrocell files are kept as a tree
+/// when they run on Hashlife, which is the default for them.
+fn build_app(options: &Options, view_size: (usize, usize)) -> Result<App, String> {
+    let path = match &options.pattern {
+        Some(path) => path,
+        None => {
+            let rule = options.rule.clone().unwrap_or(Rule::CONWAY);
+            let engine = options
+                .engine
+                .unwrap_or_else(|| EngineKind::default_for(&rule));
+            engine.supports(&rule)?;
+            engine.supports_update(options.update)?;
+            return Ok(App::new(engine, rule, None, view_size, options.seed));
+        }
+    };
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    let in_file = |e| format!("{}: {}", path.display(), e);
+
+    let is_macrocell = format::detect(&text) == Format::Macrocell;
+    if is_macrocell
+        && options.rule.is_none()
+        && matches!(options.engine, None | Some(EngineKind::Hashlife))
+    {
+        EngineKind::Hashlife.supports_update(options.update)?;
+        let universe = macrocell::parse(&text).map_err(in_file)?;
+        return Ok(App::from_engine(
+            Box::new(universe),
+            view_size,
+            options.seed,
+        ));
+    }
+
+    let pattern = format::parse(&text).map_err(in_file)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let rule = resolve_rule(options, &pattern, dir)?;
+    let default_engine = if is_macrocell {
+        EngineKind::Hashlife
+    } else {
+        EngineKind::default_for(&rule)
+    };
+    let engine = options.engine.unwrap_or(default_engine);
+    engine.supports(&rule)?;
+    engine.supports_update(options.update)?;
+    Ok(App::new(
+        engine,
+        rule,
+        Some(pattern),
+        view_size,
+        options.seed,
+    ))
+}
+
+/// The rule given on the command line wins over the one in the pattern file,
+/// whose rule files are looked for in `dir`.
+fn resolve_rule(options: &Options, pattern: &Pattern, dir: &Path) -> Result<Rule, String> {
+    if let Some(rule) = &options.rule {
+        return Ok(rule.clone());
+    }
+    match &pattern.rule {
+        Some(rule) => load_rule(rule, dir)
+            .map_err(|e| format!("pattern rule '{}' is not supported: {}", rule, e)),
+        None => Ok(Rule::CONWAY),
+    }
+}
+
+/// Reads a rulestring or built-in rule name, or a Golly `.rule` file given
+/// by its path or, for any other name such as `Byl`, found as `Byl.rule` in
+/// `dir`. A bounded-grid suffix applies to either.
+fn load_rule(value: &str, dir: &Path) -> Result<Rule, String> {
+    let (name, topology) = match value.split_once(':') {
+        Some((name, topology)) => (name.trim(), Some(topology)),
+        None => (value.trim(), None),
+    };
+    let path = if name.ends_with(".rule") {
+        PathBuf::from(name)
+    } else {
+        match value.parse() {
+            Ok(rule) => return Ok(rule),
+            Err(e) => {
+                let path = dir.join(format!("{}.rule", name));
+                if !path.is_file() {
+                    return Err(e.to_string());
+                }
+                path
+            }
+        }
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    let rule = rulefile::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let topology = topology
+        .map(|topology| topology.trim().parse::<Topology>())
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    Ok(rule.with_topology(topology))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let exit = |message: String| -> ! {
+        eprintln!("{}", message);
+        process::exit(2);
+    };
+    let options = Options::from_args(env::args().skip(1)).unwrap_or_else(|e| exit(e));
+    let (width, height) = terminal::size()?;
+    let view_size = ui::board_size(Rect::new(0, 0, width, height));
+    let mut app = build_app(&options, view_size).unwrap_or_else(|e| exit(e));
+    app.set_threads(options.threads);
+    app.set_update(options.update);
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // A worker such as the census thread reports its failure to the
+        // viewer, which keeps running on the screen it has set up.
+        let current = thread::current();
+        if current.name() != Some("main") {
+            if let Ok(mut panics) = WORKER_PANICS.lock() {
+                let name = current.name().unwrap_or("<unnamed>");
+                panics.push(format!("thread '{}' {}", name, info));
+            }
+            return;
+        }
+        let _ = restore_terminal();
+        report_worker_panics();
+        default_hook(info);
+    }));
+
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    terminal.hide_cursor()?;
+
+    let result = run(&mut terminal, app);
+    restore_terminal()?;
+    report_worker_panics();
+    result
+}
+
+fn run(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    mut app: App,
+) -> Result<(), Box<dyn Error>> {
+    let mut last_tick = Instant::now();
+
+    while !app.should_quit {
+        terminal.draw(|f| ui::draw(f, &app))?;
+
+        let timeout = app
+            .tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_default();
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) => {
+                    app.message = None;
+                    app.on_key(key);
+                }
+                Event::Resize(width, height) => {
+                    app.on_resize(ui::board_size(Rect::new(0, 0, width, height)))
+                }
+                _ => {}
+            }
+        }
+        if last_tick.elapsed() >= app.tick_rate {
+            app.on_tick();
+            last_tick = Instant::now();
+        }
+    }
+    Ok(())
+}
+
+/// Prints the panics held back from other threads.
+fn report_worker_panics() {
+    if let Ok(mut panics) = WORKER_PANICS.lock() {
+        for panic in panics.drain(..) {
+            eprintln!("{}", panic);
+        }
+    }
+}
+
+/// Leaves the alternate screen and raw mode so the shell is usable again.
+fn restore_terminal() -> Result<(), Box<dyn Error>> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, crossterm::cursor::Show)?;
+    Ok(())
 }
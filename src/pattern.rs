@@ -0,0 +1,129 @@
+//! Engine-independent description of a pattern, as exchanged through files.
+
+use crate::grid::Grid;
+
+/// Cells of a pattern together with the metadata pattern files carry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    /// Pattern name, from `#N` lines.
+    pub name: Option<String>,
+    /// Author or origin, from `#O` lines.
+    pub author: Option<String>,
+    /// Free-form comment lines.
+    pub comments: Vec<String>,
+    /// Rulestring the pattern is meant to run under, if the file names one.
+    pub rule: Option<String>,
+    /// Non-zero cells as `(x, y, state)`. A two-state pattern only uses
+    /// state 1.
+    pub cells: Vec<(i64, i64, u8)>,
+}
+
+/// Smallest rectangle containing every cell of a pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub left: i64,
+    pub top: i64,
+    pub width: u64,
+    pub height: u64,
+}
+
+impl Pattern {
+    /// Captures the live and dying cells of `grid`, along with its rule.
+    pub fn from_grid(grid: &Grid) -> Self {
+        let mut cells = Vec::new();
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let state = grid.state(x, y);
+                if state != 0 {
+                    cells.push((x as i64, y as i64, state));
+                }
+            }
+        }
+        Pattern {
+            rule: Some(grid.rule().to_string()),
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    /// Bounding box of the cells, or `None` for an empty pattern.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let (first, rest) = self.cells.split_first()?;
+        let (mut left, mut top, mut right, mut bottom) = (first.0, first.1, first.0, first.1);
+        for &(x, y, _) in rest {
+            left = left.min(x);
+            right = right.max(x);
+            top = top.min(y);
+            bottom = bottom.max(y);
+        }
+        Some(Bounds {
+            left,
+            top,
+            width: (right - left) as u64 + 1,
+            height: (bottom - top) as u64 + 1,
+        })
+    }
+
+    /// Largest cell state used, 0 for an empty pattern.
+    pub fn max_state(&self) -> u8 {
+        self.cells
+            .iter()
+            .map(|&(_, _, state)| state)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Writes the pattern onto `grid`, centred. Cells that fall outside the
+    /// grid are dropped.
+    pub fn place_centered(&self, grid: &mut Grid) {
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let left = (grid.width() as i64 - bounds.width as i64) / 2 - bounds.left;
+        let top = (grid.height() as i64 - bounds.height as i64) / 2 - bounds.top;
+        for &(x, y, state) in &self.cells {
+            let (x, y) = (x + left, y + top);
+            if x >= 0 && y >= 0 && (x as usize) < grid.width() && (y as usize) < grid.height() {
+                grid.set_state(x as usize, y as usize, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_cover_every_cell() {
+        let pattern = Pattern {
+            cells: vec![(-2, 5, 1), (3, -1, 2), (0, 0, 1)],
+            ..Pattern::default()
+        };
+        assert_eq!(
+            pattern.bounds(),
+            Some(Bounds {
+                left: -2,
+                top: -1,
+                width: 6,
+                height: 7
+            })
+        );
+        assert_eq!(pattern.max_state(), 2);
+        assert_eq!(Pattern::default().bounds(), None);
+    }
+
+    #[test]
+    fn placing_centres_and_round_trips_through_a_grid() {
+        let pattern = Pattern {
+            cells: vec![(10, 10, 1), (11, 10, 1), (12, 10, 1)],
+            ..Pattern::default()
+        };
+        let mut grid = Grid::new(5, 5);
+        pattern.place_centered(&mut grid);
+        let placed = Pattern::from_grid(&grid);
+        assert_eq!(placed.cells, [(1, 2, 1), (2, 2, 1), (3, 2, 1)]);
+        assert_eq!(placed.rule.as_deref(), Some("B3/S23"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Spotting when a pattern repeats, by hashing the live cells of each
+//! generation and looking for a hash seen before.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use crate::blocks::Blocks;
+use crate::engine::Engine;
+
+/// Most generations remembered at once. Once this many have gone by
+/// without a repeat the detector starts afresh, so longer periods go
+/// unnoticed.
+const MAX_HISTORY: usize = 1 << 16;
+
+/// What a pattern turns out to be once a generation repeats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Periodicity {
+    /// No cells are left alive.
+    Extinct,
+    /// Every generation is the same.
+    StillLife,
+    /// The pattern comes back in place every `period` generations.
+    Oscillator { period: u64 },
+    /// The pattern comes back every `period` generations, moved by
+    /// `displacement` cells.
+    Spaceship {
+        period: u64,
+        displacement: (i64, i64),
+    },
+}
+
+impl Periodicity {
+    /// Generations between repeats; 1 for still lifes and dead universes.
+    pub fn period(&self) -> u64 {
+        match *self {
+            Periodicity::Extinct | Periodicity::StillLife => 1,
+            Periodicity::Oscillator { period } | Periodicity::Spaceship { period, .. } => period,
+        }
+    }
+}
+
+impl fmt::Display for Periodicity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Periodicity::Extinct => write!(f, "extinct"),
+            Periodicity::StillLife => write!(f, "still life"),
+            Periodicity::Oscillator { period } => write!(f, "oscillator p{}", period),
+            Periodicity::Spaceship {
+                period,
+                displacement: (dx, dy),
+            } => write!(f, "spaceship p{} moving ({}, {})", period, dx, dy),
+        }
+    }
+}
+
+/// Watches the generations of a universe go by and reports the first time
+/// one of them repeats an earlier one, optionally allowing it to have
+/// moved.
+///
+/// Generations are told apart by a 64-bit hash of their live cells, listed
+/// from the top-left corner of their bounding box when moves are allowed,
+/// so a false match is possible but vanishingly unlikely.
+#[derive(Clone, Debug)]
+pub struct Detector {
+    translations: bool,
+    /// For each generation's hash, its number and the top-left corner of
+    /// its bounding box.
+    seen: HashMap<u64, (u64, (i64, i64))>,
+    /// The generation observed last, which the next one must follow.
+    last: Option<u64>,
+    found: Option<Periodicity>,
+}
+
+impl Detector {
+    /// Creates a detector that, when `translations` is set, also spots
+    /// patterns that come back somewhere else.
+    pub fn new(translations: bool) -> Self {
+        Detector {
+            translations,
+            seen: HashMap::new(),
+            last: None,
+            found: None,
+        }
+    }
+
+    /// Whether patterns that come back moved count as repeats.
+    pub fn translations(&self) -> bool {
+        self.translations
+    }
+
+    /// What the universe turned out to be, once a generation has repeated.
+    pub fn periodicity(&self) -> Option<Periodicity> {
+        self.found
+    }
+
+    /// Forgets every generation seen, as after the universe is edited.
+    pub fn reset(&mut self) {
+        self.seen.clear();
+        self.last = None;
+        self.found = None;
+    }
+
+    /// Looks at the universe's current generation, returning what it has
+    /// turned out to be once a generation has repeated. Skipping
+    /// generations, or going back, starts the search again.
+    pub fn observe(&mut self, engine: &dyn Engine) -> Option<Periodicity> {
+        let generation = engine.generation();
+        if self.last.is_some_and(|last| last + 1 != generation) {
+            self.reset();
+        }
+        self.last = Some(generation);
+        if self.found.is_some() {
+            return self.found;
+        }
+
+        let mut cells = engine.live_cells();
+        if cells.is_empty() {
+            self.found = Some(Periodicity::Extinct);
+            return self.found;
+        }
+        cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+        let corner = if self.translations {
+            let left = cells.iter().map(|&(x, _, _)| x).min().unwrap();
+            (left, cells[0].1)
+        } else {
+            (0, 0)
+        };
+        let mut hasher = DefaultHasher::new();
+        // Block rules only repeat when the blocks line up the same way.
+        if engine.as_any().is::<Blocks>() {
+            (generation % 2).hash(&mut hasher);
+        }
+        for &(x, y, state) in &cells {
+            (x - corner.0, y - corner.1, state).hash(&mut hasher);
+        }
+
+        match self.seen.get(&hasher.finish()) {
+            Some(&(earlier, earlier_corner)) => {
+                let period = generation - earlier;
+                let displacement = (corner.0 - earlier_corner.0, corner.1 - earlier_corner.1);
+                self.found = Some(match displacement {
+                    (0, 0) if period == 1 => Periodicity::StillLife,
+                    (0, 0) => Periodicity::Oscillator { period },
+                    _ => Periodicity::Spaceship {
+                        period,
+                        displacement,
+                    },
+                });
+            }
+            None => {
+                if self.seen.len() >= MAX_HISTORY {
+                    self.seen.clear();
+                }
+                self.seen.insert(hasher.finish(), (generation, corner));
+            }
+        }
+        self.found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rule::Rule, sparse::Sparse};
+
+    const BLOCK: &[(i64, i64)] = &[(0, 0), (1, 0), (0, 1), (1, 1)];
+    const BLINKER: &[(i64, i64)] = &[(0, 1), (1, 1), (2, 1)];
+    const GLIDER: &[(i64, i64)] = &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+    fn life(cells: &[(i64, i64)]) -> Sparse {
+        let mut engine = Sparse::new(Rule::CONWAY);
+        for &(x, y) in cells {
+            engine.set_cell(x, y, 1);
+        }
+        engine
+    }
+
+    /// What a detector makes of `cells` within 100 generations.
+    fn detect(cells: &[(i64, i64)], translations: bool) -> Option<Periodicity> {
+        let mut engine = life(cells);
+        let mut detector = Detector::new(translations);
+        for _ in 0..100 {
+            if let Some(found) = detector.observe(&engine) {
+                return Some(found);
+            }
+            engine.step();
+        }
+        None
+    }
+
+    #[test]
+    fn tells_still_lifes_oscillators_and_spaceships_apart() {
+        assert_eq!(detect(BLOCK, false), Some(Periodicity::StillLife));
+        assert_eq!(
+            detect(BLINKER, false),
+            Some(Periodicity::Oscillator { period: 2 })
+        );
+        assert_eq!(
+            detect(GLIDER, true),
+            Some(Periodicity::Spaceship {
+                period: 4,
+                displacement: (1, 1)
+            })
+        );
+        assert_eq!(detect(GLIDER, false), None);
+        assert_eq!(detect(&[(0, 0)], false), Some(Periodicity::Extinct));
+        // Moves are allowed, not required.
+        assert_eq!(
+            detect(BLINKER, true),
+            Some(Periodicity::Oscillator { period: 2 })
+        );
+    }
+
+    #[test]
+    fn skipped_generations_start_again() {
+        let mut engine = life(BLINKER);
+        let mut detector = Detector::new(false);
+        detector.observe(&engine);
+        engine.step();
+        engine.step();
+        // Generation 2 matches generation 0, but generation 1 went unseen.
+        assert_eq!(detector.observe(&engine), None);
+        engine.step();
+        assert_eq!(detector.observe(&engine), None);
+        engine.step();
+        assert_eq!(
+            detector.observe(&engine),
+            Some(Periodicity::Oscillator { period: 2 })
+        );
+
+        detector.reset();
+        assert_eq!(detector.periodicity(), None);
+        assert_eq!(detector.observe(&engine), None);
+    }
+
+    #[test]
+    fn displays_what_was_found() {
+        assert_eq!(Periodicity::StillLife.to_string(), "still life");
+        assert_eq!(
+            Periodicity::Oscillator { period: 15 }.to_string(),
+            "oscillator p15"
+        );
+        let glider = Periodicity::Spaceship {
+            period: 4,
+            displacement: (1, 1),
+        };
+        assert_eq!(glider.to_string(), "spaceship p4 moving (1, 1)");
+        assert_eq!(glider.period(), 4);
+        assert_eq!(Periodicity::Extinct.period(), 1);
+    }
+}
@@ -0,0 +1,105 @@
+//! A small seedable random number generator, so that runs with random
+//! soups or stochastic updates can be repeated exactly.
+
+/// xorshift64* with its state scrambled from the seed by SplitMix64, so
+/// that nearby seeds give unrelated sequences and 0 is a valid seed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // xorshift never leaves the all-zero state.
+        Random { state: z | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `true` with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+
+    /// A number below `bound`, which must not be 0.
+    pub fn below(&mut self, bound: usize) -> usize {
+        ((u128::from(self.next_u64()) * bound as u128) >> 64) as usize
+    }
+
+    /// Puts `items` in a random order, each order equally likely.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_repeat_their_sequence() {
+        let first: Vec<u64> = (0..8)
+            .scan(Random::new(7), |r, _| Some(r.next_u64()))
+            .collect();
+        let again: Vec<u64> = (0..8)
+            .scan(Random::new(7), |r, _| Some(r.next_u64()))
+            .collect();
+        let other: Vec<u64> = (0..8)
+            .scan(Random::new(8), |r, _| Some(r.next_u64()))
+            .collect();
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+        assert_ne!(Random::new(0).next_u64(), 0);
+    }
+
+    #[test]
+    fn numbers_stay_in_range() {
+        let mut random = Random::new(1);
+        let mut seen = [0; 6];
+        for _ in 0..6000 {
+            let f = random.next_f64();
+            assert!((0.0..1.0).contains(&f));
+            seen[random.below(6)] += 1;
+        }
+        // Each face of a fair die comes up about 1000 times.
+        assert!(
+            seen.iter().all(|&count| (850..1150).contains(&count)),
+            "{:?}",
+            seen
+        );
+        assert!(!random.chance(0.0));
+        assert!(random.chance(1.0));
+    }
+
+    #[test]
+    fn shuffling_permutes() {
+        let mut random = Random::new(3);
+        let mut items: Vec<usize> = (0..50).collect();
+        random.shuffle(&mut items);
+        assert_ne!(items, (0..50).collect::<Vec<_>>());
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..50).collect::<Vec<_>>());
+
+        let mut again: Vec<usize> = (0..50).collect();
+        Random::new(3).shuffle(&mut again);
+        assert_eq!(items, again);
+    }
+}
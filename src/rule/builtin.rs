@@ -0,0 +1,222 @@
+//! Classic automata that can be asked for by name instead of by rulestring
+//! or rule file.
+
+use super::{Rule, RuleTable, TableNeighbourhood, Transition};
+use crate::format::rulefile;
+
+/// The names [`find`] knows, as they are usually written.
+pub const NAMES: [&str; 5] = ["WireWorld", "LangtonsAnt", "BriansBrain", "Critters", "BBM"];
+
+/// Brian Silverman's WireWorld: electrons made of a head and a tail run
+/// along copper wires.
+const WIREWORLD: &str = "\
+@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+@COLORS
+1 0 128 255
+2 255 255 255
+3 255 128 0
+@NAMES
+0 empty
+1 electron head
+2 electron tail
+3 copper
+";
+
+/// The built-in rule called `name`, ignoring case and any `'`, `-` or `_`
+/// in it, so that `Langton's-Ant` and `langtons_ant` both work.
+pub(super) fn find(name: &str) -> Option<Rule> {
+    let key: String = name
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '-' | '_' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    match key.as_str() {
+        "wireworld" => Some(rulefile::parse(WIREWORLD).expect("WireWorld is a valid rule file")),
+        "langtonsant" => Some(Rule::from_table(langtons_ant())),
+        // Generations rules already cover Brian's Brain: cells fire with
+        // two firing neighbours and always rest for a generation after.
+        "briansbrain" => Some(Rule::new(&[2], &[]).with_states(3)),
+        // Tommaso Toffoli and Norman Margolus's reversible block rules:
+        // Critters inverts every block but those with two live cells,
+        // turning those with three half a turn as well, and the billiard
+        // ball machine bounces balls off each other and off walls.
+        "critters" => Some(block_rule("M15,14,13,3,11,5,6,1,7,9,10,2,12,4,8,0")),
+        "bbm" | "billiardballmachine" => Some(block_rule("M0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,15")),
+        _ => None,
+    }
+}
+
+fn block_rule(rulestring: &str) -> Rule {
+    rulestring.parse().expect("built-in block rules are valid")
+}
+
+/// Langton's ant as a rule table on the von Neumann neighbourhood. States 0
+/// and 1 are white and black squares, and states 2 to 9 the ant on a white
+/// (2 to 5) or black (6 to 9) square facing north, east, south or west. On
+/// white the ant turns right, on black left; it then flips its square and
+/// steps forward.
+fn langtons_ant() -> RuleTable {
+    const DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+    let ant = |colour: u8, direction: u8| 2 + 4 * colour + direction;
+    let any: Vec<u8> = (0..10).collect();
+    let mut transitions = Vec::new();
+    // The ant leaves its square, flipping it.
+    for colour in 0..2 {
+        transitions.push(Transition {
+            inputs: std::iter::once((0..4).map(|direction| ant(colour, direction)).collect())
+                .chain(vec![any.clone(); 4])
+                .collect(),
+            output: 1 - colour,
+        });
+    }
+    // The ant arrives from the neighbour in clockwise position `from`, which
+    // it leaves heading the opposite way.
+    for square in 0..2 {
+        for from in 0..4 {
+            let heading = (from + 2) % 4;
+            for colour in 0..2 {
+                let turn = if colour == 0 { 1 } else { 3 };
+                let facing = (heading + 4 - turn) % 4;
+                let mut inputs = vec![vec![square]];
+                inputs.extend((0..4).map(|position| {
+                    if position == from {
+                        vec![ant(colour, facing)]
+                    } else {
+                        any.clone()
+                    }
+                }));
+                transitions.push(Transition {
+                    inputs,
+                    output: ant(square, heading),
+                });
+            }
+        }
+    }
+    let mut table = RuleTable::from_transitions(
+        "LangtonsAnt".to_string(),
+        10,
+        TableNeighbourhood::VonNeumann,
+        &transitions,
+    );
+    table.set_name(0, "white".to_string());
+    table.set_name(1, "black".to_string());
+    table.set_color(1, (160, 160, 160));
+    for colour in 0..2 {
+        for (direction, name) in DIRECTIONS.iter().enumerate() {
+            let state = ant(colour, direction as u8);
+            let square = if colour == 0 { "white" } else { "black" };
+            table.set_name(state, format!("ant facing {} on {}", name, square));
+            table.set_color(
+                state,
+                if colour == 0 {
+                    (255, 0, 0)
+                } else {
+                    (255, 0, 255)
+                },
+            );
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    /// A board running the built-in rule `name` with the cells in `cells`
+    /// set to their states.
+    fn board(name: &str, width: usize, height: usize, cells: &[(usize, usize, u8)]) -> Grid {
+        let mut grid = Grid::with_rule(width, height, find(name).unwrap());
+        for &(x, y, state) in cells {
+            grid.set_state(x, y, state);
+        }
+        grid
+    }
+
+    #[test]
+    fn every_name_is_found() {
+        for name in NAMES {
+            let rule: Rule = name.parse().unwrap();
+            assert_eq!(Some(rule), find(name));
+        }
+        assert_eq!(find("Langton's-Ant"), find("langtons_ant"));
+        assert_eq!(find("Brian's Brain").unwrap().to_string(), "B2/S/C3");
+        assert!(find("critters").unwrap().margolus().is_some());
+        assert!(find("Billiard-Ball-Machine").unwrap().margolus().is_some());
+        assert_eq!(find("Langton"), None);
+    }
+
+    #[test]
+    fn wireworld_electrons_follow_the_wire() {
+        let mut grid = board(
+            "WireWorld",
+            8,
+            3,
+            &[(0, 1, 2), (1, 1, 1), (2, 1, 3), (3, 1, 3), (4, 1, 3)],
+        );
+        grid.step();
+        let row: Vec<_> = (0..5).map(|x| grid.state(x, 1)).collect();
+        assert_eq!(row, [3, 2, 1, 3, 3]);
+        grid.step();
+        grid.step();
+        let row: Vec<_> = (0..5).map(|x| grid.state(x, 1)).collect();
+        assert_eq!(row, [3, 3, 3, 2, 1]);
+        assert_eq!(grid.rule().state_name(3), "copper");
+    }
+
+    #[test]
+    fn langtons_ant_turns_and_flips_squares() {
+        // The ant starts on white facing north, so turns right four times
+        // round a square before finding black underneath it.
+        let mut grid = board("LangtonsAnt", 11, 11, &[(5, 5, 2)]);
+        for _ in 0..4 {
+            grid.step();
+        }
+        assert_eq!(grid.state(5, 5), 6);
+        assert_eq!(
+            [grid.state(6, 5), grid.state(6, 6), grid.state(5, 6)],
+            [1, 1, 1]
+        );
+        grid.step();
+        assert_eq!(grid.state(5, 5), 0);
+        assert_eq!(grid.state(4, 5), 2 + 3);
+        assert_eq!(grid.rule().state_name(5), "ant facing west on white");
+    }
+
+    #[test]
+    fn langtons_ant_never_splits() {
+        let mut grid = board("LangtonsAnt", 40, 40, &[(20, 20, 2)]);
+        for _ in 0..200 {
+            grid.step();
+            let ants = (0..40)
+                .flat_map(|y| (0..40).map(move |x| (x, y)))
+                .filter(|&(x, y)| grid.state(x, y) >= 2)
+                .count();
+            assert_eq!(ants, 1);
+        }
+    }
+}
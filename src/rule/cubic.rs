@@ -0,0 +1,226 @@
+//! Totalistic rules on a cubic lattice of cells in Carter Bays' `B/S`
+//! notation, written as `B5/S45/3D` for the 26 surrounding cells or
+//! `B1/S1/3D6` for the 6 face neighbours.
+
+use std::fmt;
+
+use super::{parse_states, ParseRuleError};
+
+/// Which cells around a cube count as its neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CubicNeighbourhood {
+    /// The 26 cubes sharing a face, an edge or a corner.
+    Moore,
+    /// The 6 cubes sharing a face.
+    VonNeumann,
+}
+
+impl CubicNeighbourhood {
+    /// Number of neighbours a cell has.
+    pub fn size(self) -> u8 {
+        match self {
+            CubicNeighbourhood::Moore => 26,
+            CubicNeighbourhood::VonNeumann => 6,
+        }
+    }
+}
+
+/// The neighbourhood and counts of a 3D rule. Bit `n` of a mask stands for
+/// `n` neighbours in state 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cubic {
+    neighbourhood: CubicNeighbourhood,
+    birth: u32,
+    survival: u32,
+}
+
+impl Cubic {
+    pub fn neighbourhood(&self) -> CubicNeighbourhood {
+        self.neighbourhood
+    }
+
+    /// Whether a dead cell with `count` live neighbours is born.
+    pub fn is_birth(&self, count: u32) -> bool {
+        count < 32 && self.birth & 1 << count != 0
+    }
+
+    /// Whether a live cell with `count` live neighbours survives.
+    pub fn is_survival(&self, count: u32) -> bool {
+        count < 32 && self.survival & 1 << count != 0
+    }
+
+    /// Writes the rule in canonical form, with its number of states when
+    /// it has dying ones.
+    pub(super) fn write(&self, states: u16, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        write_counts(f, self.birth)?;
+        write!(f, "/S")?;
+        write_counts(f, self.survival)?;
+        if states > 2 {
+            write!(f, "/C{}", states)?;
+        }
+        match self.neighbourhood {
+            CubicNeighbourhood::Moore => write!(f, "/3D"),
+            CubicNeighbourhood::VonNeumann => write!(f, "/3D6"),
+        }
+    }
+}
+
+/// Writes counts as digits when they are all single digits, and separated
+/// by commas otherwise.
+fn write_counts(f: &mut fmt::Formatter, mask: u32) -> fmt::Result {
+    let counts: Vec<u32> = (0..32).filter(|count| mask & 1 << count != 0).collect();
+    let separator = if counts.iter().all(|&count| count < 10) {
+        ""
+    } else {
+        ","
+    };
+    for (i, count) in counts.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{}", separator)?;
+        }
+        write!(f, "{}", count)?;
+    }
+    Ok(())
+}
+
+/// Splits a `/3D`, `/3D26` or `/3D6` suffix off `s`, returning the rest
+/// of the rulestring and the neighbourhood the suffix selects.
+pub(super) fn strip_suffix(s: &str) -> Option<(&str, CubicNeighbourhood)> {
+    let slash = s.rfind('/')?;
+    let neighbourhood = match s[slash + 1..].to_ascii_uppercase().as_str() {
+        "3D" | "3D26" => CubicNeighbourhood::Moore,
+        "3D6" => CubicNeighbourhood::VonNeumann,
+        _ => return None,
+    };
+    Some((&s[..slash], neighbourhood))
+}
+
+/// Parses the `B`, `S` and optional `C` sections of a 3D rule, in any
+/// order and separated by slashes, returning the rule and its number of
+/// states. Counts are single digits, as in `S45`, or numbers and ranges
+/// separated by commas, as in `S4,5,13..26`.
+pub(super) fn parse(
+    s: &str,
+    neighbourhood: CubicNeighbourhood,
+) -> Result<(Cubic, u16), ParseRuleError> {
+    let mut sections: [Option<&str>; 3] = [None; 3];
+    let mut offset = 0;
+    for part in s.split('/') {
+        let name = part.chars().next().map(|c| c.to_ascii_uppercase());
+        let index = match name {
+            Some('B') => 0,
+            Some('S') => 1,
+            Some('C') => 2,
+            _ => {
+                return Err(ParseRuleError::UnexpectedChar {
+                    position: offset,
+                    found: part.chars().next().unwrap_or('/'),
+                })
+            }
+        };
+        if sections[index].replace(&part[1..]).is_some() {
+            return Err(ParseRuleError::DuplicateSection(name.unwrap()));
+        }
+        offset += part.len() + 1;
+    }
+    let [birth, survival, states] = sections;
+    let max = u32::from(neighbourhood.size());
+    let counts = |name: char, section: Option<&str>| {
+        let section = section.ok_or(ParseRuleError::MissingSection(name))?;
+        parse_counts(name, section, max)
+    };
+    let birth = counts('B', birth)?;
+    let survival = counts('S', survival)?;
+    let states = match states {
+        Some(states) => parse_states(states)?,
+        None => 2,
+    };
+    Ok((
+        Cubic {
+            neighbourhood,
+            birth,
+            survival,
+        },
+        states,
+    ))
+}
+
+/// Parses the counts of section `name`, none of which may exceed `max`.
+fn parse_counts(name: char, section: &str, max: u32) -> Result<u32, ParseRuleError> {
+    let invalid = |value: &str| ParseRuleError::InvalidParameter {
+        name,
+        value: value.to_string(),
+    };
+    let mut mask = 0;
+    if !section.contains(',') && !section.contains("..") {
+        for c in section.chars() {
+            match c.to_digit(10) {
+                Some(count) if count <= max => mask |= 1 << count,
+                Some(_) => return Err(ParseRuleError::CountOutOfRange(c)),
+                None => return Err(invalid(section)),
+            }
+        }
+        return Ok(mask);
+    }
+    for item in section.split(',') {
+        let (low, high) = item.split_once("..").unwrap_or((item, item));
+        match (low.trim().parse::<u32>(), high.trim().parse::<u32>()) {
+            (Ok(low), Ok(high)) if low <= high && high <= max => {
+                mask |= (low..=high).fold(0, |mask, count| mask | 1 << count)
+            }
+            _ => return Err(invalid(item)),
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "B5/S45/3D",
+            "B1/S1/3D6",
+            "B5/S45/C4/3D",
+            "B14,15/S4/3D",
+            "B/S/3D",
+        ] {
+            assert_eq!(s.parse::<Rule>().unwrap().to_string(), s);
+        }
+        let rule: Rule = "S4,5,13..15/b5/3d26".parse().unwrap();
+        assert_eq!(rule.to_string(), "B5/S4,5,13,14,15/3D");
+    }
+
+    #[test]
+    fn counts_pick_births_and_survivals() {
+        let (cubic, states) = parse("B5/S4,5,13..26", CubicNeighbourhood::Moore).unwrap();
+        assert_eq!(states, 2);
+        assert!(cubic.is_birth(5) && !cubic.is_birth(4));
+        assert!(cubic.is_survival(13) && cubic.is_survival(26) && !cubic.is_survival(12));
+        assert!(!cubic.is_survival(40));
+        assert_eq!(
+            strip_suffix("B5/S45/3D6"),
+            Some(("B5/S45", CubicNeighbourhood::VonNeumann))
+        );
+        assert_eq!(strip_suffix("B5/S45"), None);
+    }
+
+    #[test]
+    fn rejects_counts_beyond_the_neighbourhood() {
+        let error = |s: &str| s.parse::<Rule>().unwrap_err();
+        assert_eq!(error("B7/S/3D6"), ParseRuleError::CountOutOfRange('7'));
+        assert_eq!(
+            error("B30,1/S/3D"),
+            ParseRuleError::InvalidParameter {
+                name: 'B',
+                value: "30".to_string()
+            }
+        );
+        assert_eq!(error("B5/3D"), ParseRuleError::MissingSection('S'));
+        assert_eq!(error("B5/S4/B6/3D"), ParseRuleError::DuplicateSection('B'));
+    }
+}
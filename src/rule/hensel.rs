@@ -0,0 +1,229 @@
+//! Sets of neighbourhoods and Hensel's letters for naming them.
+//!
+//! A neighbourhood is the arrangement of live cells among the eight around
+//! a cell, packed into a byte with one bit per neighbour in the order of
+//! [`NEIGHBOURS`]. Isotropic rules treat all rotations and reflections of a
+//! neighbourhood alike; Hensel notation names each such class by its number
+//! of live cells and a letter, as in `2a` or `4w`.
+
+use std::fmt;
+
+use super::NEIGHBOURS;
+
+/// Letters naming the classes of neighbourhoods with 0 to 4 live cells, in
+/// the canonical order. Classes with 5 to 8 live cells take the letter of
+/// their complement.
+const LETTERS: [&str; 5] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz"];
+
+/// One neighbourhood from each class, in the order of [`LETTERS`].
+const REPRESENTATIVES: [&[u8]; 5] = [
+    &[],
+    &[1, 2],
+    &[5, 10, 3, 24, 17, 36],
+    &[37, 26, 11, 7, 50, 13, 14, 38, 25, 49],
+    &[165, 90, 15, 29, 51, 39, 58, 54, 27, 53, 57, 46, 60],
+];
+
+/// A set of neighbourhoods, one bit for each of the 256 arrangements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(super) struct Neighbourhoods([u64; 4]);
+
+impl Neighbourhoods {
+    pub const EMPTY: Neighbourhoods = Neighbourhoods([0; 4]);
+
+    /// Every neighbourhood whose number of live cells has its bit set in
+    /// `counts`.
+    pub const fn with_counts(counts: u16) -> Self {
+        let mut words = [0; 4];
+        let mut neighbourhood = 0;
+        while neighbourhood < 256 {
+            if counts >> (neighbourhood as u8).count_ones() & 1 != 0 {
+                words[neighbourhood / 64] |= 1 << (neighbourhood % 64);
+            }
+            neighbourhood += 1;
+        }
+        Neighbourhoods(words)
+    }
+
+    /// The class Hensel names `count` followed by `letter`, or `None` if
+    /// there is no such class.
+    pub fn with_letter(count: u8, letter: char) -> Option<Self> {
+        let (letters, complement) = letters(count);
+        let index = letters.find(letter)?;
+        let representative = REPRESENTATIVES[usize::from(count.min(8 - count))][index];
+        let representative = if complement {
+            !representative
+        } else {
+            representative
+        };
+        let mut set = Neighbourhoods::EMPTY;
+        for symmetry in 0..8 {
+            set.insert(transform(representative, symmetry));
+        }
+        Some(set)
+    }
+
+    pub fn contains(&self, neighbourhood: u8) -> bool {
+        self.0[usize::from(neighbourhood / 64)] >> (neighbourhood % 64) & 1 != 0
+    }
+
+    pub fn insert(&mut self, neighbourhood: u8) {
+        self.0[usize::from(neighbourhood / 64)] |= 1 << (neighbourhood % 64);
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        let mut words = self.0;
+        words.iter_mut().zip(&other.0).for_each(|(a, b)| *a |= b);
+        Neighbourhoods(words)
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        let mut words = self.0;
+        words.iter_mut().zip(&other.0).for_each(|(a, b)| *a &= b);
+        Neighbourhoods(words)
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        let mut words = self.0;
+        words.iter_mut().zip(&other.0).for_each(|(a, b)| *a &= !b);
+        Neighbourhoods(words)
+    }
+
+    /// Whether membership depends only on the number of live cells.
+    pub fn is_totalistic(&self) -> bool {
+        (0..=8).all(|count| {
+            let all = Neighbourhoods::with_counts(1 << count);
+            let present = self.intersection(all);
+            present == all || present == Neighbourhoods::EMPTY
+        })
+    }
+
+    /// Writes the set in Hensel notation: each count with all its classes
+    /// as a bare digit, and the others with their letters or, when that is
+    /// shorter, with `-` and the letters left out.
+    pub fn write_hensel(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for count in 0..=8 {
+            let all = Neighbourhoods::with_counts(1 << count);
+            let present = self.intersection(all);
+            if present == Neighbourhoods::EMPTY {
+                continue;
+            }
+            write!(f, "{}", count)?;
+            if present == all {
+                continue;
+            }
+            let (included, excluded): (String, String) =
+                letters(count).0.chars().partition(|&letter| {
+                    let class = Neighbourhoods::with_letter(count, letter).unwrap();
+                    present.intersection(class) == class
+                });
+            if excluded.len() < included.len() {
+                write!(f, "-{}", excluded)?;
+            } else {
+                write!(f, "{}", included)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hensel's letters for `count` live neighbours, and whether each letter
+/// names the complement of the class it names for `8 - count`.
+fn letters(count: u8) -> (&'static str, bool) {
+    match count {
+        0..=4 => (LETTERS[usize::from(count)], false),
+        5..=8 => (LETTERS[usize::from(8 - count)], true),
+        _ => ("", false),
+    }
+}
+
+/// Applies one of the eight symmetries of the square to `neighbourhood`:
+/// `symmetry % 4` quarter turns, followed by a mirror image when
+/// `symmetry >= 4`.
+fn transform(neighbourhood: u8, symmetry: u8) -> u8 {
+    let mut result = 0;
+    for (bit, &(dx, dy)) in NEIGHBOURS.iter().enumerate() {
+        if neighbourhood >> bit & 1 == 0 {
+            continue;
+        }
+        let (mut x, mut y) = (dx, dy);
+        for _ in 0..symmetry % 4 {
+            (x, y) = (-y, x);
+        }
+        if symmetry >= 4 {
+            x = -x;
+        }
+        let target = NEIGHBOURS
+            .iter()
+            .position(|&offset| offset == (x, y))
+            .unwrap();
+        result |= 1 << target;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn len(set: Neighbourhoods) -> usize {
+        (0..=255).filter(|&n| set.contains(n)).count()
+    }
+
+    #[test]
+    fn letters_split_each_count_into_classes() {
+        for count in 0..=8 {
+            let all = Neighbourhoods::with_counts(1 << count);
+            let mut covered = Neighbourhoods::EMPTY;
+            for letter in letters(count).0.chars() {
+                let class = Neighbourhoods::with_letter(count, letter).unwrap();
+                assert_eq!(class.intersection(covered), Neighbourhoods::EMPTY);
+                assert_eq!(class.intersection(all), class);
+                covered = covered.union(class);
+            }
+            if count != 0 && count != 8 {
+                assert_eq!(covered, all, "count {}", count);
+            }
+        }
+    }
+
+    #[test]
+    fn classes_have_their_known_sizes() {
+        let sizes = |count| {
+            letters(count)
+                .0
+                .chars()
+                .map(|letter| len(Neighbourhoods::with_letter(count, letter).unwrap()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sizes(1), [4, 4]);
+        assert_eq!(sizes(2), [4, 4, 8, 2, 8, 2]);
+        assert_eq!(sizes(7), sizes(1));
+        assert_eq!(len(Neighbourhoods::with_counts(1 << 4)), 70);
+    }
+
+    #[test]
+    fn unknown_letters_name_nothing() {
+        assert_eq!(Neighbourhoods::with_letter(0, 'c'), None);
+        assert_eq!(Neighbourhoods::with_letter(1, 'a'), None);
+        assert_eq!(Neighbourhoods::with_letter(5, 'w'), None);
+        assert!(Neighbourhoods::with_letter(4, 'w').is_some());
+    }
+
+    #[test]
+    fn symmetries_keep_the_count() {
+        for neighbourhood in 0..=255u8 {
+            for symmetry in 0..8 {
+                let image = transform(neighbourhood, symmetry);
+                assert_eq!(image.count_ones(), neighbourhood.count_ones());
+            }
+            assert_eq!(transform(transform(neighbourhood, 4), 4), neighbourhood);
+        }
+    }
+
+    #[test]
+    fn totalistic_sets_are_recognised() {
+        assert!(Neighbourhoods::with_counts(0b1100).is_totalistic());
+        assert!(!Neighbourhoods::with_letter(2, 'a').unwrap().is_totalistic());
+    }
+}
@@ -0,0 +1,128 @@
+//! The tilings a rule can run on, all stored in the same square array of
+//! cells.
+
+/// The shape of the cells a rule runs on.
+///
+/// Hexagonal cells use Golly's layout: the square array is sheared so that
+/// each cell's six neighbours are the eight around it in the array less the
+/// north-east and south-west ones.
+///
+/// Triangular cells alternate between pointing up, where `x + y` is even,
+/// and down. Each has twelve neighbours: the triangles it shares an edge or
+/// a corner with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lattice {
+    Square,
+    Hexagonal,
+    Triangular,
+}
+
+/// Bits of a neighbourhood, in the order of [`NEIGHBOURS`](super::NEIGHBOURS),
+/// that are neighbours on the hexagonal lattice: all but north-east and
+/// south-west.
+pub(crate) const HEXAGONAL_MASK: u8 = !(1 << 2 | 1 << 5);
+
+/// Offsets of the twelve neighbours of a triangle pointing up.
+const UP_NEIGHBOURS: [(isize, isize); 12] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-2, 0),
+    (-1, 0),
+    (1, 0),
+    (2, 0),
+    (-2, 1),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (2, 1),
+];
+
+/// Offsets of the twelve neighbours of a triangle pointing down.
+const DOWN_NEIGHBOURS: [(isize, isize); 12] = [
+    (-2, -1),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (2, -1),
+    (-2, 0),
+    (-1, 0),
+    (1, 0),
+    (2, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+impl Lattice {
+    /// Most neighbours a cell has.
+    pub fn max_neighbours(self) -> u8 {
+        match self {
+            Lattice::Square => 8,
+            Lattice::Hexagonal => 6,
+            Lattice::Triangular => 12,
+        }
+    }
+
+    /// The letter that follows a rulestring to select this lattice.
+    pub(super) fn suffix(self) -> Option<char> {
+        match self {
+            Lattice::Square => None,
+            Lattice::Hexagonal => Some('H'),
+            Lattice::Triangular => Some('L'),
+        }
+    }
+}
+
+/// Whether the triangle at `(x, y)` points up.
+pub fn points_up(x: i64, y: i64) -> bool {
+    (x + y).rem_euclid(2) == 0
+}
+
+/// Offsets of the neighbours of the triangle at `(x, y)`.
+pub(crate) fn triangular_neighbours(x: i64, y: i64) -> &'static [(isize, isize); 12] {
+    if points_up(x, y) {
+        &UP_NEIGHBOURS
+    } else {
+        &DOWN_NEIGHBOURS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::NEIGHBOURS;
+
+    #[test]
+    fn hexagonal_neighbours_are_symmetric() {
+        let offsets: Vec<_> = NEIGHBOURS
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| HEXAGONAL_MASK >> bit & 1 != 0)
+            .map(|(_, &offset)| offset)
+            .collect();
+        assert_eq!(offsets.len(), 6);
+        for &(dx, dy) in &offsets {
+            assert!(offsets.contains(&(-dx, -dy)));
+        }
+        assert!(!offsets.contains(&(1, -1)) && !offsets.contains(&(-1, 1)));
+    }
+
+    #[test]
+    fn triangles_are_each_others_neighbours() {
+        for (x, y) in [(0, 0), (1, 0), (-3, 2), (4, -7)] {
+            for &(dx, dy) in triangular_neighbours(x, y) {
+                let (nx, ny) = (x + dx as i64, y + dy as i64);
+                assert!(triangular_neighbours(nx, ny).contains(&(-dx, -dy)));
+            }
+        }
+        assert!(points_up(0, 0) && !points_up(1, 0) && points_up(-1, 1));
+    }
+
+    #[test]
+    fn neighbour_counts() {
+        assert_eq!(Lattice::Square.max_neighbours(), 8);
+        assert_eq!(Lattice::Hexagonal.max_neighbours(), 6);
+        assert_eq!(Lattice::Triangular.max_neighbours(), 12);
+    }
+}
@@ -0,0 +1,369 @@
+//! Continuous rules in the style of Bert Chan's Lenia, written with his
+//! parameter names: `R=13;T=10;b=1;m=0.15;s=0.015;kn=1;gn=1`.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use super::ParseRuleError;
+
+/// Largest kernel radius a rule may have.
+const MAX_RADIUS: u32 = 100;
+
+/// Finest time resolution a rule may have.
+const MAX_STEPS: u32 = 1000;
+
+/// The shape of each ring of the kernel, as a function of how far across
+/// the ring a cell lies, from 0 on its inner edge to 1 on its outer one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KernelCore {
+    /// `(4r(1 - r))^4`, `kn=1`.
+    Polynomial,
+    /// `exp(4 - 1 / (r(1 - r)))`, `kn=2`.
+    Exponential,
+    /// 1 across the middle half of the ring, `kn=3`.
+    Step,
+    /// The step with a half-height inner quarter, `kn=4`, which with a
+    /// radius of 1 and a narrow growth function gives Life-like rules.
+    Staircase,
+}
+
+/// The growth function, mapping the weighted sum of the cells under the
+/// kernel to a rate between -1 and 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Growth {
+    /// `2(1 - (u - m)² / 9s²)^4 - 1`, clamped at -1, `gn=1`.
+    Polynomial,
+    /// `2exp(-(u - m)² / 2s²) - 1`, `gn=2`.
+    Exponential,
+    /// 1 within `s` of `m` and -1 elsewhere, `gn=3`.
+    Step,
+}
+
+/// A rule whose cells hold real values between 0 and 1.
+///
+/// Each generation, every cell takes the average of the cells within
+/// `radius` of it, weighted by a kernel made of concentric rings whose
+/// heights are the `peaks`, feeds it to the growth function centred on
+/// `mu` with width `sigma`, and moves `1 / steps` of the result towards 0
+/// or 1.
+#[derive(Clone, Debug)]
+pub struct Lenia {
+    radius: u32,
+    steps: u32,
+    peaks: Vec<f64>,
+    mu: f64,
+    sigma: f64,
+    core: KernelCore,
+    growth: Growth,
+}
+
+impl Lenia {
+    /// How far the kernel reaches, in cells.
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+
+    /// Fraction of the growth rate applied each generation.
+    pub fn time_step(&self) -> f64 {
+        1.0 / f64::from(self.steps)
+    }
+
+    /// Heights of the kernel's rings, from the innermost out.
+    pub fn peaks(&self) -> &[f64] {
+        &self.peaks
+    }
+
+    pub fn core(&self) -> KernelCore {
+        self.core
+    }
+
+    pub fn growth(&self) -> Growth {
+        self.growth
+    }
+
+    /// Weight the kernel gives a cell `distance` cells away, before it is
+    /// scaled to add up to 1.
+    pub fn kernel_weight(&self, distance: f64) -> f64 {
+        let r = distance / f64::from(self.radius);
+        if r <= 0.0 || r >= 1.0 {
+            return 0.0;
+        }
+        let position = r * self.peaks.len() as f64;
+        let peak = self.peaks[position as usize];
+        let r = position.fract();
+        let height = match self.core {
+            KernelCore::Polynomial => (4.0 * r * (1.0 - r)).powi(4),
+            KernelCore::Exponential if r == 0.0 => 0.0,
+            KernelCore::Exponential => (4.0 - 1.0 / (r * (1.0 - r))).exp(),
+            KernelCore::Step => f64::from(u8::from((0.25..=0.75).contains(&r))),
+            KernelCore::Staircase if r < 0.25 => 0.5,
+            KernelCore::Staircase => f64::from(u8::from(r <= 0.75)),
+        };
+        peak * height
+    }
+
+    /// Growth rate of a cell whose weighted neighbourhood sums to
+    /// `potential`.
+    pub fn growth_rate(&self, potential: f64) -> f64 {
+        let offset = potential - self.mu;
+        match self.growth {
+            Growth::Polynomial => {
+                let base = 1.0 - offset * offset / (9.0 * self.sigma * self.sigma);
+                2.0 * base.max(0.0).powi(4) - 1.0
+            }
+            Growth::Exponential => {
+                2.0 * (-offset * offset / (2.0 * self.sigma * self.sigma)).exp() - 1.0
+            }
+            Growth::Step if offset.abs() <= self.sigma => 1.0,
+            Growth::Step => -1.0,
+        }
+    }
+
+    /// Everything that identifies the rule, with the real parameters by
+    /// their bits so that rules can be compared and hashed.
+    fn key(&self) -> (u32, u32, Vec<u64>, u64, u64, KernelCore, Growth) {
+        (
+            self.radius,
+            self.steps,
+            self.peaks.iter().map(|peak| peak.to_bits()).collect(),
+            self.mu.to_bits(),
+            self.sigma.to_bits(),
+            self.core,
+            self.growth,
+        )
+    }
+}
+
+impl PartialEq for Lenia {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Lenia {}
+
+impl Hash for Lenia {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl fmt::Display for Lenia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "R={};T={};b=", self.radius, self.steps)?;
+        for (i, &peak) in self.peaks.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write_fraction(f, peak)?;
+        }
+        let core = match self.core {
+            KernelCore::Polynomial => 1,
+            KernelCore::Exponential => 2,
+            KernelCore::Step => 3,
+            KernelCore::Staircase => 4,
+        };
+        let growth = match self.growth {
+            Growth::Polynomial => 1,
+            Growth::Exponential => 2,
+            Growth::Step => 3,
+        };
+        write!(
+            f,
+            ";m={};s={};kn={};gn={}",
+            self.mu, self.sigma, core, growth
+        )
+    }
+}
+
+/// Writes `value` as a fraction such as `1/3` when one with a small
+/// denominator gives it exactly, and as a decimal otherwise.
+fn write_fraction(f: &mut fmt::Formatter, value: f64) -> fmt::Result {
+    for denominator in 1..=12 {
+        let numerator = (value * f64::from(denominator)).round();
+        if numerator / f64::from(denominator) == value {
+            return match denominator {
+                1 => write!(f, "{}", numerator),
+                _ => write!(f, "{}/{}", numerator, denominator),
+            };
+        }
+    }
+    write!(f, "{}", value)
+}
+
+/// Parses the `name=value` parameters of a continuous rule, separated by
+/// semicolons and in any order. `R`, `m` and `s` are required; `T`
+/// defaults to 10, `b` to a single ring of height 1, and `kn` and `gn` to
+/// the polynomial kernel and growth function.
+pub(super) fn parse(s: &str) -> Result<Lenia, ParseRuleError> {
+    const NAMES: [&str; 7] = ["r", "t", "b", "m", "s", "kn", "gn"];
+    let mut values: [Option<&str>; 7] = [None; 7];
+    let mut offset = 0;
+    for part in s.split(';') {
+        let (name, value) = part.split_once('=').unwrap_or((part, ""));
+        let name = name.trim().to_ascii_lowercase();
+        let index = NAMES.iter().position(|&known| known == name).ok_or(
+            ParseRuleError::UnexpectedChar {
+                position: offset,
+                found: part.chars().next().unwrap_or(';'),
+            },
+        )?;
+        if values[index].replace(value.trim()).is_some() {
+            return Err(ParseRuleError::DuplicateSection(
+                part.trim_start().chars().next().unwrap(),
+            ));
+        }
+        offset += part.len() + 1;
+    }
+    let [radius, steps, peaks, mu, sigma, core, growth] = values;
+
+    let invalid = |name: char, value: &str| ParseRuleError::InvalidParameter {
+        name,
+        value: value.to_string(),
+    };
+    let integer = |name: char, value: &str, max: u32| match value.parse() {
+        Ok(value) if (1..=max).contains(&value) => Ok(value),
+        _ => Err(invalid(name, value)),
+    };
+    let real = |name: char, value: Option<&str>, min: f64| {
+        let value = value.ok_or(ParseRuleError::MissingSection(name))?;
+        match value.parse::<f64>() {
+            Ok(real) if real >= min && real <= 1.0 => Ok(real),
+            _ => Err(invalid(name, value)),
+        }
+    };
+    let radius = integer(
+        'R',
+        radius.ok_or(ParseRuleError::MissingSection('R'))?,
+        MAX_RADIUS,
+    )?;
+    let steps = integer('T', steps.unwrap_or("10"), MAX_STEPS)?;
+    let peaks = match peaks {
+        Some(peaks) => peaks
+            .split(',')
+            .map(|peak| match parse_fraction(peak.trim()) {
+                Some(height) if (0.0..=1.0).contains(&height) => Ok(height),
+                _ => Err(invalid('b', peak)),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![1.0],
+    };
+    let mu = real('m', mu, 0.0)?;
+    let sigma = real('s', sigma, f64::MIN_POSITIVE)?;
+    let core = match core.unwrap_or("1") {
+        "1" => KernelCore::Polynomial,
+        "2" => KernelCore::Exponential,
+        "3" => KernelCore::Step,
+        "4" => KernelCore::Staircase,
+        value => return Err(invalid('k', value)),
+    };
+    let growth = match growth.unwrap_or("1") {
+        "1" => Growth::Polynomial,
+        "2" => Growth::Exponential,
+        "3" => Growth::Step,
+        value => return Err(invalid('g', value)),
+    };
+    Ok(Lenia {
+        radius,
+        steps,
+        peaks,
+        mu,
+        sigma,
+        core,
+        growth,
+    })
+}
+
+/// Parses a decimal such as `0.5` or a fraction such as `1/2`.
+fn parse_fraction(s: &str) -> Option<f64> {
+    match s.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator: f64 = denominator.parse().ok()?;
+            Some(numerator.parse::<f64>().ok()? / denominator).filter(|value| value.is_finite())
+        }
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    const ORBIUM: &str = "R=13;T=10;b=1;m=0.15;s=0.015;kn=1;gn=1";
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            ORBIUM,
+            "R=10;T=5;b=1/2,1,2/3;m=0.26;s=0.036;kn=2;gn=2",
+            "R=1;T=1;b=1;m=0.35;s=0.07;kn=4;gn=3",
+        ] {
+            assert_eq!(s.parse::<Rule>().unwrap().to_string(), s);
+        }
+        assert_eq!(
+            parse("s=0.03; m=0.2; R=5").unwrap().to_string(),
+            "R=5;T=10;b=1;m=0.2;s=0.03;kn=1;gn=1"
+        );
+        assert_eq!(
+            parse("R=5;m=0.2;s=0.03;b=0.5,0.25").unwrap().peaks(),
+            [0.5, 0.25]
+        );
+    }
+
+    #[test]
+    fn kernels_peak_in_the_middle_of_each_ring() {
+        let lenia = parse(ORBIUM).unwrap();
+        assert_eq!(lenia.kernel_weight(0.0), 0.0);
+        assert_eq!(lenia.kernel_weight(6.5), 1.0);
+        assert!(lenia.kernel_weight(3.0) < lenia.kernel_weight(5.0));
+        assert_eq!(lenia.kernel_weight(13.0), 0.0);
+
+        let rings = parse("R=4;m=0.2;s=0.03;b=1,1/2;kn=3").unwrap();
+        assert_eq!(rings.kernel_weight(1.0), 1.0);
+        assert_eq!(rings.kernel_weight(3.0), 0.5);
+        assert_eq!(rings.kernel_weight(2.1), 0.0);
+    }
+
+    #[test]
+    fn growth_is_highest_at_mu() {
+        for gn in 1..=3 {
+            let lenia = parse(&format!("R=5;m=0.2;s=0.03;gn={}", gn)).unwrap();
+            assert_eq!(lenia.growth_rate(0.2), 1.0);
+            assert_eq!(lenia.growth_rate(0.9), -1.0);
+            assert!(lenia.growth_rate(0.21) >= lenia.growth_rate(0.23));
+            assert!(lenia.growth_rate(0.21) > 0.0);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        let invalid = |name, value: &str| {
+            Err(ParseRuleError::InvalidParameter {
+                name,
+                value: value.to_string(),
+            })
+        };
+        assert_eq!(parse("R=0;m=0.1;s=0.1"), invalid('R', "0"));
+        assert_eq!(parse("R=5;T=0;m=0.1;s=0.1"), invalid('T', "0"));
+        assert_eq!(parse("R=5;m=0.1;s=0"), invalid('s', "0"));
+        assert_eq!(parse("R=5;m=1.5;s=0.1"), invalid('m', "1.5"));
+        assert_eq!(parse("R=5;m=0.1;s=0.1;b=2"), invalid('b', "2"));
+        assert_eq!(parse("R=5;m=0.1;s=0.1;kn=5"), invalid('k', "5"));
+        assert_eq!(parse("R=5;m=0.1;s=0.1;gn=0"), invalid('g', "0"));
+        assert_eq!(parse("R=5;s=0.1"), Err(ParseRuleError::MissingSection('m')));
+        assert_eq!(
+            parse("R=5;m=0.1;s=0.1;R=6"),
+            Err(ParseRuleError::DuplicateSection('R'))
+        );
+        assert_eq!(
+            parse("R=5;x=1"),
+            Err(ParseRuleError::UnexpectedChar {
+                position: 4,
+                found: 'x'
+            })
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Larger than Life rules: totalistic rules counting the live cells within
+//! some range of a cell, written as `R5,C0,M1,S34..58,B34..45,NM`.
+
+use std::fmt;
+
+use super::{parse_states, ParseRuleError};
+
+/// Largest range a Larger than Life rule may have.
+const MAX_RANGE: u32 = 500;
+
+/// Which cells within range of a cell make up its neighbourhood.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    /// The square of side `2 * range + 1`.
+    Moore,
+    /// The diamond of cells at most `range` steps away along the axes.
+    VonNeumann,
+    /// The cells whose centres lie within `range + 1/2` of the cell's.
+    Circular,
+}
+
+impl Shape {
+    fn letter(self) -> char {
+        match self {
+            Shape::Moore => 'M',
+            Shape::VonNeumann => 'N',
+            Shape::Circular => 'C',
+        }
+    }
+}
+
+/// The neighbourhood and counts of a Larger than Life rule. Only cells in
+/// state 1 are counted, the cell itself included when the rule says so.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LargerThanLife {
+    range: u32,
+    shape: Shape,
+    middle: bool,
+    /// Inclusive bounds on the count that lets a live cell survive.
+    survival: (u32, u32),
+    /// Inclusive bounds on the count that causes a dead cell to be born.
+    birth: (u32, u32),
+}
+
+impl LargerThanLife {
+    /// How far the neighbourhood reaches from the cell along each axis.
+    pub fn range(&self) -> u32 {
+        self.range
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    /// Whether the cell counts towards its own neighbourhood.
+    pub fn includes_middle(&self) -> bool {
+        self.middle
+    }
+
+    /// Whether a dead cell with `count` live cells around it is born.
+    pub fn is_birth(&self, count: u32) -> bool {
+        (self.birth.0..=self.birth.1).contains(&count)
+    }
+
+    /// Whether a live cell with `count` live cells around it survives.
+    pub fn is_survival(&self, count: u32) -> bool {
+        (self.survival.0..=self.survival.1).contains(&count)
+    }
+
+    /// How far the neighbourhood reaches left and right in the row `dy`
+    /// rows above or below the cell, or `None` if it does not reach that
+    /// row at all.
+    pub fn row_reach(&self, dy: i64) -> Option<u32> {
+        let (range, dy) = (i64::from(self.range), dy.abs());
+        if dy > range {
+            return None;
+        }
+        let reach = match self.shape {
+            Shape::Moore => range,
+            Shape::VonNeumann => range - dy,
+            Shape::Circular => {
+                // Largest dx with dx² + dy² <= (range + 1/2)², in integers.
+                let limit = range * range + range - dy * dy;
+                let mut dx = (limit as f64).sqrt() as i64;
+                while dx * dx > limit {
+                    dx -= 1;
+                }
+                while (dx + 1) * (dx + 1) <= limit {
+                    dx += 1;
+                }
+                dx
+            }
+        };
+        Some(reach as u32)
+    }
+
+    /// Writes the rule in canonical form, with the number of states in its
+    /// `C` parameter.
+    pub(super) fn write(&self, states: u16, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.range,
+            if states == 2 { 0 } else { states },
+            u8::from(self.middle),
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            self.shape.letter()
+        )
+    }
+}
+
+/// Parses the comma-separated parameters of a Larger than Life rule, in any
+/// order, returning the rule and its number of states. `R`, `S` and `B` are
+/// required; `C` defaults to 2 states, `M` to 0 and `N` to Moore.
+pub(super) fn parse(s: &str) -> Result<(LargerThanLife, u16), ParseRuleError> {
+    const NAMES: [char; 6] = ['R', 'C', 'M', 'S', 'B', 'N'];
+    let mut values: [Option<&str>; 6] = [None; 6];
+    let mut offset = 0;
+    for part in s.split(',') {
+        let mut chars = part.chars();
+        let name = match chars.next() {
+            Some(c) => c.to_ascii_uppercase(),
+            None => {
+                return Err(ParseRuleError::UnexpectedChar {
+                    position: offset,
+                    found: ',',
+                })
+            }
+        };
+        let index = NAMES.iter().position(|&known| known == name).ok_or(
+            ParseRuleError::UnexpectedChar {
+                position: offset,
+                found: part.chars().next().unwrap(),
+            },
+        )?;
+        if values[index].replace(chars.as_str()).is_some() {
+            return Err(ParseRuleError::DuplicateSection(name));
+        }
+        offset += part.len() + 1;
+    }
+    let [range, states, middle, survival, birth, shape] = values;
+
+    let invalid = |name: char, value: &str| ParseRuleError::InvalidParameter {
+        name,
+        value: value.to_string(),
+    };
+    let range = range.ok_or(ParseRuleError::MissingSection('R'))?;
+    let range = match range.parse() {
+        Ok(value) if (1..=MAX_RANGE).contains(&value) => value,
+        _ => return Err(invalid('R', range)),
+    };
+    let states = match states {
+        None | Some("0") | Some("1") => 2,
+        Some(states) => parse_states(states)?,
+    };
+    let middle = match middle {
+        None | Some("0") => false,
+        Some("1") => true,
+        Some(value) => return Err(invalid('M', value)),
+    };
+    let bounds = |name: char, value: Option<&str>| {
+        let value = value.ok_or(ParseRuleError::MissingSection(name))?;
+        let (low, high) = value.split_once("..").unwrap_or((value, value));
+        match (low.parse(), high.parse()) {
+            (Ok(low), Ok(high)) if low <= high => Ok((low, high)),
+            _ => Err(invalid(name, value)),
+        }
+    };
+    let survival = bounds('S', survival)?;
+    let birth = bounds('B', birth)?;
+    let shape = match shape.map(str::to_ascii_uppercase).as_deref() {
+        None | Some("M") => Shape::Moore,
+        Some("N") => Shape::VonNeumann,
+        Some("C") => Shape::Circular,
+        Some(_) => return Err(invalid('N', shape.unwrap())),
+    };
+    Ok((
+        LargerThanLife {
+            range,
+            shape,
+            middle,
+            survival,
+            birth,
+        },
+        states,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    const BOSCO: &str = "R5,C0,M1,S34..58,B34..45,NM";
+
+    #[test]
+    fn parameters_come_in_any_order() {
+        assert_eq!(parse("S34..58,b34..45,R5,c0,NM,M1"), parse(BOSCO));
+        let (ltl, states) = parse(BOSCO).unwrap();
+        assert_eq!((ltl.range(), ltl.shape(), states), (5, Shape::Moore, 2));
+        assert!(ltl.includes_middle());
+        assert!(ltl.is_birth(34) && ltl.is_birth(45) && !ltl.is_birth(46));
+        assert!(ltl.is_survival(58) && !ltl.is_survival(33));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            BOSCO,
+            "R7,C3,M0,S10..20,B12..14,NC",
+            "R2,C0,M0,S1..4,B3..3,NN",
+        ] {
+            assert_eq!(s.parse::<Rule>().unwrap().to_string(), s);
+        }
+        let defaults: Rule = "R2,S3..5,B4".parse().unwrap();
+        assert_eq!(defaults.to_string(), "R2,C0,M0,S3..5,B4..4,NM");
+    }
+
+    #[test]
+    fn shapes_reach_their_rows() {
+        let reach = |s: &str| {
+            let (ltl, _) = parse(s).unwrap();
+            (-3..=3).map(|dy| ltl.row_reach(dy)).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            reach("R2,S1,B1,NM"),
+            [None, Some(2), Some(2), Some(2), Some(2), Some(2), None]
+        );
+        assert_eq!(
+            reach("R2,S1,B1,NN"),
+            [None, Some(0), Some(1), Some(2), Some(1), Some(0), None]
+        );
+        assert_eq!(
+            reach("R2,S1,B1,NC"),
+            [None, Some(1), Some(2), Some(2), Some(2), Some(1), None]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        let invalid = |name, value: &str| {
+            Err(ParseRuleError::InvalidParameter {
+                name,
+                value: value.to_string(),
+            })
+        };
+        assert_eq!(parse("R0,S1,B1"), invalid('R', "0"));
+        assert_eq!(parse("R501,S1,B1"), invalid('R', "501"));
+        assert_eq!(parse("R5,S9..3,B1"), invalid('S', "9..3"));
+        assert_eq!(parse("R5,S1,B1,M2"), invalid('M', "2"));
+        assert_eq!(parse("R5,S1,B1,NX"), invalid('N', "X"));
+        assert_eq!(parse("R5,B1"), Err(ParseRuleError::MissingSection('S')));
+        assert_eq!(
+            parse("R5,R6,S1,B1"),
+            Err(ParseRuleError::DuplicateSection('R'))
+        );
+        assert_eq!(
+            parse("R5,,S1,B1"),
+            Err(ParseRuleError::UnexpectedChar {
+                position: 3,
+                found: ','
+            })
+        );
+    }
+}
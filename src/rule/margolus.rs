@@ -0,0 +1,131 @@
+//! Block rules on the Margolus neighbourhood, written as in MCell:
+//! `M0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,15`.
+
+use std::fmt;
+
+use super::ParseRuleError;
+
+/// Number of ways to fill a 2x2 block with two states.
+const BLOCKS: usize = 16;
+
+/// A rule that splits the plane into 2x2 blocks and replaces each block
+/// as a whole, the blocks shifting one cell down and right every other
+/// generation.
+///
+/// A block is numbered by adding 1 for a live top-left cell, 2 for the
+/// top-right, 4 for the bottom-left and 8 for the bottom-right, and the
+/// rule lists the block each of the 16 becomes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Margolus {
+    outputs: [u8; BLOCKS],
+}
+
+impl Margolus {
+    /// The block that `block` becomes.
+    pub fn next_block(&self, block: u8) -> u8 {
+        self.outputs[usize::from(block & 15)]
+    }
+
+    /// Whether no two blocks become the same one, so that every
+    /// generation can be worked out from the next.
+    pub fn is_reversible(&self) -> bool {
+        self.inverse().is_some()
+    }
+
+    /// The rule that undoes this one, if it is reversible.
+    pub fn inverse(&self) -> Option<Margolus> {
+        let mut outputs = [None; BLOCKS];
+        for (block, &output) in self.outputs.iter().enumerate() {
+            let slot = &mut outputs[usize::from(output)];
+            if slot.is_some() {
+                return None;
+            }
+            *slot = Some(block as u8);
+        }
+        let mut inverse = [0; BLOCKS];
+        for (slot, output) in inverse.iter_mut().zip(&outputs) {
+            *slot = (*output)?;
+        }
+        Some(Margolus { outputs: inverse })
+    }
+}
+
+impl fmt::Display for Margolus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "M")?;
+        for (i, output) in self.outputs.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `M` followed by the 16 blocks the blocks become, separated by
+/// commas.
+pub(super) fn parse(s: &str) -> Result<Margolus, ParseRuleError> {
+    let invalid = |value: &str| ParseRuleError::InvalidParameter {
+        name: 'M',
+        value: value.to_string(),
+    };
+    let fields: Vec<&str> = s[1..].split(',').collect();
+    if fields.len() != BLOCKS {
+        return Err(invalid(&s[1..]));
+    }
+    let mut outputs = [0; BLOCKS];
+    for (output, field) in outputs.iter_mut().zip(fields) {
+        *output = match field.trim().parse() {
+            Ok(block) if usize::from(block) < BLOCKS => block,
+            _ => return Err(invalid(field)),
+        };
+    }
+    Ok(Margolus { outputs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    const BBM: &str = "M0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,15";
+
+    #[test]
+    fn display_round_trips() {
+        assert_eq!(BBM.parse::<Rule>().unwrap().to_string(), BBM);
+        assert_eq!(
+            parse("M 0, 8,4,3,2,5,9,7,1,6,10,11,12,13,14,15").unwrap(),
+            parse(BBM).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_tables() {
+        let invalid = |value: &str| {
+            Err(ParseRuleError::InvalidParameter {
+                name: 'M',
+                value: value.to_string(),
+            })
+        };
+        assert_eq!(parse("M0,1,2"), invalid("0,1,2"));
+        assert_eq!(
+            parse("M0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,16"),
+            invalid("16")
+        );
+        assert_eq!(parse("M0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,x"), invalid("x"));
+    }
+
+    #[test]
+    fn inverses_undo_the_rule() {
+        let bbm = parse(BBM).unwrap();
+        let inverse = bbm.inverse().unwrap();
+        for block in 0..16 {
+            assert_eq!(inverse.next_block(bbm.next_block(block)), block);
+        }
+        assert!(bbm.is_reversible());
+        let merging = parse("M0,0,2,3,4,5,6,7,8,9,10,11,12,13,14,15").unwrap();
+        assert!(!merging.is_reversible());
+        assert_eq!(merging.inverse(), None);
+    }
+}
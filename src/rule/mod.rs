@@ -0,0 +1,1097 @@
+//! Life-like rules written as B/S rulestrings, including isotropic
+//! non-totalistic rules in Hensel notation, the multi-state Generations
+//! family, hexagonal and triangular rules, Larger than Life, rule tables
+//! loaded from Golly's `.rule` files, one-dimensional rules, block rules
+//! on the Margolus neighbourhood, 3D rules and continuous rules in the
+//! style of Lenia.
+
+pub mod builtin;
+mod cubic;
+mod hensel;
+mod lattice;
+mod lenia;
+mod ltl;
+mod margolus;
+mod table;
+mod wolfram;
+
+use std::{error::Error, fmt, str::FromStr, sync::Arc};
+
+use crate::topology::Topology;
+
+use self::hensel::Neighbourhoods;
+
+pub use self::cubic::{Cubic, CubicNeighbourhood};
+pub use self::lattice::{points_up, Lattice};
+pub use self::lenia::{Growth, KernelCore, Lenia};
+pub use self::ltl::{LargerThanLife, Shape};
+pub use self::margolus::Margolus;
+pub use self::table::{RuleTable, TableNeighbourhood};
+pub use self::wolfram::Wolfram;
+
+pub(crate) use self::lattice::triangular_neighbours;
+pub(crate) use self::table::Transition;
+
+/// Offsets of the eight cells surrounding a cell. Bit `i` of a
+/// neighbourhood, as taken by [`Rule::next_state`], is the neighbour at
+/// `NEIGHBOURS[i]`.
+pub(crate) const NEIGHBOURS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// The states of the neighbours in `neighbourhood`: 1 for those whose bit
+/// is set, and 0 for the others.
+fn neighbour_states(neighbourhood: u8) -> [u8; 8] {
+    let mut neighbours = [0; 8];
+    for (bit, neighbour) in neighbours.iter_mut().enumerate() {
+        *neighbour = neighbourhood >> bit & 1;
+    }
+    neighbours
+}
+
+/// Which of `neighbours`, given in the order of [`NEIGHBOURS`], are in
+/// state 1, as a neighbourhood.
+fn alive_neighbourhood(neighbours: [u8; 8]) -> u8 {
+    neighbours
+        .iter()
+        .enumerate()
+        .fold(0, |neighbourhood, (bit, &state)| {
+            neighbourhood | u8::from(state == 1) << bit
+        })
+}
+
+/// A Life-like rule: which neighbour counts cause a dead cell to be born and
+/// which let a live cell survive.
+///
+/// Parses from `B36/S23`, `b36s23`, `S23/B36` and the older survival-first
+/// `23/36` form, and always prints as `B36/S23`. A bounded-grid suffix such
+/// as `B3/S23:T64,64` selects the [`Topology`] the rule runs on.
+///
+/// In the `B`/`S` form a count may be followed by Hensel's letters to pick
+/// out arrangements of that many neighbours, as in `B2-a/S12` or
+/// `B3/S2ae3aijr`; such rules are isotropic but not totalistic. They print
+/// in a canonical form, listing whichever of the letters in or out is
+/// shorter.
+///
+/// Generations rules such as `B2/S/C3` or `345/2/4` add dying states: a
+/// live cell that does not survive passes through states 2, 3, ... up to
+/// `states - 1` before it is dead again, and only cells in state 1 count
+/// as live neighbours.
+///
+/// A trailing `H` runs the rule on hexagonal cells, as in `B2/S34H`, and a
+/// trailing `L` on triangular ones, as in `B4/S345L`; see [`Lattice`].
+/// Triangular cells have up to 12 neighbours, and the counts 10 to 12 are
+/// written `a` to `c`.
+///
+/// Larger than Life rules such as `R5,C0,M1,S34..58,B34..45,NM` count live
+/// cells over a wider neighbourhood; see [`LargerThanLife`].
+///
+/// One-dimensional rules such as `W30`, or `W777K3` for a totalistic rule
+/// with three colours, update a line of cells; see [`Wolfram`]. Only the
+/// [`SpaceTime`](crate::SpaceTime) engine runs them.
+///
+/// Block rules such as `M0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,15` replace
+/// 2x2 blocks of cells at a time; see [`Margolus`]. Only the
+/// [`Blocks`](crate::Blocks) engine runs them.
+///
+/// 3D rules such as `B5/S45/3D`, or `B1/S1/3D6` counting only the six face
+/// neighbours, run on a cubic lattice; see [`Cubic`]. Only the
+/// [`Volume`](crate::Volume) engine runs them.
+///
+/// Continuous rules such as `R=13;T=10;b=1;m=0.15;s=0.015;kn=1;gn=1` give
+/// cells real values between 0 and 1, held as the 256 states from 0 for
+/// 0.0 to 255 for 1.0; see [`Lenia`]. Only the
+/// [`Continuous`](crate::Continuous) engine runs them.
+///
+/// Rule tables have no rulestring; they are read from files and print as
+/// their name. See [`RuleTable`]. The classic automata in [`builtin`] parse
+/// from their names, such as `WireWorld`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rule {
+    family: Family,
+    /// Number of cell states, 2 for rules without dying states.
+    states: u16,
+    /// `None` for the unbounded plane.
+    topology: Option<Topology>,
+}
+
+/// How a rule looks at the cells around a cell.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Family {
+    /// The eight surrounding cells, with birth and survival decided by how
+    /// the live ones are arranged.
+    Moore {
+        birth: Neighbourhoods,
+        survival: Neighbourhoods,
+    },
+    /// Hexagonal or triangular neighbours, with birth and survival decided
+    /// by how many are alive; bit `n` of a mask stands for `n` neighbours.
+    Counts {
+        lattice: Lattice,
+        birth: u16,
+        survival: u16,
+    },
+    LargerThanLife(LargerThanLife),
+    Table(Arc<RuleTable>),
+    /// A line of cells, each looking at the one to its west and east.
+    Wolfram(Wolfram),
+    /// 2x2 blocks of cells, each replaced as a whole.
+    Margolus(Margolus),
+    /// Cubes with birth and survival decided by how many of their 26 or 6
+    /// neighbours are alive.
+    Cubic(Cubic),
+    /// Real-valued cells growing or shrinking with a weighted average of
+    /// the cells around them.
+    Lenia(Lenia),
+}
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub const CONWAY: Rule = Rule {
+        family: Family::Moore {
+            birth: Neighbourhoods::with_counts(1 << 3),
+            survival: Neighbourhoods::with_counts(1 << 2 | 1 << 3),
+        },
+        states: 2,
+        topology: None,
+    };
+
+    /// Builds a rule from the neighbour counts that cause birth and survival.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a count is greater than 8.
+    pub fn new(birth: &[u8], survival: &[u8]) -> Self {
+        Rule {
+            family: Family::Moore {
+                birth: Neighbourhoods::with_counts(counts_to_mask(birth)),
+                survival: Neighbourhoods::with_counts(counts_to_mask(survival)),
+            },
+            states: 2,
+            topology: None,
+        }
+    }
+
+    /// Runs `table`, with as many states as it has.
+    pub fn from_table(table: RuleTable) -> Self {
+        Rule {
+            states: table.states(),
+            family: Family::Table(Arc::new(table)),
+            topology: None,
+        }
+    }
+
+    /// Number of cell states: 2 for Life-like rules, more for Generations
+    /// rules and rule tables.
+    pub fn states(&self) -> u16 {
+        self.states
+    }
+
+    /// The same rule with `states` cell states, where states beyond 1 are
+    /// dying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `states` is not between 2 and 256.
+    pub fn with_states(self, states: u16) -> Self {
+        assert!(
+            (2..=MAX_STATES).contains(&states),
+            "a rule has between 2 and {} states, got {}",
+            MAX_STATES,
+            states
+        );
+        Rule { states, ..self }
+    }
+
+    /// The bounded grid the rule runs on, `None` for the unbounded plane.
+    pub fn topology(&self) -> Option<Topology> {
+        self.topology
+    }
+
+    /// The same rule on another grid.
+    pub fn with_topology(self, topology: Option<Topology>) -> Self {
+        Rule { topology, ..self }
+    }
+
+    /// The neighbourhood and counts of a Larger than Life rule, `None` for
+    /// rules that only look at the eight surrounding cells.
+    pub fn larger_than_life(&self) -> Option<&LargerThanLife> {
+        match &self.family {
+            Family::LargerThanLife(ltl) => Some(ltl),
+            _ => None,
+        }
+    }
+
+    /// The neighbourhood and counts of a 3D rule.
+    pub fn cubic(&self) -> Option<&Cubic> {
+        match &self.family {
+            Family::Cubic(cubic) => Some(cubic),
+            _ => None,
+        }
+    }
+
+    /// The kernel and growth function of a continuous rule.
+    pub fn lenia(&self) -> Option<&Lenia> {
+        match &self.family {
+            Family::Lenia(lenia) => Some(lenia),
+            _ => None,
+        }
+    }
+
+    /// The block rule, for rules on the Margolus neighbourhood.
+    pub fn margolus(&self) -> Option<&Margolus> {
+        match &self.family {
+            Family::Margolus(margolus) => Some(margolus),
+            _ => None,
+        }
+    }
+
+    /// The one-dimensional rule, for rules such as `W30`.
+    pub fn wolfram(&self) -> Option<&Wolfram> {
+        match &self.family {
+            Family::Wolfram(wolfram) => Some(wolfram),
+            _ => None,
+        }
+    }
+
+    /// The rule table, for rules loaded from a file.
+    pub fn table(&self) -> Option<&RuleTable> {
+        match &self.family {
+            Family::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// The colour the rule's file gives `state`, if any.
+    pub fn color(&self, state: u8) -> Option<(u8, u8, u8)> {
+        self.table().and_then(|table| table.color(state))
+    }
+
+    /// What `state` stands for: the name the rule's file gives it, the
+    /// value it holds under a continuous rule, or whether it is dead, alive
+    /// or dying.
+    pub fn state_name(&self, state: u8) -> String {
+        if self.lenia().is_some() {
+            return format!("{:.2}", f64::from(state) / 255.0);
+        }
+        if let Some(name) = self.table().and_then(|table| table.state_name(state)) {
+            return name.to_string();
+        }
+        match state {
+            0 => "dead".to_string(),
+            1 => "alive".to_string(),
+            _ if self.table().is_some() || self.wolfram().is_some() => {
+                format!("state {}", state)
+            }
+            _ => format!("dying {}", state - 1),
+        }
+    }
+
+    /// The shape of the cells the rule runs on.
+    pub fn lattice(&self) -> Lattice {
+        match self.family {
+            Family::Counts { lattice, .. } => lattice,
+            Family::Table(ref table) if table.neighbourhood() == TableNeighbourhood::Hexagonal => {
+                Lattice::Hexagonal
+            }
+            _ => Lattice::Square,
+        }
+    }
+
+    /// How far from a cell, in cells of the array, the rule looks: 1
+    /// except for triangular, Larger than Life and continuous rules.
+    pub fn range(&self) -> u32 {
+        match &self.family {
+            Family::Counts {
+                lattice: Lattice::Triangular,
+                ..
+            } => 2,
+            Family::LargerThanLife(ltl) => ltl.range(),
+            Family::Lenia(lenia) => lenia.radius(),
+            _ => 1,
+        }
+    }
+
+    /// Whether the rule only looks at how many neighbours are alive, not
+    /// at where they are.
+    pub fn is_totalistic(&self) -> bool {
+        match &self.family {
+            Family::Moore { birth, survival } => birth.is_totalistic() && survival.is_totalistic(),
+            Family::Counts { .. } | Family::LargerThanLife(_) | Family::Cubic(_) => true,
+            Family::Table(_) | Family::Margolus(_) | Family::Lenia(_) => false,
+            Family::Wolfram(wolfram) => wolfram.is_totalistic(),
+        }
+    }
+
+    /// Whether a dead cell with `neighbours` live neighbours is born,
+    /// however they are arranged. For rule tables the other neighbours are
+    /// dead, and being born means leaving state 0 for any state, as it
+    /// does for one-dimensional rules, whose neighbours are the cells to
+    /// either side. Block rules see the cell as the top-left of its block
+    /// and the other three cells of the block as its neighbours. Cells of
+    /// continuous rules are never born from nothing, and no cell is born
+    /// with more neighbours than the rule looks at.
+    pub fn is_birth(&self, neighbours: u8) -> bool {
+        match &self.family {
+            Family::Lenia(_) => false,
+            Family::Moore { birth, .. } => {
+                neighbours <= 8 && {
+                    let all = Neighbourhoods::with_counts(1 << neighbours);
+                    birth.intersection(all) == all
+                }
+            }
+            Family::Counts { birth, .. } => neighbours < 16 && birth & 1 << neighbours != 0,
+            Family::LargerThanLife(ltl) => ltl.is_birth(u32::from(neighbours)),
+            Family::Cubic(cubic) => cubic.is_birth(u32::from(neighbours)),
+            Family::Table(table) => {
+                neighbours <= 8
+                    && arrangements(neighbours)
+                        .all(|neighbours| table.next_state(0, neighbours) != 0)
+            }
+            Family::Wolfram(wolfram) => {
+                neighbours <= 2
+                    && sides(neighbours)
+                        .all(|(left, right)| wolfram.next_state(left, 0, right) != 0)
+            }
+            Family::Margolus(margolus) => {
+                neighbours <= 3
+                    && block_mates(neighbours).all(|block| margolus.next_block(block) & 1 != 0)
+            }
+        }
+    }
+
+    /// Whether a live cell with `neighbours` live neighbours survives,
+    /// however they are arranged. For rule tables the other neighbours are
+    /// dead. Continuous rules have no fixed survival counts, and no cell
+    /// survives with more neighbours than the rule looks at.
+    pub fn is_survival(&self, neighbours: u8) -> bool {
+        match &self.family {
+            Family::Lenia(_) => false,
+            Family::Moore { survival, .. } => {
+                neighbours <= 8 && {
+                    let all = Neighbourhoods::with_counts(1 << neighbours);
+                    survival.intersection(all) == all
+                }
+            }
+            Family::Counts { survival, .. } => neighbours < 16 && survival & 1 << neighbours != 0,
+            Family::LargerThanLife(ltl) => ltl.is_survival(u32::from(neighbours)),
+            Family::Cubic(cubic) => cubic.is_survival(u32::from(neighbours)),
+            Family::Table(table) => {
+                neighbours <= 8
+                    && arrangements(neighbours)
+                        .all(|neighbours| table.next_state(1, neighbours) == 1)
+            }
+            Family::Wolfram(wolfram) => {
+                neighbours <= 2
+                    && sides(neighbours)
+                        .all(|(left, right)| wolfram.next_state(left, 1, right) == 1)
+            }
+            Family::Margolus(margolus) => {
+                neighbours <= 3
+                    && block_mates(neighbours).all(|block| margolus.next_block(block | 1) & 1 != 0)
+            }
+        }
+    }
+
+    /// Next state of a cell in `state` whose neighbours in state 1 are the
+    /// bits of `neighbourhood`, in the order of the grid's neighbour
+    /// offsets, taking dying states into account.
+    ///
+    /// Hexagonal rules leave out the north-east and south-west bits.
+    /// Triangular, Larger than Life and 3D rules need more than the eight
+    /// surrounding cells and should go through
+    /// [`Rule::next_state_with_count`]; here they just count the bits.
+    /// Continuous rules only run on whole fields of cells and leave a
+    /// single cell as it is.
+    pub fn next_state(&self, state: u8, neighbourhood: u8) -> u8 {
+        match &self.family {
+            Family::Lenia(_) => state,
+            Family::Moore { birth, survival } => self.transition(
+                state,
+                birth.contains(neighbourhood),
+                survival.contains(neighbourhood),
+            ),
+            Family::Counts {
+                lattice: Lattice::Hexagonal,
+                ..
+            } => self.next_state_with_count(
+                state,
+                (neighbourhood & lattice::HEXAGONAL_MASK).count_ones(),
+            ),
+            Family::Counts { .. } | Family::LargerThanLife(_) | Family::Cubic(_) => {
+                self.next_state_with_count(state, neighbourhood.count_ones())
+            }
+            Family::Table(_) | Family::Wolfram(_) | Family::Margolus(_) => {
+                self.next_state_with_states(state, neighbour_states(neighbourhood))
+            }
+        }
+    }
+
+    /// Next state of a cell in `state` whose neighbours, in the order of
+    /// [`NEIGHBOURS`], are in the states `neighbours`. Only rule tables and
+    /// one-dimensional rules look at more than which neighbours are in
+    /// state 1, and one-dimensional rules only look west and east. Block
+    /// rules take the cell to be the top-left of its block.
+    pub fn next_state_with_states(&self, state: u8, neighbours: [u8; 8]) -> u8 {
+        match &self.family {
+            Family::Table(table) => table.next_state(state, neighbours),
+            Family::Wolfram(wolfram) => wolfram.next_state(neighbours[3], state, neighbours[4]),
+            Family::Margolus(margolus) => {
+                let block = [state, neighbours[4], neighbours[6], neighbours[7]]
+                    .iter()
+                    .enumerate()
+                    .fold(0, |block, (bit, &state)| {
+                        block | u8::from(state == 1) << bit
+                    });
+                margolus.next_block(block) & 1
+            }
+            _ => self.next_state(state, alive_neighbourhood(neighbours)),
+        }
+    }
+
+    /// Next state of the middle cell of a 3x3 block of states given row by
+    /// row.
+    pub(crate) fn next_state_in_block<'a>(&self, block: impl IntoIterator<Item = &'a u8>) -> u8 {
+        let mut cells = [0; 9];
+        cells
+            .iter_mut()
+            .zip(block)
+            .for_each(|(cell, &state)| *cell = state);
+        let mut neighbours = [0; 8];
+        neighbours[..4].copy_from_slice(&cells[..4]);
+        neighbours[4..].copy_from_slice(&cells[5..]);
+        self.next_state_with_states(cells[4], neighbours)
+    }
+
+    /// Next state of a cell in `state` under a totalistic rule, given the
+    /// number of cells in state 1 in its neighbourhood. For Larger than Life
+    /// rules that count includes the cell itself when the rule says so.
+    /// Rule tables and one-dimensional rules see that many neighbours in
+    /// state 1 in the order of [`NEIGHBOURS`], west before east for the
+    /// latter.
+    pub fn next_state_with_count(&self, state: u8, count: u32) -> u8 {
+        match &self.family {
+            Family::Moore { .. } => {
+                let count = count.min(8) as u8;
+                self.transition(state, self.is_birth(count), self.is_survival(count))
+            }
+            Family::Counts { .. } => {
+                let count = count.min(15) as u8;
+                self.transition(state, self.is_birth(count), self.is_survival(count))
+            }
+            Family::LargerThanLife(ltl) => {
+                self.transition(state, ltl.is_birth(count), ltl.is_survival(count))
+            }
+            Family::Cubic(cubic) => {
+                self.transition(state, cubic.is_birth(count), cubic.is_survival(count))
+            }
+            Family::Table(_) => self.next_state(state, ((1u16 << count.min(8)) - 1) as u8),
+            Family::Wolfram(wolfram) => {
+                wolfram.next_state(u8::from(count >= 1), state, u8::from(count >= 2))
+            }
+            Family::Margolus(_) => self.next_state(state, ((1u16 << count.min(8)) - 1) as u8),
+            Family::Lenia(_) => state,
+        }
+    }
+
+    /// State a live cell passes into when it does not survive: the first
+    /// dying state of a Generations rule, and otherwise dead.
+    pub fn death_state(&self) -> u8 {
+        match self.family {
+            Family::Table(_) => 0,
+            _ => self.transition(1, false, false),
+        }
+    }
+
+    fn transition(&self, state: u8, born: bool, survives: bool) -> u8 {
+        match state {
+            0 => u8::from(born),
+            1 if survives => 1,
+            _ if u16::from(state) + 1 >= self.states => 0,
+            _ => state + 1,
+        }
+    }
+}
+
+/// Most cell states a Generations rule can have.
+const MAX_STATES: u16 = 256;
+
+/// Every way of putting `count` of the cells to the left and right of a
+/// cell in state 1 and the rest in state 0.
+fn sides(count: u8) -> impl Iterator<Item = (u8, u8)> {
+    [(0, 0), (1, 0), (0, 1), (1, 1)]
+        .iter()
+        .copied()
+        .filter(move |&(left, right)| left + right == count)
+}
+
+/// Every block with the top-left cell dead and `count` of the other three
+/// alive, numbered as in [`Margolus`].
+fn block_mates(count: u8) -> impl Iterator<Item = u8> {
+    (0..16)
+        .step_by(2)
+        .filter(move |block: &u8| block.count_ones() == u32::from(count))
+}
+
+/// Every way of putting `count` neighbours in state 1 and the rest in state
+/// 0, in the order of [`NEIGHBOURS`].
+fn arrangements(count: u8) -> impl Iterator<Item = [u8; 8]> {
+    (0..=u8::MAX)
+        .filter(move |neighbourhood| neighbourhood.count_ones() == u32::from(count))
+        .map(neighbour_states)
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::CONWAY
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.family {
+            Family::Moore { birth, survival } => {
+                write!(f, "B")?;
+                birth.write_hensel(f)?;
+                write!(f, "/S")?;
+                survival.write_hensel(f)?;
+                if self.states > 2 {
+                    write!(f, "/C{}", self.states)?;
+                }
+            }
+            Family::Counts {
+                lattice,
+                birth,
+                survival,
+            } => {
+                write!(f, "B")?;
+                write_counts(f, *birth)?;
+                write!(f, "/S")?;
+                write_counts(f, *survival)?;
+                if self.states > 2 {
+                    write!(f, "/C{}", self.states)?;
+                }
+                if let Some(suffix) = lattice.suffix() {
+                    write!(f, "{}", suffix)?;
+                }
+            }
+            Family::LargerThanLife(ltl) => ltl.write(self.states, f)?,
+            Family::Cubic(cubic) => cubic.write(self.states, f)?,
+            Family::Table(table) => write!(f, "{}", table.name())?,
+            Family::Wolfram(wolfram) => write!(f, "{}", wolfram)?,
+            Family::Margolus(margolus) => write!(f, "{}", margolus)?,
+            Family::Lenia(lenia) => write!(f, "{}", lenia)?,
+        }
+        match &self.topology {
+            Some(topology) => write!(f, "{}", topology),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (s, topology) = match s.find(':') {
+            Some(colon) => (&s[..colon], Some(s[colon + 1..].trim().parse()?)),
+            None => (s, None),
+        };
+        if s.is_empty() {
+            return Err(ParseRuleError::Empty);
+        }
+        let rule = if let Some(rule) = builtin::find(s) {
+            rule
+        } else if s.contains('=') {
+            Rule {
+                family: Family::Lenia(lenia::parse(s)?),
+                states: MAX_STATES,
+                topology: None,
+            }
+        } else if let Some((s, neighbourhood)) = cubic::strip_suffix(s) {
+            let (cubic, states) = cubic::parse(s, neighbourhood)?;
+            Rule {
+                family: Family::Cubic(cubic),
+                states,
+                topology: None,
+            }
+        } else if s.starts_with(['M', 'm']) {
+            Rule {
+                family: Family::Margolus(margolus::parse(s)?),
+                states: 2,
+                topology: None,
+            }
+        } else if s.starts_with(['W', 'w']) {
+            let wolfram = wolfram::parse(s)?;
+            Rule {
+                states: u16::from(wolfram.colors()),
+                family: Family::Wolfram(wolfram),
+                topology: None,
+            }
+        } else if s.starts_with(['R', 'r']) {
+            let (ltl, states) = ltl::parse(s)?;
+            Rule {
+                family: Family::LargerThanLife(ltl),
+                states,
+                topology: None,
+            }
+        } else {
+            let (s, lattice) = match s.char_indices().last() {
+                Some((last, 'H' | 'h')) => (&s[..last], Lattice::Hexagonal),
+                Some((last, 'L' | 'l')) => (&s[..last], Lattice::Triangular),
+                _ => (s, Lattice::Square),
+            };
+            if s.starts_with(|c: char| c.is_ascii_digit() || c == '/') {
+                parse_survival_birth(s, lattice)?
+            } else {
+                parse_prefixed(s, lattice)?
+            }
+        };
+        Ok(rule.with_topology(topology))
+    }
+}
+
+/// Parses the `B36/S23` family: sections introduced by `B` or `S`, in
+/// either order, optionally separated by a slash, and an optional `C`
+/// section giving the number of states of a Generations rule.
+fn parse_prefixed(s: &str, lattice: Lattice) -> Result<Rule, ParseRuleError> {
+    const SECTIONS: [char; 3] = ['B', 'S', 'C'];
+    let mut contents: [Option<Vec<(usize, char)>>; 3] = [None, None, None];
+    let mut current = None;
+    let mut previous = None;
+    for (position, c) in s.char_indices() {
+        let upper = c.to_ascii_uppercase();
+        // A lowercase c straight after a count is Hensel's letter, or 12 on
+        // the triangular lattice, not the start of the Generations section;
+        // likewise a lowercase b standing for 11.
+        let is_letter = (c == 'c' || c == 'b' && lattice == Lattice::Triangular)
+            && current.is_some_and(|section| section < 2)
+            && previous.is_some_and(|previous: char| previous != '/');
+        previous = Some(c);
+        if let Some(section) = SECTIONS.iter().position(|&name| name == upper) {
+            if !is_letter {
+                if contents[section].is_some() {
+                    return Err(ParseRuleError::DuplicateSection(upper));
+                }
+                contents[section] = Some(Vec::new());
+                current = Some(section);
+                previous = None;
+                continue;
+            }
+        }
+        match (c, current) {
+            ('/', Some(_)) => {}
+            ('0'..='9', Some(section)) => contents[section].as_mut().unwrap().push((position, c)),
+            ('-' | 'a'..='z', Some(section)) if section < 2 => {
+                contents[section].as_mut().unwrap().push((position, c))
+            }
+            _ => return Err(ParseRuleError::UnexpectedChar { position, found: c }),
+        }
+    }
+    let [birth, survival, states] = contents;
+    let birth = birth.ok_or(ParseRuleError::MissingSection('B'))?;
+    let survival = survival.ok_or(ParseRuleError::MissingSection('S'))?;
+    Ok(Rule {
+        family: match lattice {
+            Lattice::Square => Family::Moore {
+                birth: parse_hensel(&birth)?,
+                survival: parse_hensel(&survival)?,
+            },
+            _ => Family::Counts {
+                lattice,
+                birth: parse_counts(&birth, lattice)?,
+                survival: parse_counts(&survival, lattice)?,
+            },
+        },
+        states: states.map_or(Ok(2), |states| {
+            parse_states(&states.iter().map(|&(_, c)| c).collect::<String>())
+        })?,
+        topology: None,
+    })
+}
+
+/// Parses the counts of a `B` or `S` section, each optionally followed by
+/// Hensel's letters for the arrangements it includes or, after a `-`,
+/// excludes.
+fn parse_hensel(section: &[(usize, char)]) -> Result<Neighbourhoods, ParseRuleError> {
+    let mut neighbourhoods = Neighbourhoods::EMPTY;
+    let mut chars = section.iter().copied().peekable();
+    while let Some((position, c)) = chars.next() {
+        let count = match c.to_digit(10) {
+            Some(count) if count <= 8 => count as u8,
+            Some(_) => return Err(ParseRuleError::CountOutOfRange(c)),
+            None => return Err(ParseRuleError::UnexpectedChar { position, found: c }),
+        };
+        let all = Neighbourhoods::with_counts(1 << count);
+        let negated = chars.next_if(|&(_, c)| c == '-');
+        let mut letters = None;
+        while let Some((position, letter)) = chars.next_if(|&(_, c)| !c.is_ascii_digit()) {
+            let class = Neighbourhoods::with_letter(count, letter).ok_or(
+                ParseRuleError::UnexpectedChar {
+                    position,
+                    found: letter,
+                },
+            )?;
+            letters = Some(letters.unwrap_or(Neighbourhoods::EMPTY).union(class));
+        }
+        neighbourhoods = neighbourhoods.union(match (negated, letters) {
+            (None, None) => all,
+            (None, Some(letters)) => letters,
+            (Some(_), Some(letters)) => all.difference(letters),
+            (Some((position, found)), None) => {
+                return Err(ParseRuleError::UnexpectedChar { position, found })
+            }
+        });
+    }
+    Ok(neighbourhoods)
+}
+
+/// Parses the survival-first `23/3` notation, and `345/2/4` for
+/// Generations rules.
+fn parse_survival_birth(s: &str, lattice: Lattice) -> Result<Rule, ParseRuleError> {
+    let mut parts = Vec::new();
+    let mut offset = 0;
+    for part in s.split('/') {
+        if parts.len() == 3 {
+            return Err(ParseRuleError::UnexpectedChar {
+                position: offset - 1,
+                found: '/',
+            });
+        }
+        parts.push(
+            part.char_indices()
+                .map(|(position, c)| (offset + position, c))
+                .collect::<Vec<_>>(),
+        );
+        offset += part.len() + 1;
+    }
+    if parts.len() < 2 {
+        return Err(ParseRuleError::MissingSeparator);
+    }
+    let survival = parse_counts(&parts[0], lattice)?;
+    let birth = parse_counts(&parts[1], lattice)?;
+    Ok(Rule {
+        family: match lattice {
+            Lattice::Square => Family::Moore {
+                birth: Neighbourhoods::with_counts(birth),
+                survival: Neighbourhoods::with_counts(survival),
+            },
+            _ => Family::Counts {
+                lattice,
+                birth,
+                survival,
+            },
+        },
+        states: parts.get(2).map_or(Ok(2), |states| {
+            if let Some(&(position, found)) = states.iter().find(|(_, c)| !c.is_ascii_digit()) {
+                return Err(ParseRuleError::UnexpectedChar { position, found });
+            }
+            parse_states(&states.iter().map(|&(_, c)| c).collect::<String>())
+        })?,
+        topology: None,
+    })
+}
+
+/// Turns the neighbour counts of a section into a mask. Counts of 10 to 12
+/// on the triangular lattice are written `a` to `c`.
+fn parse_counts(section: &[(usize, char)], lattice: Lattice) -> Result<u16, ParseRuleError> {
+    let mut mask = 0;
+    for &(position, c) in section {
+        let count = match c {
+            '0'..='9' => c as u8 - b'0',
+            'a'..='c' if lattice == Lattice::Triangular => c as u8 - b'a' + 10,
+            _ => return Err(ParseRuleError::UnexpectedChar { position, found: c }),
+        };
+        if count > lattice.max_neighbours() {
+            return Err(ParseRuleError::CountOutOfRange(c));
+        }
+        mask |= 1 << count;
+    }
+    Ok(mask)
+}
+
+fn parse_states(digits: &str) -> Result<u16, ParseRuleError> {
+    match digits.parse() {
+        Ok(states) if (2..=MAX_STATES).contains(&states) => Ok(states),
+        _ => Err(ParseRuleError::StatesOutOfRange(digits.to_string())),
+    }
+}
+
+fn counts_to_mask(counts: &[u8]) -> u16 {
+    counts.iter().fold(0, |mask, &count| {
+        assert!(count <= 8, "a cell has at most 8 neighbours, got {}", count);
+        mask | 1 << count
+    })
+}
+
+/// Writes the counts in `mask`, using `a` to `c` for 10 to 12.
+fn write_counts(f: &mut fmt::Formatter, mask: u16) -> fmt::Result {
+    (0..=12)
+        .filter(|count| mask & 1 << count != 0)
+        .try_for_each(|count| write!(f, "{}", char::from_digit(count, 13).unwrap()))
+}
+
+/// Reasons a rulestring can be rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseRuleError {
+    /// The rulestring was empty or only whitespace.
+    Empty,
+    /// A character that has no meaning at this point of the rulestring.
+    UnexpectedChar { position: usize, found: char },
+    /// A neighbour count above the number of neighbours a cell has.
+    CountOutOfRange(char),
+    /// A section such as `B` or `S` appears twice.
+    DuplicateSection(char),
+    /// A required section such as `B` or `S` is missing.
+    MissingSection(char),
+    /// A survival-first rulestring without the `/` between its halves.
+    MissingSeparator,
+    /// A Generations state count that is missing or not between 2 and 256.
+    StatesOutOfRange(String),
+    /// A Larger than Life, one-dimensional, block, 3D or continuous rule
+    /// parameter with a value it cannot take.
+    InvalidParameter { name: char, value: String },
+    /// A malformed bounded-grid suffix.
+    InvalidTopology(String),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseRuleError::Empty => write!(f, "empty rulestring"),
+            ParseRuleError::UnexpectedChar { position, found } => {
+                write!(f, "unexpected '{}' at position {}", found, position)
+            }
+            ParseRuleError::CountOutOfRange(digit) => {
+                write!(f, "neighbour count {} is out of range", digit)
+            }
+            ParseRuleError::DuplicateSection(section) => {
+                write!(f, "section '{}' appears more than once", section)
+            }
+            ParseRuleError::MissingSection(section) => write!(f, "missing section '{}'", section),
+            ParseRuleError::StatesOutOfRange(states) if states.is_empty() => {
+                write!(f, "missing state count after 'C'")
+            }
+            ParseRuleError::StatesOutOfRange(states) => {
+                write!(f, "state count '{}' is out of range 2-256", states)
+            }
+            ParseRuleError::InvalidParameter { name, value } => {
+                write!(f, "invalid value '{}' for '{}'", value, name)
+            }
+            ParseRuleError::InvalidTopology(reason) => write!(f, "invalid grid: {}", reason),
+            ParseRuleError::MissingSeparator => {
+                write!(f, "expected '/' between survival and birth")
+            }
+        }
+    }
+}
+
+impl Error for ParseRuleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Rule {
+        s.parse().unwrap_or_else(|e| panic!("{}: {}", s, e))
+    }
+
+    #[test]
+    fn parses_birth_survival_notation() {
+        let rule = parse("B36/S23");
+        assert_eq!(rule, Rule::new(&[3, 6], &[2, 3]));
+        assert!(rule.is_birth(6) && !rule.is_birth(2));
+        assert!(rule.is_survival(2) && !rule.is_survival(6));
+        assert_eq!(parse("s23b3"), Rule::CONWAY);
+        assert_eq!(parse("B3S23"), Rule::CONWAY);
+    }
+
+    #[test]
+    fn parses_survival_birth_notation() {
+        assert_eq!(parse("23/3"), Rule::CONWAY);
+        assert_eq!(parse("/2"), Rule::new(&[2], &[]));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in ["B3/S23", "B36/S23", "B/S012345678", "B2/S"] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+        assert_eq!(parse("23/36").to_string(), "B36/S23");
+    }
+
+    #[test]
+    fn next_state_follows_the_counts() {
+        let rule = Rule::CONWAY;
+        assert_eq!(rule.next_state_with_count(0, 3), 1);
+        assert_eq!(rule.next_state_with_count(0, 2), 0);
+        assert_eq!(rule.next_state_with_count(1, 2), 1);
+        assert_eq!(rule.next_state_with_count(1, 4), 0);
+    }
+
+    #[test]
+    fn rejects_malformed_rulestrings() {
+        let error = |s: &str| s.parse::<Rule>().unwrap_err();
+        assert_eq!(error("  "), ParseRuleError::Empty);
+        assert_eq!(error("B3"), ParseRuleError::MissingSection('S'));
+        assert_eq!(error("B9/S23"), ParseRuleError::CountOutOfRange('9'));
+        assert_eq!(error("B3/S2/B3"), ParseRuleError::DuplicateSection('B'));
+        assert_eq!(error("23"), ParseRuleError::MissingSeparator);
+        assert_eq!(
+            error("B3/S2x"),
+            ParseRuleError::UnexpectedChar {
+                position: 5,
+                found: 'x'
+            }
+        );
+    }
+
+    #[test]
+    fn parses_generations_rules() {
+        let brain = parse("B2/S/C3");
+        assert_eq!(brain.states(), 3);
+        assert_eq!(brain.death_state(), 2);
+        assert_eq!(parse("b2s/c3"), brain);
+        assert_eq!(parse("345/2/4").to_string(), "B2/S345/C4");
+        assert_eq!(parse("B3/S23/C2"), Rule::CONWAY);
+        assert_eq!(parse("B2/S/C256").states(), 256);
+        for s in ["B2/S/C", "B2/S/C1", "B2/S/C300"] {
+            assert!(matches!(
+                s.parse::<Rule>(),
+                Err(ParseRuleError::StatesOutOfRange(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn dying_cells_decay_and_block_births() {
+        let star_wars = parse("345/2/4");
+        assert_eq!(star_wars.next_state_with_count(1, 2), 2);
+        assert_eq!(star_wars.next_state_with_count(1, 3), 1);
+        assert_eq!(star_wars.next_state_with_count(2, 2), 3);
+        assert_eq!(star_wars.next_state_with_count(3, 2), 0);
+        assert_eq!(star_wars.next_state_with_count(0, 2), 1);
+        assert_eq!(star_wars.state_name(0), "dead");
+        assert_eq!(star_wars.state_name(1), "alive");
+        assert_eq!(star_wars.state_name(3), "dying 2");
+    }
+
+    #[test]
+    fn hensel_notation_round_trips() {
+        for s in [
+            "B2-a/S12",
+            "B3/S23-q",
+            "B2e3ai/S1c23",
+            "B3/S2-i34q",
+            "B2cek3-q/S",
+        ] {
+            let rule = parse(s);
+            assert!(!rule.is_totalistic());
+            assert_eq!(rule.to_string(), s);
+        }
+        assert_eq!(parse("B3/S2ac").to_string(), "B3/S2ca");
+    }
+
+    #[test]
+    fn hensel_letters_pick_neighbourhoods() {
+        let rule = parse("B2a/S");
+        let class = Neighbourhoods::with_letter(2, 'a').unwrap();
+        for neighbourhood in 0..=255 {
+            let born = rule.next_state(0, neighbourhood) == 1;
+            assert_eq!(born, class.contains(neighbourhood), "{:08b}", neighbourhood);
+        }
+    }
+
+    #[test]
+    fn counts_beyond_the_neighbourhood_never_apply() {
+        let everything = parse("B012345678/S012345678");
+        assert!(everything.is_birth(8) && everything.is_survival(8));
+        let hensel = parse("B2a3/S23-q");
+        assert!(hensel.is_birth(3) && !hensel.is_survival(3));
+        for rule in [everything, hensel] {
+            for neighbours in [9, 16, 31, 32, 255] {
+                assert!(!rule.is_birth(neighbours), "{} {}", rule, neighbours);
+                assert!(!rule.is_survival(neighbours), "{} {}", rule, neighbours);
+            }
+        }
+        let rule_254 = parse("W254");
+        assert!(rule_254.is_birth(2) && !rule_254.is_birth(3));
+        assert!(!rule_254.is_survival(3));
+        let bbm = parse("BBM");
+        assert!(!bbm.is_birth(4) && !bbm.is_survival(4));
+    }
+
+    #[test]
+    fn rejects_letters_a_count_does_not_have() {
+        let error = |s: &str| s.parse::<Rule>().unwrap_err();
+        assert_eq!(
+            error("B1a/S"),
+            ParseRuleError::UnexpectedChar {
+                position: 2,
+                found: 'a'
+            }
+        );
+        assert_eq!(
+            error("B3/S2-"),
+            ParseRuleError::UnexpectedChar {
+                position: 5,
+                found: '-'
+            }
+        );
+        assert_eq!(
+            error("B5w/S"),
+            ParseRuleError::UnexpectedChar {
+                position: 2,
+                found: 'w'
+            }
+        );
+    }
+
+    #[test]
+    fn lattice_suffixes_round_trip() {
+        for s in ["B2/S34H", "B2/S/C3H", "B4ab/S5cL", "B45/S34L"] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+        assert_eq!(parse("B2/S34H").lattice(), Lattice::Hexagonal);
+        assert_eq!(parse("B45/S34L").lattice(), Lattice::Triangular);
+        assert_eq!(parse("34/2h").to_string(), "B2/S34H");
+        assert_eq!(parse("B2/S34H").range(), 1);
+        assert_eq!(parse("B45/S34L").range(), 2);
+    }
+
+    #[test]
+    fn hexagonal_rules_ignore_two_corners() {
+        let rule = parse("B2/SH");
+        assert_eq!(rule.next_state(0, 1 << 2 | 1 << 5), 0);
+        assert_eq!(rule.next_state(0, 1 << 0 | 1 << 7), 1);
+        assert_eq!(rule.next_state(0, 1 << 0 | 1 << 2 | 1 << 7), 1);
+    }
+
+    #[test]
+    fn rejects_counts_beyond_the_lattice() {
+        let error = |s: &str| s.parse::<Rule>().unwrap_err();
+        assert_eq!(error("B7/S2H"), ParseRuleError::CountOutOfRange('7'));
+        assert_eq!(
+            error("B2a/S3H"),
+            ParseRuleError::UnexpectedChar {
+                position: 2,
+                found: 'a'
+            }
+        );
+        assert_eq!(
+            error("B2d/S3L"),
+            ParseRuleError::UnexpectedChar {
+                position: 2,
+                found: 'd'
+            }
+        );
+    }
+}
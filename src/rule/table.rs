@@ -0,0 +1,295 @@
+//! Rules given as an explicit list of transitions or as a decision tree over
+//! the states of a cell and its neighbours, as loaded from Golly's `.rule`
+//! files by [`format::rulefile`](crate::format::rulefile).
+
+/// Which cells around a cell a rule table looks at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TableNeighbourhood {
+    /// The eight surrounding cells.
+    Moore,
+    /// The four orthogonally adjacent cells.
+    VonNeumann,
+    /// The six neighbours of a hexagonal cell, in the sheared layout of
+    /// [`Lattice::Hexagonal`](super::Lattice::Hexagonal).
+    Hexagonal,
+}
+
+impl TableNeighbourhood {
+    /// Indices into [`NEIGHBOURS`](super::NEIGHBOURS) of the neighbours in
+    /// the order a `@TABLE` lists them: clockwise from north.
+    pub(crate) fn clockwise(self) -> &'static [usize] {
+        match self {
+            TableNeighbourhood::Moore => &[1, 2, 4, 7, 6, 5, 3, 0],
+            TableNeighbourhood::VonNeumann => &[1, 4, 6, 3],
+            TableNeighbourhood::Hexagonal => &[1, 4, 7, 6, 3, 0],
+        }
+    }
+
+    /// Indices into [`NEIGHBOURS`](super::NEIGHBOURS) of the neighbours in
+    /// the order a `@TREE` visits them, before the cell itself.
+    fn tree_order(self) -> &'static [usize] {
+        match self {
+            TableNeighbourhood::Moore => &[0, 2, 5, 7, 1, 3, 4, 6],
+            TableNeighbourhood::VonNeumann => &[1, 3, 4, 6],
+            TableNeighbourhood::Hexagonal => unreachable!("rule trees are never hexagonal"),
+        }
+    }
+}
+
+/// One line of a rule table once its variables have been expanded: the
+/// states allowed for the cell and then for each neighbour in clockwise
+/// order, and the state the cell takes when they all match.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Transition {
+    pub inputs: Vec<Vec<u8>>,
+    pub output: u8,
+}
+
+/// A rule over up to 256 states that looks at the exact state of every
+/// neighbour. Cells that no transition matches keep their state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RuleTable {
+    name: String,
+    states: u16,
+    neighbourhood: TableNeighbourhood,
+    lookup: Lookup,
+    /// Display colour of each state, where the rule gives one.
+    colors: Vec<Option<(u8, u8, u8)>>,
+    /// Name of each state, where the rule gives one.
+    names: Vec<Option<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Lookup {
+    /// For each position, cell first, and each state there, a bit set of
+    /// the transitions that allow it. The first transition allowed at
+    /// every position wins.
+    Table {
+        words: usize,
+        allowed: Vec<u64>,
+        outputs: Vec<u8>,
+    },
+    /// Nodes of `states` children each. The root's children are picked by
+    /// the first neighbour in tree order, and the children of the last
+    /// level, picked by the cell itself, are states.
+    Tree { root: usize, children: Vec<u32> },
+}
+
+impl RuleTable {
+    /// Builds a rule that applies the first matching of `transitions`.
+    pub(crate) fn from_transitions(
+        name: String,
+        states: u16,
+        neighbourhood: TableNeighbourhood,
+        transitions: &[Transition],
+    ) -> Self {
+        let positions = neighbourhood.clockwise().len() + 1;
+        let words = transitions.len().div_ceil(64).max(1);
+        let mut allowed = vec![0; positions * usize::from(states) * words];
+        for (index, transition) in transitions.iter().enumerate() {
+            for (position, inputs) in transition.inputs.iter().enumerate() {
+                for &state in inputs {
+                    let slot = (position * usize::from(states) + usize::from(state)) * words;
+                    allowed[slot + index / 64] |= 1 << (index % 64);
+                }
+            }
+        }
+        RuleTable {
+            name,
+            states,
+            neighbourhood,
+            lookup: Lookup::Table {
+                words,
+                allowed,
+                outputs: transitions.iter().map(|t| t.output).collect(),
+            },
+            colors: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Builds a rule from the flattened children of tree nodes, `states`
+    /// to a node, whose last node is the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the neighbourhood is hexagonal, which trees cannot have.
+    pub(crate) fn from_tree(
+        name: String,
+        states: u16,
+        neighbourhood: TableNeighbourhood,
+        children: Vec<u32>,
+    ) -> Self {
+        assert!(
+            neighbourhood != TableNeighbourhood::Hexagonal,
+            "rule trees are never hexagonal"
+        );
+        RuleTable {
+            name,
+            states,
+            neighbourhood,
+            lookup: Lookup::Tree {
+                root: children.len() / usize::from(states) - 1,
+                children,
+            },
+            colors: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Gives `state` the colour `rgb`.
+    pub(crate) fn set_color(&mut self, state: u8, rgb: (u8, u8, u8)) {
+        let index = usize::from(state);
+        if self.colors.len() <= index {
+            self.colors.resize(index + 1, None);
+        }
+        self.colors[index] = Some(rgb);
+    }
+
+    /// Gives `state` the name `name`.
+    pub(crate) fn set_name(&mut self, state: u8, name: String) {
+        let index = usize::from(state);
+        if self.names.len() <= index {
+            self.names.resize(index + 1, None);
+        }
+        self.names[index] = Some(name);
+    }
+
+    /// The name the rule goes by, from its file.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn states(&self) -> u16 {
+        self.states
+    }
+
+    pub fn neighbourhood(&self) -> TableNeighbourhood {
+        self.neighbourhood
+    }
+
+    /// The colour the rule gives `state`, if any.
+    pub fn color(&self, state: u8) -> Option<(u8, u8, u8)> {
+        self.colors.get(usize::from(state)).copied().flatten()
+    }
+
+    /// The name the rule gives `state`, if any.
+    pub fn state_name(&self, state: u8) -> Option<&str> {
+        self.names.get(usize::from(state))?.as_deref()
+    }
+
+    /// Next state of a cell in `state` whose neighbours, in the order of
+    /// [`NEIGHBOURS`](super::NEIGHBOURS), are in `neighbours`. States the
+    /// rule does not have count as 0.
+    pub fn next_state(&self, state: u8, neighbours: [u8; 8]) -> u8 {
+        let valid = |state: u8| {
+            if u16::from(state) < self.states {
+                usize::from(state)
+            } else {
+                0
+            }
+        };
+        let states = usize::from(self.states);
+        match &self.lookup {
+            Lookup::Table {
+                words,
+                allowed,
+                outputs,
+            } => {
+                let clockwise = self.neighbourhood.clockwise();
+                let mut cells = [valid(state); 9];
+                for (cell, &i) in cells[1..].iter_mut().zip(clockwise) {
+                    *cell = valid(neighbours[i]);
+                }
+                let cells = &cells[..clockwise.len() + 1];
+                for word in 0..*words {
+                    let matches =
+                        cells
+                            .iter()
+                            .enumerate()
+                            .fold(u64::MAX, |matches, (position, &cell)| {
+                                matches & allowed[(position * states + cell) * words + word]
+                            });
+                    if matches != 0 {
+                        return outputs[word * 64 + matches.trailing_zeros() as usize];
+                    }
+                }
+                state
+            }
+            Lookup::Tree { root, children } => {
+                let node = self
+                    .neighbourhood
+                    .tree_order()
+                    .iter()
+                    .map(|&i| neighbours[i])
+                    .chain(std::iter::once(state))
+                    .fold(*root, |node, cell| {
+                        children[node * states + valid(cell)] as usize
+                    });
+                node as u8
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The eight neighbours with only the one at `NEIGHBOURS[index]` in
+    /// `state`.
+    fn only(index: usize, state: u8) -> [u8; 8] {
+        let mut neighbours = [0; 8];
+        neighbours[index] = state;
+        neighbours
+    }
+
+    #[test]
+    fn first_matching_transition_wins() {
+        // Enough transitions that the last ones land in a second word.
+        let mut transitions = vec![
+            Transition {
+                inputs: vec![vec![]; 5],
+                output: 1,
+            };
+            70
+        ];
+        let any = vec![0, 1, 2];
+        transitions.push(Transition {
+            inputs: vec![vec![0], vec![2], any.clone(), any.clone(), any.clone()],
+            output: 2,
+        });
+        transitions.push(Transition {
+            inputs: vec![vec![0], any.clone(), any.clone(), any.clone(), any],
+            output: 1,
+        });
+        let table = RuleTable::from_transitions(
+            "Test".to_string(),
+            3,
+            TableNeighbourhood::VonNeumann,
+            &transitions,
+        );
+        assert_eq!(table.next_state(0, only(1, 2)), 2);
+        assert_eq!(table.next_state(0, only(4, 2)), 1);
+        // Cells no transition matches keep their state, and states the
+        // rule does not have read as 0.
+        assert_eq!(table.next_state(2, [0; 8]), 2);
+        assert_eq!(table.next_state(0, only(1, 7)), 1);
+    }
+
+    #[test]
+    fn trees_visit_the_neighbours_in_order() {
+        // A von Neumann tree whose result is the state of the north
+        // neighbour, the first one it looks at.
+        let children = vec![0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7];
+        let table = RuleTable::from_tree(
+            "North".to_string(),
+            2,
+            TableNeighbourhood::VonNeumann,
+            children,
+        );
+        assert_eq!(table.next_state(0, only(1, 1)), 1);
+        assert_eq!(table.next_state(1, only(6, 1)), 0);
+        assert_eq!(table.next_state(1, only(0, 1)), 0);
+    }
+}
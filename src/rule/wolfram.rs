@@ -0,0 +1,175 @@
+//! One-dimensional rules in which a cell looks at itself and the cells to
+//! its left and right, numbered the way Wolfram numbers them: elementary
+//! rules such as `W30` and totalistic rules with more colours such as
+//! `W777K3`.
+
+use std::fmt;
+
+use super::ParseRuleError;
+
+/// Most colours a totalistic rule may have.
+const MAX_COLORS: u8 = 10;
+
+/// A one-dimensional rule of range 1.
+///
+/// Elementary rules have two colours and give the next state of each of the
+/// eight neighbourhoods as a bit of their number, the neighbourhood read as
+/// a binary number from left to right picking the bit. Totalistic rules
+/// with `k` colours give it as a digit in base `k`, picked by the sum of
+/// the three cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Wolfram {
+    code: u128,
+    colors: u8,
+    totalistic: bool,
+}
+
+impl Wolfram {
+    /// The rule's number.
+    pub fn code(&self) -> u128 {
+        self.code
+    }
+
+    /// Number of cell states.
+    pub fn colors(&self) -> u8 {
+        self.colors
+    }
+
+    /// Whether the rule only looks at the sum of the three cells.
+    pub fn is_totalistic(&self) -> bool {
+        self.totalistic
+    }
+
+    /// Next state of a cell in state `centre` between cells in states
+    /// `left` and `right`. States the rule does not have count as 0.
+    pub fn next_state(&self, left: u8, centre: u8, right: u8) -> u8 {
+        let k = u128::from(self.colors);
+        let valid = |state: u8| {
+            if state < self.colors {
+                u32::from(state)
+            } else {
+                0
+            }
+        };
+        let (left, centre, right) = (valid(left), valid(centre), valid(right));
+        let digit = if self.totalistic {
+            left + centre + right
+        } else {
+            left << 2 | centre << 1 | right
+        };
+        (self.code / k.pow(digit) % k) as u8
+    }
+
+    /// Number of digits the rule's number has in base `colors`: one for
+    /// each neighbourhood, or for each sum of one.
+    fn digits(colors: u8, totalistic: bool) -> u32 {
+        if totalistic {
+            3 * u32::from(colors) - 2
+        } else {
+            u32::from(colors).pow(3)
+        }
+    }
+}
+
+impl fmt::Display for Wolfram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "W{}", self.code)?;
+        if self.totalistic {
+            write!(f, "K{}", self.colors)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `W` and the rule's number, followed for totalistic rules by `K`
+/// and the number of colours.
+pub(super) fn parse(s: &str) -> Result<Wolfram, ParseRuleError> {
+    let body = &s[1..];
+    let (code, colors) = match body.find(['K', 'k']) {
+        Some(k) => (&body[..k], Some(&body[k + 1..])),
+        None => (body, None),
+    };
+    let invalid = |name: char, value: &str| ParseRuleError::InvalidParameter {
+        name,
+        value: value.to_string(),
+    };
+    let (colors, totalistic) = match colors {
+        Some(value) => match value.parse() {
+            Ok(colors) if (2..=MAX_COLORS).contains(&colors) => (colors, true),
+            _ => return Err(invalid('K', value)),
+        },
+        None => (2, false),
+    };
+    let limit = u128::from(colors).pow(Wolfram::digits(colors, totalistic));
+    match code.parse() {
+        Ok(code) if code < limit => Ok(Wolfram {
+            code,
+            colors,
+            totalistic,
+        }),
+        _ => Err(invalid('W', code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    #[test]
+    fn display_round_trips() {
+        for s in ["W30", "W110", "W0", "W255", "W777K3", "W1K2"] {
+            assert_eq!(s.parse::<Rule>().unwrap().to_string(), s);
+        }
+        assert_eq!(parse("W777k3").unwrap().to_string(), "W777K3");
+    }
+
+    #[test]
+    fn elementary_rules_read_their_bits() {
+        let rule30 = parse("W30").unwrap();
+        let outputs: Vec<_> = (0..8)
+            .rev()
+            .map(|n| rule30.next_state(n >> 2 & 1, n >> 1 & 1, n & 1))
+            .collect();
+        assert_eq!(outputs, [0, 0, 0, 1, 1, 1, 1, 0]);
+        let rule90 = parse("W90").unwrap();
+        for n in 0..8 {
+            let (left, centre, right) = (n >> 2 & 1, n >> 1 & 1, n & 1);
+            assert_eq!(rule90.next_state(left, centre, right), left ^ right);
+        }
+        assert!(!rule30.is_totalistic());
+        assert_eq!(rule30.colors(), 2);
+    }
+
+    #[test]
+    fn totalistic_rules_read_their_digits() {
+        // 777 is 1001210 in base 3, read from the sum 6 down to 0.
+        let rule = parse("W777K3").unwrap();
+        let outputs: Vec<_> = (0..=6)
+            .map(|sum: u8| {
+                rule.next_state(
+                    sum.min(2),
+                    sum.saturating_sub(2).min(2),
+                    sum.saturating_sub(4),
+                )
+            })
+            .collect();
+        assert_eq!(outputs, [0, 1, 2, 1, 0, 0, 1]);
+        assert_eq!(rule.next_state(9, 0, 1), 1);
+    }
+
+    #[test]
+    fn rejects_numbers_out_of_range() {
+        let invalid = |name, value: &str| {
+            Err(ParseRuleError::InvalidParameter {
+                name,
+                value: value.to_string(),
+            })
+        };
+        assert_eq!(parse("W256"), invalid('W', "256"));
+        assert_eq!(parse("W2187K3"), invalid('W', "2187"));
+        assert_eq!(parse("Wx"), invalid('W', "x"));
+        assert_eq!(parse("W30K1"), invalid('K', "1"));
+        assert_eq!(parse("W30K11"), invalid('K', "11"));
+    }
+}
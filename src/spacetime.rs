@@ -0,0 +1,391 @@
+//! A one-dimensional automaton drawn as a space-time diagram: row `y` of
+//! the universe is the line of cells at generation `y`, so its history
+//! reads from top to bottom.
+
+use std::{any::Any, collections::VecDeque, convert::TryFrom};
+
+use crate::engine::Engine;
+use crate::rule::{Rule, Wolfram};
+use crate::topology::Topology;
+
+/// Most cells of history kept; the oldest rows are dropped beyond it.
+const MAX_HISTORY_CELLS: usize = 1 << 26;
+
+/// A line of cells starting at column `left`.
+#[derive(Clone, Debug, Default)]
+struct Row {
+    left: i64,
+    cells: Vec<u8>,
+}
+
+impl Row {
+    fn get(&self, x: i64) -> u8 {
+        usize::try_from(x - self.left)
+            .ok()
+            .and_then(|i| self.cells.get(i).copied())
+            .unwrap_or(0)
+    }
+}
+
+/// The history of a one-dimensional rule such as `W30`.
+///
+/// On the unbounded line, rules that turn three dead cells into a live
+/// one are not supported, since they would fill the infinite empty
+/// background in a single generation. A `:T` suffix joins the ends of a
+/// line `width` cells long into a ring, and a `:P` suffix keeps the cells
+/// beyond its ends dead; the height of either is ignored.
+#[derive(Clone, Debug)]
+pub struct SpaceTime {
+    rule: Rule,
+    /// Next state for each neighbourhood, indexed by
+    /// `(left * colors + centre) * colors + right`.
+    outputs: Vec<u8>,
+    colors: usize,
+    /// Length of a bounded line, and whether its ends are joined.
+    bounds: Option<(i64, bool)>,
+    /// The lines kept, oldest first; the last is the current generation.
+    rows: VecDeque<Row>,
+    /// Cells held by `rows`.
+    kept: usize,
+    generation: u64,
+}
+
+impl SpaceTime {
+    /// Creates an empty line running `rule`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rule` is not one-dimensional, if it turns dead cells
+    /// live on the unbounded line, or if it runs on a topology other than
+    /// a plane or an unshifted torus of some width.
+    pub fn new(rule: Rule) -> Self {
+        let wolfram: Wolfram = *rule
+            .wolfram()
+            .expect("a space-time diagram needs a one-dimensional rule");
+        let bounds = match rule.topology() {
+            None => None,
+            Some(Topology::Plane { width, .. }) if width > 0 => Some((i64::from(width), false)),
+            Some(Topology::Torus {
+                width, shift: None, ..
+            }) if width > 0 => Some((i64::from(width), true)),
+            Some(topology) => panic!("a space-time diagram cannot run on {}", topology),
+        };
+        assert!(
+            bounds.is_some() || !rule.is_birth(0),
+            "an unbounded space-time diagram cannot run rules that bring dead cells to life"
+        );
+        let colors = usize::from(wolfram.colors());
+        let mut outputs = vec![0; colors.pow(3)];
+        for (index, output) in outputs.iter_mut().enumerate() {
+            let (left, centre, right) = (
+                index / colors / colors,
+                index / colors % colors,
+                index % colors,
+            );
+            *output = wolfram.next_state(left as u8, centre as u8, right as u8);
+        }
+        let row = match bounds {
+            Some((width, _)) => Row {
+                left: 0,
+                cells: vec![0; width as usize],
+            },
+            None => Row::default(),
+        };
+        SpaceTime {
+            rule,
+            outputs,
+            colors,
+            bounds,
+            kept: row.cells.len(),
+            rows: VecDeque::from(vec![row]),
+            generation: 0,
+        }
+    }
+
+    /// Generation of the oldest row still kept.
+    pub fn first_generation(&self) -> u64 {
+        self.generation + 1 - self.rows.len() as u64
+    }
+
+    /// The diagram as text, one line per generation kept and one character
+    /// per cell across the columns ever occupied: `.` for state 0, and `#`
+    /// for state 1 of a two-colour rule or the state's digit otherwise.
+    pub fn to_text(&self) -> String {
+        let left = self.rows.iter().map(|row| row.left).min().unwrap_or(0);
+        let right = self
+            .rows
+            .iter()
+            .map(|row| row.left + row.cells.len() as i64)
+            .max()
+            .unwrap_or(0);
+        let mut text = format!(
+            "! {}\n! generations {} to {}, columns {} to {}\n",
+            self.rule,
+            self.first_generation(),
+            self.generation,
+            left,
+            right - 1
+        );
+        for row in &self.rows {
+            text.extend((left..right).map(|x| match row.get(x) {
+                0 => '.',
+                1 if self.colors == 2 => '#',
+                state => char::from_digit(u32::from(state), 36).unwrap_or('?'),
+            }));
+            text.push('\n');
+        }
+        text
+    }
+
+    fn current(&self) -> &Row {
+        self.rows
+            .back()
+            .expect("a space-time diagram always has a row")
+    }
+
+    /// Index into the rows of generation `y`, if it is kept.
+    fn row_index(&self, y: i64) -> Option<usize> {
+        let first = self.first_generation();
+        let y = u64::try_from(y).ok()?;
+        if y < first || y > self.generation {
+            None
+        } else {
+            Some((y - first) as usize)
+        }
+    }
+
+    /// Computes the line after `row`.
+    fn next_row(&self, row: &Row) -> Row {
+        let colors = self.colors;
+        let valid = |state: u8| {
+            if usize::from(state) < colors {
+                usize::from(state)
+            } else {
+                0
+            }
+        };
+        let output = |left: u8, centre: u8, right: u8| {
+            self.outputs[(valid(left) * colors + valid(centre)) * colors + valid(right)]
+        };
+        match self.bounds {
+            Some((width, wrap)) => {
+                let at = |x: i64| {
+                    if wrap {
+                        row.get(x.rem_euclid(width))
+                    } else {
+                        row.get(x)
+                    }
+                };
+                Row {
+                    left: 0,
+                    cells: (0..width)
+                        .map(|x| output(at(x - 1), at(x), at(x + 1)))
+                        .collect(),
+                }
+            }
+            None => {
+                let mut cells: Vec<u8> = (row.left - 1..row.left + row.cells.len() as i64 + 1)
+                    .map(|x| output(row.get(x - 1), row.get(x), row.get(x + 1)))
+                    .collect();
+                let start = cells.iter().position(|&state| state != 0).unwrap_or(0);
+                let end = cells
+                    .iter()
+                    .rposition(|&state| state != 0)
+                    .map_or(0, |end| end + 1);
+                cells.truncate(end);
+                cells.drain(..start.min(end));
+                Row {
+                    left: row.left - 1 + start as i64,
+                    cells,
+                }
+            }
+        }
+    }
+}
+
+impl Default for SpaceTime {
+    fn default() -> Self {
+        SpaceTime::new("W30".parse().expect("W30 is a valid rule"))
+    }
+}
+
+impl Engine for SpaceTime {
+    fn name(&self) -> &'static str {
+        "spacetime"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        self.rows
+            .iter()
+            .map(|row| row.cells.iter().filter(|&&state| state != 0).count() as u64)
+            .sum()
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        self.row_index(y).map_or(0, |index| self.rows[index].get(x))
+    }
+
+    /// Only cells of the current generation can be set; the history is
+    /// fixed.
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        if y < 0 || y as u64 != self.generation {
+            return;
+        }
+        if let Some((width, _)) = self.bounds {
+            if x < 0 || x >= width {
+                return;
+            }
+        }
+        let row = self
+            .rows
+            .back_mut()
+            .expect("a space-time diagram always has a row");
+        let before = row.cells.len();
+        if state == 0 && (x < row.left || x >= row.left + before as i64) {
+            return;
+        }
+        if row.cells.is_empty() {
+            row.left = x;
+        }
+        if x < row.left {
+            let grow = (row.left - x) as usize;
+            row.cells.splice(0..0, vec![0; grow]);
+            row.left = x;
+        }
+        let index = (x - row.left) as usize;
+        if index >= row.cells.len() {
+            row.cells.resize(index + 1, 0);
+        }
+        row.cells[index] = state;
+        self.kept += row.cells.len() - before;
+    }
+
+    /// Kills every cell of every generation kept.
+    fn clear(&mut self) {
+        for row in &mut self.rows {
+            row.cells.iter_mut().for_each(|cell| *cell = 0);
+        }
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        let first = self.first_generation() as i64;
+        let mut cells = Vec::new();
+        for (y, row) in (first..).zip(&self.rows) {
+            for (x, &state) in (row.left..).zip(&row.cells) {
+                if state != 0 {
+                    cells.push((x, y, state));
+                }
+            }
+        }
+        cells
+    }
+
+    fn step(&mut self) {
+        let next = self.next_row(self.current());
+        self.kept += next.cells.len();
+        self.rows.push_back(next);
+        self.generation += 1;
+        while self.kept > MAX_HISTORY_CELLS && self.rows.len() > 1 {
+            let oldest = self.rows.pop_front().expect("more than one row is kept");
+            self.kept -= oldest.cells.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A diagram running `rule` from the cells of generation 0 at `cells`.
+    fn diagram(rule: &str, cells: &[i64]) -> SpaceTime {
+        let mut diagram = SpaceTime::new(rule.parse().unwrap());
+        for &x in cells {
+            diagram.set_cell(x, 0, 1);
+        }
+        diagram
+    }
+
+    /// Rows of the diagram as drawn by `to_text`, without its header.
+    fn rows(diagram: &SpaceTime) -> Vec<String> {
+        diagram
+            .to_text()
+            .lines()
+            .skip(2)
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn rule_30_grows_from_a_single_cell() {
+        let mut rule30 = SpaceTime::default();
+        rule30.set_cell(0, 0, 1);
+        for _ in 0..4 {
+            rule30.step();
+        }
+        assert_eq!(
+            rows(&rule30),
+            [
+                "....#....",
+                "...###...",
+                "..##..#..",
+                ".##.####.",
+                "##..#...#"
+            ]
+        );
+        assert!(rule30
+            .to_text()
+            .starts_with("! W30\n! generations 0 to 4, columns -4 to 4\n"));
+        assert_eq!(rule30.cell(-1, 2), 1);
+        assert_eq!(rule30.population(), 1 + 3 + 3 + 6 + 4);
+    }
+
+    #[test]
+    fn rings_wrap_and_planes_cut_off() {
+        let mut ring = diagram("W90:T4,4", &[0]);
+        ring.step();
+        assert_eq!(rows(&ring), ["#...", ".#.#"]);
+        // Both neighbours of every cell are now alive and cancel out.
+        ring.step();
+        assert_eq!(rows(&ring)[2], "....");
+
+        let mut line = diagram("W90:P4,4", &[0]);
+        line.step();
+        assert_eq!(rows(&line)[1], ".#..");
+    }
+
+    #[test]
+    fn only_the_current_generation_is_edited() {
+        let mut diagram = diagram("W90", &[0]);
+        diagram.step();
+        diagram.set_cell(5, 0, 1);
+        assert_eq!(diagram.cell(5, 0), 0);
+        diagram.set_cell(5, 1, 1);
+        assert_eq!(diagram.cell(5, 1), 1);
+        assert_eq!(
+            diagram.live_cells(),
+            [(0, 0, 1), (-1, 1, 1), (1, 1, 1), (5, 1, 1)]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_births_on_the_unbounded_line() {
+        SpaceTime::new("W1".parse().unwrap());
+    }
+}
@@ -0,0 +1,247 @@
+//! An unbounded universe stored as square tiles in a hash map. Only tiles
+//! holding live cells are kept, so patterns can travel arbitrarily far.
+
+use std::{any::Any, collections::HashMap};
+
+use crate::engine::Engine;
+use crate::rule::{Lattice, Rule};
+
+/// Side of a tile, in cells.
+const TILE: i64 = 16;
+
+type Tile = [u8; (TILE * TILE) as usize];
+
+/// A Life-like universe on the unbounded plane, allocated tile by tile
+/// where cells are alive.
+///
+/// Rules with `B0` are not supported, since they would fill the infinite
+/// empty background in a single generation.
+#[derive(Clone, Debug)]
+pub struct Sparse {
+    rule: Rule,
+    /// Tiles keyed by tile coordinates, each holding at least one live cell.
+    tiles: HashMap<(i64, i64), Tile>,
+    generation: u64,
+}
+
+impl Sparse {
+    /// Creates an empty universe running `rule`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rule` has birth on 0 neighbours, is a Larger than Life
+    /// rule or runs on triangular cells.
+    pub fn new(rule: Rule) -> Self {
+        assert!(!rule.is_birth(0), "a sparse universe cannot run B0 rules");
+        assert!(
+            rule.larger_than_life().is_none(),
+            "a sparse universe cannot run Larger than Life rules"
+        );
+        assert!(
+            rule.lattice() != Lattice::Triangular,
+            "a sparse universe cannot run triangular rules"
+        );
+        Sparse {
+            rule,
+            tiles: HashMap::new(),
+            generation: 0,
+        }
+    }
+
+    /// Number of tiles currently allocated.
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn split(x: i64, y: i64) -> ((i64, i64), usize) {
+        let key = (x.div_euclid(TILE), y.div_euclid(TILE));
+        let index = y.rem_euclid(TILE) * TILE + x.rem_euclid(TILE);
+        (key, index as usize)
+    }
+
+    /// Computes the next generation of the tile at `key`, reading a one
+    /// cell border from its neighbours.
+    fn next_tile(&self, key: (i64, i64)) -> Tile {
+        const SIDE: usize = TILE as usize + 2;
+        let mut area = [[0; SIDE]; SIDE];
+        for (ty, row) in (-1..=1).zip([0..1, 1..SIDE - 1, SIDE - 1..SIDE].iter()) {
+            for (tx, columns) in (-1..=1).zip([0..1, 1..SIDE - 1, SIDE - 1..SIDE].iter()) {
+                let tile = match self.tiles.get(&(key.0 + tx, key.1 + ty)) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                for ay in row.clone() {
+                    let cy = (ay as i64 - 1).rem_euclid(TILE);
+                    for ax in columns.clone() {
+                        let cx = (ax as i64 - 1).rem_euclid(TILE);
+                        area[ay][ax] = tile[(cy * TILE + cx) as usize];
+                    }
+                }
+            }
+        }
+
+        let mut next = [0; (TILE * TILE) as usize];
+        for (index, cell) in next.iter_mut().enumerate() {
+            let (x, y) = (index % TILE as usize + 1, index / TILE as usize + 1);
+            let block = area[y - 1..=y + 1]
+                .iter()
+                .flat_map(|row| &row[x - 1..=x + 1]);
+            *cell = self.rule.next_state_in_block(block);
+        }
+        next
+    }
+}
+
+impl Default for Sparse {
+    fn default() -> Self {
+        Sparse::new(Rule::CONWAY)
+    }
+}
+
+impl Engine for Sparse {
+    fn name(&self) -> &'static str {
+        "sparse"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        self.tiles
+            .values()
+            .map(|tile| tile.iter().filter(|&&state| state != 0).count() as u64)
+            .sum()
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        let (key, index) = Sparse::split(x, y);
+        self.tiles.get(&key).map_or(0, |tile| tile[index])
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        let (key, index) = Sparse::split(x, y);
+        if state != 0 {
+            self.tiles.entry(key).or_insert([0; (TILE * TILE) as usize])[index] = state;
+        } else if let Some(tile) = self.tiles.get_mut(&key) {
+            tile[index] = 0;
+            if tile.iter().all(|&state| state == 0) {
+                self.tiles.remove(&key);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        let mut cells = Vec::new();
+        for (&(tx, ty), tile) in &self.tiles {
+            for (index, &state) in tile.iter().enumerate() {
+                if state != 0 {
+                    let (x, y) = (index as i64 % TILE, index as i64 / TILE);
+                    cells.push((tx * TILE + x, ty * TILE + y, state));
+                }
+            }
+        }
+        cells
+    }
+
+    fn step(&mut self) {
+        let mut candidates: Vec<(i64, i64)> = self
+            .tiles
+            .keys()
+            .flat_map(|&(tx, ty)| {
+                (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (tx + dx, ty + dy)))
+            })
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let tiles = candidates
+            .into_iter()
+            .map(|key| (key, self.next_tile(key)))
+            .filter(|(_, tile)| tile.iter().any(|&state| state != 0))
+            .collect();
+        self.tiles = tiles;
+        self.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    fn sorted_cells(engine: &dyn Engine) -> Vec<(i64, i64, u8)> {
+        let mut cells = engine.live_cells();
+        cells.sort_unstable();
+        cells
+    }
+
+    #[test]
+    fn gliders_travel_without_leaving_tiles_behind() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut sparse = Sparse::default();
+        for &(x, y) in &glider {
+            sparse.set_cell(x, y, 1);
+        }
+        for _ in 0..400 {
+            sparse.step();
+            assert!(sparse.tile_count() <= 4);
+        }
+        let mut expected: Vec<_> = glider.iter().map(|&(x, y)| (x + 100, y + 100, 1)).collect();
+        expected.sort_unstable();
+        assert_eq!(sorted_cells(&sparse), expected);
+        assert_eq!(sparse.generation(), 400);
+    }
+
+    #[test]
+    fn matches_a_grid_across_tile_edges() {
+        // The R-pentomino straddles the origin, so it spreads over tiles on
+        // both sides of every axis.
+        let r_pentomino = [(0, -1), (1, -1), (-1, 0), (0, 0), (0, 1)];
+        let mut sparse = Sparse::default();
+        let mut grid = Grid::new(100, 100);
+        for &(x, y) in &r_pentomino {
+            sparse.set_cell(x, y, 1);
+            grid.set_cell(x + 50, y + 50, 1);
+        }
+        for _ in 0..60 {
+            sparse.step();
+            grid.step();
+            let shifted: Vec<_> = sorted_cells(&grid)
+                .into_iter()
+                .map(|(x, y, state)| (x - 50, y - 50, state))
+                .collect();
+            assert_eq!(sorted_cells(&sparse), shifted);
+        }
+    }
+
+    #[test]
+    fn clearing_cells_frees_their_tiles() {
+        let mut sparse = Sparse::default();
+        sparse.set_cell(-1, -1, 1);
+        sparse.set_cell(40, 3, 1);
+        assert_eq!(sparse.tile_count(), 2);
+        assert_eq!(sparse.cell(-1, -1), 1);
+        sparse.set_cell(-1, -1, 0);
+        assert_eq!(sparse.tile_count(), 1);
+        assert_eq!(sparse.population(), 1);
+        sparse.step();
+        assert_eq!(sparse.tile_count(), 0);
+    }
+}
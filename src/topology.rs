@@ -0,0 +1,380 @@
+//! Finite universes with Golly's bounded-grid suffixes: `:P` planes, `:T`
+//! tori, `:K` Klein bottles, `:C` cross-surfaces and `:S` spheres.
+
+use std::{fmt, str::FromStr};
+
+use crate::rule::ParseRuleError;
+
+/// One of the two axes of the grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// How the edges of a finite universe are joined.
+///
+/// A width or height of 0 leaves the universe unbounded along that axis;
+/// only planes and tori allow it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Topology {
+    /// `:Pw,h`: a plane whose cells beyond the edges are always dead.
+    Plane { width: u32, height: u32 },
+    /// `:Tw,h`: opposite edges are joined. `:T20+3,10` moves cells 3
+    /// columns right as they cross the bottom edge, `:T20,10+1` moves them
+    /// 1 row down as they cross the right edge.
+    Torus {
+        width: u32,
+        height: u32,
+        shift: Option<(Axis, i32)>,
+    },
+    /// `:Kw*,h` or `:Kw,h*`: like a torus, but the starred pair of edges is
+    /// joined with a twist. `Axis::Horizontal` twists the top and bottom
+    /// edges, the default when no star is given.
+    KleinBottle {
+        width: u32,
+        height: u32,
+        twisted: Axis,
+    },
+    /// `:Cw,h`: both pairs of opposite edges are joined with a twist.
+    CrossSurface { width: u32, height: u32 },
+    /// `:Sn`: an `n` by `n` square whose top edge is joined to its left edge
+    /// and right edge to its bottom edge. Cells diagonally past a corner are
+    /// dead.
+    Sphere { size: u32 },
+}
+
+impl Topology {
+    /// Width and height, with unbounded axes reported as 0.
+    pub fn size(&self) -> (u32, u32) {
+        match *self {
+            Topology::Plane { width, height }
+            | Topology::Torus { width, height, .. }
+            | Topology::KleinBottle { width, height, .. }
+            | Topology::CrossSurface { width, height } => (width, height),
+            Topology::Sphere { size } => (size, size),
+        }
+    }
+
+    /// Finds the cell that `(x, y)`, a position possibly outside a `width`
+    /// by `height` board, refers to once edges are joined, or `None` if it
+    /// is off the edge of the universe. Unbounded axes of the topology are
+    /// cut off at the board's edge.
+    pub fn wrap(&self, x: i64, y: i64, width: i64, height: i64) -> Option<(i64, i64)> {
+        let inside = |x: i64, y: i64| x >= 0 && y >= 0 && x < width && y < height;
+        if inside(x, y) {
+            return Some((x, y));
+        }
+        let (bounded_x, bounded_y) = {
+            let (w, h) = self.size();
+            (w != 0, h != 0)
+        };
+        let (mut x, mut y) = (x, y);
+        match *self {
+            Topology::Plane { .. } => return None,
+            Topology::Torus { shift, .. } => {
+                if bounded_y {
+                    let laps = y.div_euclid(height);
+                    y = y.rem_euclid(height);
+                    if let Some((Axis::Horizontal, shift)) = shift {
+                        x += laps * i64::from(shift);
+                    }
+                }
+                if bounded_x {
+                    let laps = x.div_euclid(width);
+                    x = x.rem_euclid(width);
+                    if let Some((Axis::Vertical, shift)) = shift {
+                        y = (y + laps * i64::from(shift)).rem_euclid(height);
+                    }
+                }
+            }
+            Topology::KleinBottle { twisted, .. } => {
+                let laps = y.div_euclid(height);
+                y = y.rem_euclid(height);
+                if twisted == Axis::Horizontal && laps % 2 != 0 {
+                    x = width - 1 - x;
+                }
+                let laps = x.div_euclid(width);
+                x = x.rem_euclid(width);
+                if twisted == Axis::Vertical && laps % 2 != 0 {
+                    y = height - 1 - y;
+                }
+            }
+            Topology::CrossSurface { .. } => {
+                let laps = y.div_euclid(height);
+                y = y.rem_euclid(height);
+                if laps % 2 != 0 {
+                    x = width - 1 - x;
+                }
+                let laps = x.div_euclid(width);
+                x = x.rem_euclid(width);
+                if laps % 2 != 0 {
+                    y = height - 1 - y;
+                }
+            }
+            Topology::Sphere { .. } => {
+                let size = width;
+                let (x_inside, y_inside) = (x >= 0 && x < size, y >= 0 && y < size);
+                let (wx, wy) = match (x_inside, y_inside) {
+                    (false, true) if x < 0 => (y, -1 - x),
+                    (false, true) => (y, 2 * size - 1 - x),
+                    (true, false) if y < 0 => (-1 - y, x),
+                    (true, false) => (2 * size - 1 - y, x),
+                    _ => return None,
+                };
+                x = wx;
+                y = wy;
+            }
+        }
+        Some((x, y)).filter(|&(x, y)| inside(x, y))
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Topology::Plane { width, height } => write!(f, ":P{},{}", width, height),
+            Topology::Torus {
+                width,
+                height,
+                shift,
+            } => {
+                let shift_of = |axis| match shift {
+                    Some((shift_axis, amount)) if shift_axis == axis => format!("{:+}", amount),
+                    _ => String::new(),
+                };
+                write!(
+                    f,
+                    ":T{}{},{}{}",
+                    width,
+                    shift_of(Axis::Horizontal),
+                    height,
+                    shift_of(Axis::Vertical)
+                )
+            }
+            Topology::KleinBottle {
+                width,
+                height,
+                twisted: Axis::Horizontal,
+            } => write!(f, ":K{}*,{}", width, height),
+            Topology::KleinBottle {
+                width,
+                height,
+                twisted: Axis::Vertical,
+            } => write!(f, ":K{},{}*", width, height),
+            Topology::CrossSurface { width, height } => write!(f, ":C{},{}", width, height),
+            Topology::Sphere { size } => write!(f, ":S{}", size),
+        }
+    }
+}
+
+/// One dimension of a bounded-grid suffix, such as `20`, `20+3` or `20*`.
+#[derive(Default)]
+struct Dimension {
+    size: u32,
+    shift: Option<i32>,
+    twisted: bool,
+}
+
+impl FromStr for Dimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, twisted) = match s.strip_suffix('*') {
+            Some(rest) => (rest, true),
+            None => (s, false),
+        };
+        let (size, shift) = match s.find(['+', '-']) {
+            Some(sign) => (&s[..sign], Some(&s[sign..])),
+            None => (s, None),
+        };
+        let size = size
+            .parse()
+            .map_err(|_| format!("invalid size '{}'", size))?;
+        let shift = shift
+            .map(|shift| {
+                shift
+                    .parse()
+                    .map_err(|_| format!("invalid shift '{}'", shift))
+            })
+            .transpose()?;
+        Ok(Dimension {
+            size,
+            shift,
+            twisted,
+        })
+    }
+}
+
+/// Parses the part of a rulestring after the `:`, such as `T64,64`.
+impl FromStr for Topology {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| ParseRuleError::InvalidTopology(reason);
+        let mut chars = s.chars();
+        let kind = chars
+            .next()
+            .ok_or_else(|| invalid("missing grid type".to_string()))?
+            .to_ascii_uppercase();
+        let dimensions = chars.as_str();
+        let (width, height) = match dimensions.find(',') {
+            Some(comma) => (&dimensions[..comma], Some(&dimensions[comma + 1..])),
+            None => (dimensions, None),
+        };
+        let width: Dimension = width.parse().map_err(invalid)?;
+        let height: Dimension = match height {
+            Some(height) => height.parse().map_err(invalid)?,
+            None => Dimension {
+                size: width.size,
+                ..Dimension::default()
+            },
+        };
+
+        if kind != 'T' && (width.shift.is_some() || height.shift.is_some()) {
+            return Err(invalid("only a torus can be shifted".to_string()));
+        }
+        if kind != 'K' && (width.twisted || height.twisted) {
+            return Err(invalid("only a Klein bottle can be twisted".to_string()));
+        }
+        let (w, h) = (width.size, height.size);
+        if !matches!(kind, 'P' | 'T') && (w == 0 || h == 0) {
+            return Err(invalid(format!("a '{}' grid cannot be unbounded", kind)));
+        }
+        match kind {
+            'P' => Ok(Topology::Plane {
+                width: w,
+                height: h,
+            }),
+            'T' => {
+                let shift = match (width.shift, height.shift) {
+                    (Some(_), Some(_)) => {
+                        return Err(invalid("a torus can only be shifted along one axis".into()))
+                    }
+                    (Some(shift), None) => Some((Axis::Horizontal, shift)),
+                    (None, Some(shift)) => Some((Axis::Vertical, shift)),
+                    (None, None) => None,
+                };
+                Ok(Topology::Torus {
+                    width: w,
+                    height: h,
+                    shift,
+                })
+            }
+            'K' => {
+                let twisted = match (width.twisted, height.twisted) {
+                    (true, true) => {
+                        return Err(invalid(
+                            "a Klein bottle has one twisted pair of edges".into(),
+                        ))
+                    }
+                    (false, true) => Axis::Vertical,
+                    _ => Axis::Horizontal,
+                };
+                Ok(Topology::KleinBottle {
+                    width: w,
+                    height: h,
+                    twisted,
+                })
+            }
+            'C' => Ok(Topology::CrossSurface {
+                width: w,
+                height: h,
+            }),
+            'S' if w == h => Ok(Topology::Sphere { size: w }),
+            'S' => Err(invalid("a sphere must be square".to_string())),
+            _ => Err(invalid(format!("unknown grid type '{}'", kind))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grid::Grid, rule::Rule};
+
+    fn parse(s: &str) -> Topology {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn suffixes_round_trip() {
+        for s in &[
+            "P30,20", "T64,64", "T20+3,10", "T20,10-1", "T0,40", "K10*,8", "K10,8*", "C6,6", "S12",
+        ] {
+            assert_eq!(parse(s).to_string(), format!(":{}", s));
+        }
+        assert_eq!(parse("t64").to_string(), ":T64,64");
+        assert_eq!(parse("K10,8").to_string(), ":K10*,8");
+    }
+
+    #[test]
+    fn rejects_malformed_suffixes() {
+        for s in &[
+            "", "P10+1,5", "T10*,5", "T5+1,5+1", "K5*,5*", "C0,5", "S5,6", "X5,5", "Tab,5",
+        ] {
+            assert!(s.parse::<Topology>().is_err(), "{:?} should not parse", s);
+        }
+    }
+
+    #[test]
+    fn planes_have_dead_edges() {
+        let plane = parse("P10,8");
+        assert_eq!(plane.wrap(3, 4, 10, 8), Some((3, 4)));
+        assert_eq!(plane.wrap(-1, 0, 10, 8), None);
+        assert_eq!(plane.wrap(0, 8, 10, 8), None);
+    }
+
+    #[test]
+    fn tori_join_opposite_edges() {
+        let torus = parse("T10,8");
+        assert_eq!(torus.wrap(-1, -1, 10, 8), Some((9, 7)));
+        assert_eq!(torus.wrap(10, 8, 10, 8), Some((0, 0)));
+        assert_eq!(parse("T10+3,8").wrap(0, 8, 10, 8), Some((3, 0)));
+        assert_eq!(parse("T10,8-1").wrap(10, 2, 10, 8), Some((0, 1)));
+        // An unbounded axis is cut off at the board's edge.
+        assert_eq!(parse("T0,8").wrap(-1, -1, 10, 8), None);
+        assert_eq!(parse("T0,8").wrap(4, -1, 10, 8), Some((4, 7)));
+    }
+
+    #[test]
+    fn twisted_edges_mirror_the_other_axis() {
+        let klein = parse("K10*,8");
+        assert_eq!(klein.wrap(2, -1, 10, 8), Some((7, 7)));
+        assert_eq!(klein.wrap(-1, 2, 10, 8), Some((9, 2)));
+        let klein = parse("K10,8*");
+        assert_eq!(klein.wrap(2, -1, 10, 8), Some((2, 7)));
+        assert_eq!(klein.wrap(-1, 2, 10, 8), Some((9, 5)));
+        let cross = parse("C10,8");
+        assert_eq!(cross.wrap(2, -1, 10, 8), Some((7, 7)));
+        assert_eq!(cross.wrap(-1, 2, 10, 8), Some((9, 5)));
+    }
+
+    #[test]
+    fn spheres_join_adjacent_edges() {
+        let sphere = parse("S10");
+        assert_eq!(sphere.wrap(-1, 3, 10, 10), Some((3, 0)));
+        assert_eq!(sphere.wrap(3, -1, 10, 10), Some((0, 3)));
+        assert_eq!(sphere.wrap(10, 4, 10, 10), Some((4, 9)));
+        assert_eq!(sphere.wrap(4, 10, 10, 10), Some((9, 4)));
+        assert_eq!(sphere.wrap(-1, -1, 10, 10), None);
+    }
+
+    #[test]
+    fn gliders_circle_a_torus() {
+        let rule: Rule = "B3/S23:T8,8".parse().unwrap();
+        let mut grid = Grid::with_rule(20, 20, rule);
+        assert_eq!((grid.width(), grid.height()), (8, 8));
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for &(x, y) in &glider {
+            grid.set(x, y, true);
+        }
+        for _ in 0..32 {
+            grid.step();
+            assert_eq!(grid.population(), 5);
+        }
+        for &(x, y) in &glider {
+            assert!(grid.get(x, y));
+        }
+    }
+}
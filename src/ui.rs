@@ -0,0 +1,417 @@
+use rs_game_of_life::{
+    period::Detector,
+    rule::{points_up, Lattice},
+    volume::Axis,
+    Blocks, Census, Continuous, Engine, Grid, Rule, Volume,
+};
+use tui::{
+    backend::Backend,
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Widget},
+    Frame,
+};
+
+use crate::app::App;
+
+/// Terminal columns used to draw one cell, so cells come out roughly square.
+pub const CELL_WIDTH: u16 = 2;
+
+/// Block characters for a cell that is partly filled, from faintest to
+/// fullest: a projected line of a volume or a cell of a continuous rule.
+const SHADES: [&str; 4] = ["░░", "▒▒", "▓▓", "██"];
+
+/// Colours the cells of continuous rules run between, from nearly 0 to 1.
+const CONTINUOUS_GRADIENT: [(u8, u8, u8); 2] = [(40, 60, 170), (255, 230, 60)];
+
+/// Colours dying cells fade through, from just dead to nearly gone.
+const DYING_COLORS: [Color; 5] = [
+    Color::LightRed,
+    Color::Red,
+    Color::Magenta,
+    Color::Blue,
+    Color::DarkGray,
+];
+
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let palette_height = if app.cursor.is_some() { 1 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(3),
+                Constraint::Length(palette_height),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+
+    let board_chunk = match &app.census {
+        Some((generation, census)) => {
+            let panel = census_panel(*generation, census);
+            let width = panel.0.min(chunks[0].width / 2);
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(width)].as_ref())
+                .split(chunks[0]);
+            f.render_widget(panel.1, columns[1]);
+            columns[0]
+        }
+        None => chunks[0],
+    };
+    let block = Block::default().borders(Borders::ALL).title("Game of Life");
+    let board_area = block.inner(board_chunk);
+    f.render_widget(block, board_chunk);
+    f.render_widget(
+        Board {
+            engine: app.engine.as_ref(),
+            viewport: app.viewport,
+            cursor: app.cursor,
+            projection: app.projection,
+        },
+        board_area,
+    );
+    if app.cursor.is_some() {
+        f.render_widget(palette(app), chunks[1]);
+    }
+    f.render_widget(status_bar(app), chunks[2]);
+}
+
+/// Size in cells of the largest board that fits a terminal of `size`,
+/// leaving room for the palette shown in edit mode.
+pub fn board_size(size: Rect) -> (usize, usize) {
+    let width = size.width.saturating_sub(2) / CELL_WIDTH;
+    let height = size.height.saturating_sub(4);
+    (width.max(1) as usize, height.max(1) as usize)
+}
+
+/// The tally of a census taken at `generation`, with the width it needs
+/// to show every line whole.
+fn census_panel(generation: u64, census: &Census) -> (u16, Paragraph<'static>) {
+    let title = format!("Census of gen {}", generation);
+    let text = census.to_string();
+    let width = text
+        .lines()
+        .map(|line| line.chars().count())
+        .chain(std::iter::once(title.len()))
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Spans> = text
+        .lines()
+        .map(|line| Spans::from(line.to_string()))
+        .collect();
+    let panel = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    (width as u16 + 2, panel)
+}
+
+fn status_bar(app: &App) -> Paragraph<'_> {
+    let state = match (app.paused, app.backwards) {
+        (true, _) => "paused",
+        (false, false) => "running",
+        (false, true) => "running backwards",
+    };
+    let mut spans = vec![Span::raw(format!(
+        " {} | {} | gen {} | pop {} | at {},{} | {} | step 2^{} every {} ms ",
+        app.engine.name(),
+        app.engine.rule(),
+        app.engine.generation(),
+        app.engine.population(),
+        app.viewport.0,
+        app.viewport.1,
+        state,
+        app.step_exponent,
+        app.tick_rate.as_millis()
+    ))];
+    if let Some(grid) = app.engine.as_any().downcast_ref::<Grid>() {
+        spans.push(Span::raw(format!(
+            "| skipped {}/{} tiles ",
+            grid.skipped_tiles(),
+            grid.tile_count()
+        )));
+        if !grid.update().is_synchronous() {
+            spans.push(Span::raw(format!(
+                "| update {} seed {} ",
+                grid.update(),
+                app.seed
+            )));
+        }
+    }
+    if let Some(blocks) = app.engine.as_any().downcast_ref::<Blocks>() {
+        spans.push(Span::raw(if blocks.is_reversible() {
+            "| reversible "
+        } else {
+            "| irreversible "
+        }));
+    }
+    if let Some(periodicity) = app.detector.as_ref().and_then(Detector::periodicity) {
+        spans.push(Span::styled(
+            format!("| {} ", periodicity),
+            Style::default().fg(Color::Green),
+        ));
+    }
+    if let Some(field) = app.engine.as_any().downcast_ref::<Continuous>() {
+        spans.push(Span::raw(format!("| mass {:.1} ", field.mass())));
+    }
+    if let Some(volume) = app.engine.as_any().downcast_ref::<Volume>() {
+        spans.push(Span::raw(match app.projection {
+            None => format!("| layer {}/{} ", volume.layer(), volume.depth()),
+            Some(axis) => format!("| projection along {} ", axis_name(axis)),
+        }));
+    }
+    match &app.message {
+        Some(message) => spans.push(Span::styled(
+            format!("| {}", message),
+            Style::default().fg(Color::Cyan),
+        )),
+        None => spans.push(Span::styled(
+            "| q quit  space pause  n step  +/- speed  [/] step size  arrows pan  f find  r soup  c clear  s save  a census  e edit  b backwards  </> layer  v view  t translations",
+            Style::default().fg(Color::DarkGray),
+        )),
+    }
+    Paragraph::new(Spans::from(spans))
+}
+
+/// The states of the rule, each in its colour, with the one edit mode
+/// paints with highlighted.
+fn palette(app: &App) -> Paragraph<'_> {
+    let rule = app.engine.rule();
+    let mut spans = vec![Span::styled(
+        " edit: arrows move  enter paint  backspace erase  tab/0-9 pick | ",
+        Style::default().fg(Color::DarkGray),
+    )];
+    for state in 0..rule.states().min(256) {
+        let state = state as u8;
+        let mut style = Style::default().fg(palette_color(&rule, state));
+        if state == app.brush {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        spans.push(Span::styled(
+            format!("{} {} ", state, rule.state_name(state)),
+            style,
+        ));
+        spans.push(Span::raw(" "));
+    }
+    Paragraph::new(Spans::from(spans))
+}
+
+struct Board<'a> {
+    engine: &'a dyn Engine,
+    viewport: (i64, i64),
+    /// The cell edit mode would paint, if editing.
+    cursor: Option<(i64, i64)>,
+    /// For a volume, the axis to project it along instead of showing the
+    /// current layer.
+    projection: Option<Axis>,
+}
+
+impl<'a> Widget for Board<'a> {
+    /// Draws square and triangular cells in a grid. Hexagonal cells are
+    /// laid out like bricks, each row shifted half a cell right of the one
+    /// above and the columns sheared to match, so that every cell touches
+    /// its six neighbours on screen.
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if let (Some(volume), Some(axis)) = (
+            self.engine.as_any().downcast_ref::<Volume>(),
+            self.projection,
+        ) {
+            return render_projection(volume, axis, self.viewport, area, buf);
+        }
+        let rule = self.engine.rule();
+        let lattice = rule.lattice();
+        for row in 0..area.height {
+            let y = self.viewport.1 + i64::from(row);
+            let (shift, offset) = match lattice {
+                Lattice::Hexagonal => ((y + 1).div_euclid(2), y.rem_euclid(2) as u16),
+                _ => (0, 0),
+            };
+            for col in 0..area.width.saturating_sub(offset) / CELL_WIDTH {
+                let x = self.viewport.0 + i64::from(col) + shift;
+                let state = self.engine.cell(x, y);
+                let is_cursor = self.cursor == Some((x, y));
+                if state == 0 && !is_cursor {
+                    continue;
+                }
+                let symbol = match lattice {
+                    _ if state == 0 => "[]",
+                    _ if rule.lenia().is_some() => shade(usize::from(state), 255),
+                    Lattice::Triangular if points_up(x, y) => "◢◣",
+                    Lattice::Triangular => "◥◤",
+                    _ => "██",
+                };
+                let mut style = Style::default().fg(state_color(&rule, state));
+                if is_cursor && state != 0 {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                buf.set_string(
+                    area.x + col * CELL_WIDTH + offset,
+                    area.y + row,
+                    symbol,
+                    style,
+                );
+            }
+        }
+    }
+}
+
+/// Draws how many live cells lie behind each cell of the plane seen along
+/// `axis`, shaded by the fraction of the line through the volume they fill.
+fn render_projection(
+    volume: &Volume,
+    axis: Axis,
+    viewport: (i64, i64),
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let extent = volume.extent(axis).max(1);
+    let style = Style::default().fg(Color::Yellow);
+    for row in 0..area.height {
+        for col in 0..area.width / CELL_WIDTH {
+            let u = viewport.0 + i64::from(col);
+            let v = viewport.1 + i64::from(row);
+            let density = volume.density(axis, u, v);
+            if density == 0 {
+                continue;
+            }
+            buf.set_string(
+                area.x + col * CELL_WIDTH,
+                area.y + row,
+                shade(density, extent),
+                style,
+            );
+        }
+    }
+}
+
+fn axis_name(axis: Axis) -> &'static str {
+    match axis {
+        Axis::X => "x",
+        Axis::Y => "y",
+        Axis::Z => "z",
+    }
+}
+
+/// The shade for a cell filled `amount` out of `full`, which must be more
+/// than 0.
+fn shade(amount: usize, full: usize) -> &'static str {
+    SHADES[((amount * SHADES.len()).saturating_sub(1) / full).min(SHADES.len() - 1)]
+}
+
+/// Colour of `state` in the palette, which unlike the board shows empty
+/// cells.
+fn palette_color(rule: &Rule, state: u8) -> Color {
+    match rule.color(state) {
+        None if state == 0 => Color::Gray,
+        _ => state_color(rule, state),
+    }
+}
+
+/// Colour of a cell in `state` under `rule`: the colour the rule's file
+/// gives it if there is one, a blend along [`CONTINUOUS_GRADIENT`] for
+/// continuous rules, and otherwise yellow for live cells and an even spread
+/// over [`DYING_COLORS`] for dying ones.
+fn state_color(rule: &Rule, state: u8) -> Color {
+    if let Some((red, green, blue)) = rule.color(state) {
+        return Color::Rgb(red, green, blue);
+    }
+    if rule.lenia().is_some() {
+        let [low, high] = CONTINUOUS_GRADIENT;
+        let blend = |low: u8, high: u8| {
+            let (low, high) = (u32::from(low), u32::from(high));
+            ((low * (255 - u32::from(state)) + high * u32::from(state)) / 255) as u8
+        };
+        return Color::Rgb(
+            blend(low.0, high.0),
+            blend(low.1, high.1),
+            blend(low.2, high.2),
+        );
+    }
+    let dying = rule.states().saturating_sub(2);
+    if state <= 1 || dying == 0 {
+        return Color::Yellow;
+    }
+    let index = usize::from(u16::from(state) - 2) * DYING_COLORS.len() / usize::from(dying);
+    DYING_COLORS[index.min(DYING_COLORS.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::EngineKind;
+    use rs_game_of_life::Pattern;
+    use tui::{backend::TestBackend, Terminal};
+
+    /// The text of every row of the screen `app` is drawn on.
+    fn screen(app: &App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn board_leaves_room_for_borders_and_status() {
+        assert_eq!(board_size(Rect::new(0, 0, 42, 24)), (20, 20));
+        assert_eq!(board_size(Rect::new(0, 0, 1, 1)), (1, 1));
+    }
+
+    #[test]
+    fn draws_live_cells_and_status() {
+        let pattern = Pattern {
+            cells: vec![(0, 0, 1), (1, 0, 1), (2, 0, 1)],
+            ..Pattern::default()
+        };
+        let app = App::new(EngineKind::Grid, Rule::CONWAY, Some(pattern), (3, 1), 0);
+        let rows = screen(&app, 80, 5);
+        assert!(rows[1].contains("██████"), "{:?}", rows);
+        assert!(rows[4].contains("gen 0 | pop 3"), "{:?}", rows);
+    }
+
+    #[test]
+    fn dying_states_get_their_own_colours() {
+        let star_wars: Rule = "345/2/4".parse().unwrap();
+        assert_eq!(state_color(&star_wars, 1), Color::Yellow);
+        assert_eq!(state_color(&star_wars, 2), DYING_COLORS[0]);
+        assert_ne!(state_color(&star_wars, 3), state_color(&star_wars, 2));
+        assert_eq!(state_color(&Rule::CONWAY, 1), Color::Yellow);
+    }
+
+    #[test]
+    fn edit_mode_shows_the_palette() {
+        let rule: Rule = "WireWorld".parse().unwrap();
+        let kind = EngineKind::default_for(&rule);
+        let mut app = App::new(kind, rule, None, (8, 8), 0);
+        let rows = screen(&app, 120, 12);
+        assert!(rows[10].contains("1 electron head"), "{:?}", rows);
+        assert!(rows[10].contains("3 copper"), "{:?}", rows);
+        app.cursor = None;
+        assert!(!screen(&app, 120, 12).concat().contains("copper"));
+    }
+
+    #[test]
+    fn census_is_shown_beside_the_board() {
+        let pattern = Pattern {
+            cells: vec![(0, 0, 1), (1, 0, 1), (2, 0, 1)],
+            ..Pattern::default()
+        };
+        let mut app = App::new(EngineKind::Sparse, Rule::CONWAY, Some(pattern), (8, 8), 0);
+        let cells = [(0, 0), (1, 0), (2, 0)];
+        app.census = Some((0, Census::of_cells(&Rule::CONWAY, &cells)));
+        let rows = screen(&app, 80, 8);
+        assert!(rows[0].contains("Census of gen 0"), "{:?}", rows);
+        assert!(
+            rows[2].ends_with("│xp2_7         1  blinker│"),
+            "{:?}",
+            rows
+        );
+        assert!(rows[3].contains("│1 objects"), "{:?}", rows);
+    }
+}
@@ -0,0 +1,157 @@
+//! Update schemes: in what order, and how reliably, the cells of a
+//! universe take the state their rule gives them.
+//!
+//! They are written `sync`, `sequential`, `async:0.5` and `prob:0.9,0.95`.
+
+use std::{error::Error, fmt, str::FromStr};
+
+/// How a step applies the rule to the cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Update {
+    /// Every cell moves to its next state at once, as in ordinary Life.
+    #[default]
+    Synchronous,
+    /// The cells are updated one at a time in a fresh random order each
+    /// generation, every cell seeing the states of the cells updated
+    /// before it.
+    RandomSequential,
+    /// α-asynchronous updating: each cell independently moves to its next
+    /// state with probability α and otherwise keeps its state.
+    Asynchronous(f64),
+    /// The rule's births happen with probability `birth` and its survivals
+    /// with probability `survival`; a live cell that fails to survive dies
+    /// as if the rule had not let it.
+    Probabilistic { birth: f64, survival: f64 },
+}
+
+impl Update {
+    /// Whether every cell is updated at once and without chance.
+    pub fn is_synchronous(&self) -> bool {
+        *self == Update::Synchronous
+    }
+}
+
+impl fmt::Display for Update {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Update::Synchronous => write!(f, "sync"),
+            Update::RandomSequential => write!(f, "sequential"),
+            Update::Asynchronous(alpha) => write!(f, "async:{}", alpha),
+            Update::Probabilistic { birth, survival } => {
+                write!(f, "prob:{},{}", birth, survival)
+            }
+        }
+    }
+}
+
+impl FromStr for Update {
+    type Err = ParseUpdateError;
+
+    /// Parses a scheme by name, followed for `async` by α and for `prob` by
+    /// the birth and survival probabilities, or one probability for both.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, parameters) = match s.split_once(':') {
+            Some((name, parameters)) => (name, Some(parameters)),
+            None => (s, None),
+        };
+        let probability = |value: &str| match value.trim().parse::<f64>() {
+            Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+            _ => Err(ParseUpdateError::InvalidProbability(
+                value.trim().to_string(),
+            )),
+        };
+        match (name.to_ascii_lowercase().as_str(), parameters) {
+            ("sync" | "synchronous", None) => Ok(Update::Synchronous),
+            ("sequential" | "random-sequential", None) => Ok(Update::RandomSequential),
+            ("async" | "asynchronous", Some(alpha)) => {
+                Ok(Update::Asynchronous(probability(alpha)?))
+            }
+            ("prob" | "probabilistic", Some(parameters)) => {
+                let (birth, survival) = parameters
+                    .split_once(',')
+                    .unwrap_or((parameters, parameters));
+                Ok(Update::Probabilistic {
+                    birth: probability(birth)?,
+                    survival: probability(survival)?,
+                })
+            }
+            _ => Err(ParseUpdateError::Unknown(s.to_string())),
+        }
+    }
+}
+
+/// Reasons an update scheme can be rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseUpdateError {
+    /// No scheme has this name, or it was given the wrong parameters.
+    Unknown(String),
+    /// A probability that is not a number between 0 and 1.
+    InvalidProbability(String),
+}
+
+impl fmt::Display for ParseUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseUpdateError::Unknown(scheme) => write!(
+                f,
+                "unknown update scheme '{}', expected sync, sequential, async:ALPHA or prob:BIRTH,SURVIVAL",
+                scheme
+            ),
+            ParseUpdateError::InvalidProbability(value) => {
+                write!(f, "probability '{}' is not between 0 and 1", value)
+            }
+        }
+    }
+}
+
+impl Error for ParseUpdateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "sync",
+            "sequential",
+            "async:0.5",
+            "prob:0.9,0.95",
+            "prob:1,0",
+        ] {
+            assert_eq!(s.parse::<Update>().unwrap().to_string(), s);
+        }
+        assert_eq!("Synchronous".parse(), Ok(Update::Synchronous));
+        assert_eq!("random-sequential".parse(), Ok(Update::RandomSequential));
+        assert_eq!(
+            " asynchronous:0.25 ".parse(),
+            Ok(Update::Asynchronous(0.25))
+        );
+        assert_eq!(
+            "prob:0.8".parse(),
+            Ok(Update::Probabilistic {
+                birth: 0.8,
+                survival: 0.8
+            })
+        );
+        assert!(Update::default().is_synchronous());
+        assert!(!Update::Asynchronous(1.0).is_synchronous());
+    }
+
+    #[test]
+    fn rejects_bad_schemes() {
+        let unknown = |s: &str| Err(ParseUpdateError::Unknown(s.to_string()));
+        assert_eq!("parallel".parse::<Update>(), unknown("parallel"));
+        assert_eq!("async".parse::<Update>(), unknown("async"));
+        assert_eq!("sync:0.5".parse::<Update>(), unknown("sync:0.5"));
+        assert_eq!(
+            "async:1.5".parse::<Update>(),
+            Err(ParseUpdateError::InvalidProbability("1.5".to_string()))
+        );
+        assert_eq!(
+            "prob:0.5, x".parse::<Update>(),
+            Err(ParseUpdateError::InvalidProbability("x".to_string()))
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! A box of cubic cells stepped with a 3D rule, seen through the 2D
+//! [`Engine`] interface one layer at a time.
+
+use std::any::Any;
+
+use crate::engine::Engine;
+use crate::rule::{CubicNeighbourhood, Rule};
+use crate::topology::Topology;
+
+/// One of the three axes of a volume. Layers are stacked along `Z`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// A `width` by `height` by `depth` box of cells. Everything outside it is
+/// permanently dead, unless the rule's topology is a torus, in which case
+/// opposite faces are joined along all three axes.
+///
+/// Through [`Engine`], cells are read and written on the current layer,
+/// the plane at one `z`, although the population counts the whole volume.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Volume {
+    width: usize,
+    height: usize,
+    depth: usize,
+    /// Cell states indexed by `(z * height + y) * width + x`.
+    cells: Vec<u8>,
+    rule: Rule,
+    neighbourhood: CubicNeighbourhood,
+    wrap: bool,
+    layer: usize,
+    generation: u64,
+}
+
+impl Volume {
+    /// Creates an empty box of `width` by `height` by `depth` cells running
+    /// `rule`. When the rule has a torus suffix, the box takes the width
+    /// and height it gives instead, except along unbounded axes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rule` is not a 3D rule or runs on a topology other than
+    /// an unshifted torus.
+    pub fn new(width: usize, height: usize, depth: usize, rule: Rule) -> Self {
+        let neighbourhood = rule
+            .cubic()
+            .expect("a volume needs a 3D rule")
+            .neighbourhood();
+        let (width, height, wrap) = match rule.topology() {
+            None => (width, height, false),
+            Some(Topology::Torus {
+                width: w,
+                height: h,
+                shift: None,
+            }) => (
+                if w == 0 { width } else { w as usize },
+                if h == 0 { height } else { h as usize },
+                true,
+            ),
+            Some(topology) => panic!("a volume cannot run on {}", topology),
+        };
+        Volume {
+            width,
+            height,
+            depth,
+            cells: vec![0; width * height * depth],
+            rule,
+            neighbourhood,
+            wrap,
+            layer: depth / 2,
+            generation: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The `z` of the layer [`Engine`] methods work on.
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    /// Moves to the layer at `z`, kept within the box.
+    pub fn set_layer(&mut self, z: usize) {
+        self.layer = z.min(self.depth.saturating_sub(1));
+    }
+
+    /// Length of the box along `axis`.
+    pub fn extent(&self, axis: Axis) -> usize {
+        match axis {
+            Axis::X => self.width,
+            Axis::Y => self.height,
+            Axis::Z => self.depth,
+        }
+    }
+
+    fn index(&self, x: i64, y: i64, z: i64) -> Option<usize> {
+        let inside = |value: i64, size: usize| (0..size as i64).contains(&value);
+        if inside(x, self.width) && inside(y, self.height) && inside(z, self.depth) {
+            Some(((z as usize * self.height) + y as usize) * self.width + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// State of the cell at `(x, y, z)`; 0 outside the box.
+    pub fn cell_at(&self, x: i64, y: i64, z: i64) -> u8 {
+        self.index(x, y, z).map_or(0, |i| self.cells[i])
+    }
+
+    /// Sets the cell at `(x, y, z)`, if it is inside the box.
+    pub fn set_cell_at(&mut self, x: i64, y: i64, z: i64, state: u8) {
+        if let Some(i) = self.index(x, y, z) {
+            self.cells[i] = state;
+        }
+    }
+
+    /// Number of live cells on the line along `axis` through the point
+    /// `(u, v)` of the plane seen when looking along it: `(x, y)` along
+    /// `Z`, `(x, z)` along `Y` and `(z, y)` along `X`.
+    pub fn density(&self, axis: Axis, u: i64, v: i64) -> usize {
+        (0..self.extent(axis) as i64)
+            .filter(|&w| {
+                let (x, y, z) = match axis {
+                    Axis::Z => (u, v, w),
+                    Axis::Y => (u, w, v),
+                    Axis::X => (w, v, u),
+                };
+                self.cell_at(x, y, z) != 0
+            })
+            .count()
+    }
+
+    /// For each cell, the number of cells in state 1 among it and its two
+    /// neighbours along the axis whose cells are `stride` apart in `cells`
+    /// and `size` long. Around a torus less than 3 cells long, the cells on
+    /// either side are the same one, or the cell itself, and count once.
+    fn sum_along(&self, cells: &[u8], stride: usize, size: usize) -> Vec<u8> {
+        let offsets: &[i64] = match size {
+            1 if self.wrap => &[0],
+            2 if self.wrap => &[0, 1],
+            _ => &[-1, 0, 1],
+        };
+        let mut sums = vec![0; cells.len()];
+        for (i, sum) in sums.iter_mut().enumerate() {
+            let position = i / stride % size;
+            let start = i - position * stride;
+            let at = |offset: i64| {
+                let p = position as i64 + offset;
+                let p = if self.wrap {
+                    p.rem_euclid(size as i64)
+                } else if (0..size as i64).contains(&p) {
+                    p
+                } else {
+                    return 0;
+                };
+                cells[start + p as usize * stride]
+            };
+            *sum = offsets.iter().map(|&offset| at(offset)).sum();
+        }
+        sums
+    }
+
+    /// Number of neighbours in state 1 of every cell.
+    fn neighbour_counts(&self) -> Vec<u8> {
+        let alive: Vec<u8> = self
+            .cells
+            .iter()
+            .map(|&state| u8::from(state == 1))
+            .collect();
+        let (width, plane) = (self.width, self.width * self.height);
+        match self.neighbourhood {
+            CubicNeighbourhood::Moore => {
+                let rows = self.sum_along(&alive, 1, self.width);
+                let planes = self.sum_along(&rows, width, self.height);
+                let cubes = self.sum_along(&planes, plane, self.depth);
+                cubes
+                    .iter()
+                    .zip(&alive)
+                    .map(|(sum, own)| sum - own)
+                    .collect()
+            }
+            CubicNeighbourhood::VonNeumann => {
+                let axes = [(1, self.width), (width, self.height), (plane, self.depth)];
+                let mut counts = vec![0; alive.len()];
+                for &(stride, size) in &axes {
+                    let sums = self.sum_along(&alive, stride, size);
+                    for ((count, sum), own) in counts.iter_mut().zip(&sums).zip(&alive) {
+                        *count += sum - own;
+                    }
+                }
+                counts
+            }
+        }
+    }
+}
+
+impl Engine for Volume {
+    fn name(&self) -> &'static str {
+        "volume"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> u64 {
+        self.cells.iter().filter(|&&state| state != 0).count() as u64
+    }
+
+    fn cell(&self, x: i64, y: i64) -> u8 {
+        self.cell_at(x, y, self.layer as i64)
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        self.set_cell_at(x, y, self.layer as i64, state);
+    }
+
+    fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|cell| *cell = 0);
+    }
+
+    /// The live cells of the current layer.
+    fn live_cells(&self) -> Vec<(i64, i64, u8)> {
+        let plane = self.width * self.height;
+        let start = self.layer * plane;
+        self.cells[start..start + plane]
+            .iter()
+            .enumerate()
+            .filter(|&(_, &state)| state != 0)
+            .map(|(i, &state)| ((i % self.width) as i64, (i / self.width) as i64, state))
+            .collect()
+    }
+
+    fn step(&mut self) {
+        let counts = self.neighbour_counts();
+        for (cell, &count) in self.cells.iter_mut().zip(&counts) {
+            *cell = self.rule.next_state_with_count(*cell, u32::from(count));
+        }
+        self.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+    use std::collections::HashSet;
+
+    /// A box running `rule` with each cell alive with probability 1/3.
+    fn soup(width: usize, height: usize, depth: usize, rule: &str, seed: u64) -> Volume {
+        let mut volume = Volume::new(width, height, depth, rule.parse().unwrap());
+        let mut random = Random::new(seed);
+        for cell in &mut volume.cells {
+            *cell = u8::from(random.chance(1.0 / 3.0));
+        }
+        volume
+    }
+
+    /// The next generation of `volume` worked out cell by cell, counting
+    /// each distinct neighbour once however the faces are joined.
+    fn next_cells(volume: &Volume) -> Vec<u8> {
+        let (w, h, d) = (
+            volume.width as i64,
+            volume.height as i64,
+            volume.depth as i64,
+        );
+        let mut next = Vec::new();
+        for z in 0..d {
+            for y in 0..h {
+                for x in 0..w {
+                    let mut neighbours = HashSet::new();
+                    for dz in -1..=1i64 {
+                        for dy in -1..=1i64 {
+                            for dx in -1..=1i64 {
+                                let face = dx.abs() + dy.abs() + dz.abs() == 1;
+                                if volume.neighbourhood == CubicNeighbourhood::VonNeumann && !face {
+                                    continue;
+                                }
+                                let (mut nx, mut ny, mut nz) = (x + dx, y + dy, z + dz);
+                                if volume.wrap {
+                                    nx = nx.rem_euclid(w);
+                                    ny = ny.rem_euclid(h);
+                                    nz = nz.rem_euclid(d);
+                                }
+                                if (nx, ny, nz) != (x, y, z) {
+                                    neighbours.insert((nx, ny, nz));
+                                }
+                            }
+                        }
+                    }
+                    let count = neighbours
+                        .into_iter()
+                        .filter(|&(x, y, z)| volume.cell_at(x, y, z) == 1)
+                        .count() as u32;
+                    let state = volume.cell_at(x, y, z);
+                    next.push(volume.rule.next_state_with_count(state, count));
+                }
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn single_cells_seed_their_neighbourhood() {
+        for (rule, born) in [("B1/S/3D", 26), ("B1/S/3D6", 6)] {
+            let mut volume = Volume::new(5, 5, 5, rule.parse().unwrap());
+            volume.set_cell_at(2, 2, 2, 1);
+            volume.step();
+            assert_eq!(volume.population(), born, "{}", rule);
+            assert_eq!(volume.cell_at(2, 2, 2), 0);
+        }
+    }
+
+    #[test]
+    fn matches_counting_cell_by_cell() {
+        for rule in ["B5/S45/3D", "B1/S1/3D6", "B4/S34/C5/3D"] {
+            let mut volume = soup(7, 6, 5, rule, 1);
+            for _ in 0..4 {
+                let expected = next_cells(&volume);
+                volume.step();
+                assert_eq!(volume.cells, expected, "{}", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn small_tori_count_each_neighbour_once() {
+        for size in 1..=3 {
+            for rule in ["B1/S/3D", "B1/S/3D6"] {
+                let rule = format!("{}:T{},{}", rule, size, size);
+                let mut volume = Volume::new(0, 0, size, rule.parse().unwrap());
+                volume.set_cell_at(0, 0, 0, 1);
+                let expected = next_cells(&volume);
+                volume.step();
+                assert_eq!(volume.cells, expected, "{}", rule);
+            }
+        }
+        // Every other cell of a 2x2x2 torus touches the live one, once.
+        let mut volume = Volume::new(0, 0, 2, "B1/S/3D:T2,2".parse().unwrap());
+        volume.set_cell_at(0, 0, 0, 1);
+        volume.step();
+        assert_eq!(volume.population(), 7);
+    }
+
+    #[test]
+    fn layers_and_projections() {
+        let mut volume = Volume::new(4, 3, 5, "B5/S45/3D".parse().unwrap());
+        assert_eq!(volume.layer(), 2);
+        volume.set_cell(1, 1, 1);
+        volume.set_layer(9);
+        assert_eq!(volume.layer(), 4);
+        volume.set_cell(1, 1, 1);
+        assert_eq!(volume.live_cells(), [(1, 1, 1)]);
+        assert_eq!(volume.density(Axis::Z, 1, 1), 2);
+        assert_eq!(volume.density(Axis::Y, 1, 4), 1);
+        assert_eq!(volume.density(Axis::X, 3, 1), 0);
+        assert_eq!(volume.population(), 2);
+        assert_eq!(volume.extent(Axis::Z), 5);
+    }
+}